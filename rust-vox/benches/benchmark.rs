@@ -1,21 +1,60 @@
+use std::sync::Arc;
 use criterion::{criterion_group, criterion_main, Criterion};
-use engine::engine::{terrain::PerlinGenerator, chunk::Chunk, geometry::meshing::{culling_mesher::CullingMesher, greedy_mesher::GreedyMesher}};
+use engine::engine::{terrain::{create_generator, DEFAULT_SEED}, chunk::{Chunk, MOORE_NEIGHBORHOOD_OFFSET}, chunk_manager::{ChunkArena, ChunkManageUnit},
+    geometry::{mesh::Mesh, voxel_vertex::VoxelVertex, meshing::{chunk_mesher::ChunkMesher, culling_mesher::CullingMesher, greedy_mesher::GreedyMesher, binary_mesher::BinaryGreedyMesher, surface_nets_mesher::SurfaceNetsMesher, voxel_fetcher::FetcherFactory}}};
+use glam::IVec2;
 
-fn benchmark_greedy_mesher(c: &mut Criterion)
+/// The chunk (0,0) and its Moore neighbors, ready to be meshed
+fn create_chunks() -> FetcherFactory
+{
+    let generator = create_generator("perlin", DEFAULT_SEED).unwrap();
+    let arena = Arc::new(ChunkArena::new(9));
+
+    let mut positions = [IVec2::ZERO; 9];
+    positions[1..].copy_from_slice(&MOORE_NEIGHBORHOOD_OFFSET);
+
+    let indices = positions.map(|pos|
+    {
+        let mut unit = ChunkManageUnit::default();
+        unit.chunk = Some(Chunk::new(pos, generator.as_ref()));
+        arena.try_insert(unit).ok().unwrap()
+    });
+
+    FetcherFactory::new(indices, arena)
+}
+
+fn bench_mesher<T: ChunkMesher>(c: &mut Criterion, name: &str)
 {
-    // let generator = Box::new(PerlinGenerator::default());
-    // let mut chunk = Chunk::new(0,0,0,generator.as_ref());
+    let factory = create_chunks();
 
-    // c.bench_function("greedy_mesher", |b| b.iter( || chunk.generate_mesh::<GreedyMesher>()));
+    c.bench_function(name, |b| b.iter(||
+    {
+        let mut mesh = Mesh::<VoxelVertex>::default();
+        let mut trans_faces = Vec::new();
+        T::generate_mesh(factory.get_fetcher().unwrap(), &mut mesh, &mut trans_faces);
+        mesh
+    }));
 }
 
 fn benchmark_culling_mesher(c: &mut Criterion)
 {
-    // let generator = Box::new(PerlinGenerator::default());
-    // let mut chunk = Chunk::new(0,0,0,generator.as_ref());
+    bench_mesher::<CullingMesher>(c, "culling_mesher");
+}
+
+fn benchmark_greedy_mesher(c: &mut Criterion)
+{
+    bench_mesher::<GreedyMesher>(c, "greedy_mesher");
+}
+
+fn benchmark_binary_greedy_mesher(c: &mut Criterion)
+{
+    bench_mesher::<BinaryGreedyMesher>(c, "binary_greedy_mesher");
+}
 
-    // c.bench_function("culling_mesher", |b| b.iter( || chunk.generate_mesh::<CullingMesher>()));
+fn benchmark_surface_nets_mesher(c: &mut Criterion)
+{
+    bench_mesher::<SurfaceNetsMesher>(c, "surface_nets_mesher");
 }
 
-criterion_group!(benches, benchmark_culling_mesher, benchmark_greedy_mesher);
-criterion_main!(benches);
\ No newline at end of file
+criterion_group!(benches, benchmark_culling_mesher, benchmark_greedy_mesher, benchmark_binary_greedy_mesher, benchmark_surface_nets_mesher);
+criterion_main!(benches);
@@ -2,11 +2,14 @@
 
 out vec4 color;
 
-in vec3 frag_pos_world;
+in vec3 frag_pos; // relative to the render origin, like the light space transforms
 in vec3 frag_pos_view;
 in vec2 texture_uv;
 flat in uint texture_index;
 in vec3 normal;
+in float shade;
+flat in uint smooth_surface;
+in vec4 texture_weights; // of the 4 textures of the smooth surfaces
 
 uniform sampler2DArray voxel_textures;
 uniform sampler2DArray shadow_map;
@@ -21,12 +24,12 @@ layout (std140, binding = 2) uniform light_space_transforms
     mat4 transforms[8];
 };
 
-float near = 0.1; 
-float far  = 500.0; 
+uniform float near;
+uniform float far;
+uniform float fog_density; // 0 disables the fog
 
 const float DIFFUSE_MULTIPLIER = 0.82;
 const vec4 clear_color = vec4(0.25,0.5,0.88,1.0);
-const float fog_density = 7.0;
 vec2 texel_size = 1.0 / textureSize(shadow_map,0).xy; // don'get the z component
 
 float shadow_bias = max(0.05 * (1.0 - dot(normal, light_dir)), 0.01);
@@ -90,6 +93,31 @@ vec2 get_derivative ( vec3 proj )
     return d_uv;
 }
 
+// the texture projected along the 3 axes, weighted by the normal
+vec4 triplanar(uint layer)
+{
+    vec3 weights = pow(abs(normal), vec3(4.0));
+    weights /= weights.x + weights.y + weights.z;
+
+    return weights.x * texture(voxel_textures, vec3(frag_pos.zy, layer))
+         + weights.y * texture(voxel_textures, vec3(frag_pos.xz, layer))
+         + weights.z * texture(voxel_textures, vec3(frag_pos.xy, layer));
+}
+
+vec4 get_albedo()
+{
+    if (smooth_surface == 0)
+        return texture(voxel_textures, vec3(texture_uv, texture_index));
+
+    vec4 albedo = vec4(0.0);
+    for (uint layer = 0; layer < 4; layer++)
+    {
+        if (texture_weights[layer] > 0.0)
+            albedo += texture_weights[layer] * triplanar(layer);
+    }
+    return albedo;
+}
+
 float is_in_shadow(vec4 point, int shadow_map_layer)
 {
     vec3 proj = point.xyz / point.w;
@@ -124,7 +152,7 @@ void main()
             }
         }
 
-        vec4 frag_light_space_pos = transforms[layer] * vec4(frag_pos_world,1.0);
+        vec4 frag_light_space_pos = transforms[layer] * vec4(frag_pos,1.0);
         shadow = is_in_shadow(frag_light_space_pos , layer);
     }
     
@@ -132,7 +160,8 @@ void main()
     // for now, being in shadow just means the texture's albedo colors get a bit darker
 
     float ambient = 0.5;
-    vec4 albedo = (ambient + (1.0 - shadow) * diffuse ) * texture(voxel_textures, vec3 (texture_uv,texture_index) );
+    vec4 albedo = (ambient + (1.0 - shadow) * diffuse ) * get_albedo();
+    albedo.rgb *= shade;
     float fog_intensity =  fog_intensity(linearize_depth(gl_FragCoord.z) / far);
-    color = albedo ; //fog_intensity * clear_color + (1-fog_intensity) * albedo;
+    color = fog_intensity * clear_color + (1-fog_intensity) * albedo;
 }
\ No newline at end of file
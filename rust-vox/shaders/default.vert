@@ -16,31 +16,72 @@ layout (std140, binding = 0) uniform transforms
     mat4 view;
 };
 
-layout (location = 0) in vec3 pos;
-layout (location = 1) in uint tex_coord_u;
-layout (location = 2) in uint tex_coord_v;
-layout (location = 3) in uint texture_index_in;
-layout (location = 4) in uint normal_index;
+// origin of the chunk of each draw relative to the render origin, indexed by the base instance
+layout (std430, binding = 4) readonly buffer chunk_origins
+{
+    ivec4 origins[];
+};
 
-out vec3 frag_pos_world; // fragment position in world space
+// see VoxelVertex for the layout of the bits
+layout (location = 0) in uint position_uv; // x, y, z in the chunk, u, v
+layout (location = 1) in uint attributes; // normal index, texture index, ambient occlusion, light
+
+out vec3 frag_pos; // fragment position relative to the render origin
 out vec3 frag_pos_view; // fragment position in the camera's view space
 out uint texture_index;
 out vec2 texture_uv; // needs to be vec2 so that interpolation is enabled
 out vec3 normal;
+out float shade; // from the ambient occlusion and the light
+
+// the smooth surfaces are textured from the fragment position, blending the textures by their weights,
+// interpolated between the vertices since each vertex can blend its own pair of textures
+flat out uint smooth_surface;
+out vec4 texture_weights;
+
+// the normal of a smooth surface is encoded on an octahedron
+vec3 decode_octahedral(uvec2 encoded)
+{
+    vec2 f = vec2(encoded) / 127.0 * 2.0 - 1.0;
+    vec3 n = vec3(f, 1.0 - abs(f.x) - abs(f.y));
+    float t = max(-n.z, 0.0);
+    n.xy += mix(vec2(t), vec2(-t), greaterThanEqual(n.xy, vec2(0.0)));
+    return normalize(n);
+}
 
 void main()
 {
-    vec4 pos = vec4(pos.x, pos.y, pos.z, 1.0);
-    frag_pos_world = vec3(pos);
+    smooth_surface = bitfieldExtract(position_uv, 31, 1);
+    vec3 local_pos = vec3(bitfieldExtract(position_uv, 0, 5), bitfieldExtract(position_uv, 5, 7), bitfieldExtract(position_uv, 12, 5));
+    if (smooth_surface == 1)
+        local_pos += vec3(bitfieldExtract(position_uv, 17, 4), bitfieldExtract(position_uv, 21, 4), bitfieldExtract(position_uv, 25, 4)) / 16.0;
+
+    vec4 pos = vec4(local_pos + vec3(origins[gl_BaseInstance].xyz), 1.0);
+    frag_pos = vec3(pos);
     vec4 pos_view = view * pos;
     frag_pos_view = vec3(pos_view);
     
     gl_Position = perspective * pos_view ;
 
-    texture_index = texture_index_in;
+    texture_index = bitfieldExtract(attributes, 3, 4);
+
+    texture_uv = vec2(bitfieldExtract(position_uv, 17, 7), bitfieldExtract(position_uv, 24, 7));
 
-    texture_uv = vec2(tex_coord_u,tex_coord_v);
+    if (smooth_surface == 1)
+    {
+        normal = decode_octahedral(uvec2(bitfieldExtract(attributes, 13, 7), bitfieldExtract(attributes, 20, 7)));
+        uint blend_texture_index = bitfieldExtract(attributes, 27, 4);
+        float blend = float(bitfieldExtract(attributes, 0, 3)) / 7.0;
+        texture_weights = vec4(0.0);
+        texture_weights[texture_index] += 1.0 - blend;
+        texture_weights[blend_texture_index] += blend;
+    }
+    else
+    {
+        normal = normal_lut[bitfieldExtract(attributes, 0, 3)];
+        texture_weights = vec4(0.0);
+    }
 
-    vec3 normal_vec = normal_lut[normal_index];
-    normal = normal_vec;
+    float ambient_occlusion = float(bitfieldExtract(attributes, 7, 2)) / 3.0;
+    float light = float(bitfieldExtract(attributes, 9, 4)) / 15.0;
+    shade = mix(0.4, 1.0, ambient_occlusion) * light;
 }
\ No newline at end of file
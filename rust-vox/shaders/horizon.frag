@@ -0,0 +1,48 @@
+#version 460 core
+
+out vec4 color;
+
+in vec3 frag_pos;
+in vec3 frag_pos_view;
+in vec3 normal;
+flat in uint voxel_type;
+
+uniform sampler2DArray voxel_textures;
+uniform usampler2D chunk_mask; // 1 where a chunk is rendered
+uniform ivec2 mask_origin; // chunk of the texel 0,0, relative to the chunk of the render origin
+uniform int chunk_size;
+
+uniform vec3 light_dir; // zero when the sun is not present
+uniform float far; // of the camera, the fog matches the one of the chunks
+uniform float fog_density; // 0 disables the fog
+uniform float horizon_end; // the tiles fade into the sky before this distance
+
+const float DIFFUSE_MULTIPLIER = 0.82;
+const vec4 clear_color = vec4(0.25,0.5,0.88,1.0);
+const uint NUM_TEXTURES = 4;
+const float AVERAGE_COLOR_LOD = 4.0; // the coarsest mip level of the voxel textures
+
+float fog_intensity(float depth)
+{
+    float res = 1.0 - exp(-pow(fog_density * depth , 2.0));
+    return clamp(res , 0.0 , 1.0 );
+}
+
+void main()
+{
+    // the chunk is drawn instead
+    ivec2 texel = ivec2(floor(frag_pos.xz / chunk_size)) - mask_origin;
+    if (all(greaterThanEqual(texel, ivec2(0))) && all(lessThan(texel, textureSize(chunk_mask, 0))) && texelFetch(chunk_mask, texel, 0).r != 0u)
+        discard;
+
+    if (voxel_type >= NUM_TEXTURES) // no block in the column
+        discard;
+
+    float diffuse = max(dot(normalize(normal), light_dir), 0.0) * DIFFUSE_MULTIPLIER;
+    float ambient = 0.5;
+    vec3 albedo = (ambient + diffuse) * textureLod(voxel_textures, vec3(0.5, 0.5, voxel_type), AVERAGE_COLOR_LOD).rgb;
+
+    float fog_intensity = fog_intensity(-frag_pos_view.z / far);
+    float fade = smoothstep(0.7 * horizon_end, horizon_end, length(frag_pos_view));
+    color = vec4(fog_intensity * clear_color.rgb + (1 - fog_intensity) * albedo, 1.0 - fade);
+}
@@ -0,0 +1,33 @@
+#version 460 core
+
+// transforms ubo
+layout (std140, binding = 0) uniform transforms
+{
+    mat4 perspective;
+    mat4 view;
+};
+
+uniform mat4 projection; // the camera's, with a far plane past the horizon
+uniform ivec3 origin; // render origin, the positions are drawn relative to it
+
+layout (location = 0) in vec3 pos;
+layout (location = 1) in vec3 normal_in;
+layout (location = 2) in uint voxel_type_in;
+
+out vec3 frag_pos; // relative to the render origin
+out vec3 frag_pos_view;
+out vec3 normal;
+flat out uint voxel_type;
+
+void main()
+{
+    // the samples are on whole blocks, the difference is exact
+    frag_pos = vec3(ivec3(pos) - origin);
+    vec4 pos_view = view * vec4(frag_pos, 1.0);
+    frag_pos_view = vec3(pos_view);
+
+    gl_Position = projection * pos_view;
+
+    normal = normal_in;
+    voxel_type = voxel_type_in;
+}
@@ -0,0 +1,7 @@
+#version 430 core
+
+// only the depth test matters, nothing is written
+
+void main()
+{
+}
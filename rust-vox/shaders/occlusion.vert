@@ -0,0 +1,20 @@
+#version 430 core
+
+// box of a chunk drawn for its occlusion query, needs no more than GL 4.3 like cull.comp
+
+// transforms ubo
+layout (std140, binding = 0) uniform transforms
+{
+    mat4 perspective;
+    mat4 view;
+};
+
+layout (location = 0) in vec3 corner; // of the unit cube
+
+uniform vec3 box_min;
+uniform vec3 box_max;
+
+void main()
+{
+    gl_Position = perspective * view * vec4(mix(box_min, box_max, corner), 1.0);
+}
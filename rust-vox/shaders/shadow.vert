@@ -1,9 +1,19 @@
 #version 460 core
 
-layout (location = 0) in vec3 pos;
+// origin of the chunk of each draw relative to the render origin, indexed by the base instance
+layout (std430, binding = 4) readonly buffer chunk_origins
+{
+    ivec4 origins[];
+};
+
+layout (location = 0) in uint position_uv; // see VoxelVertex
 
 void main()
 {
-    vec4 pos_out = vec4(pos.x, pos.y, pos.z, 1.0) ;    
+    vec3 local_pos = vec3(bitfieldExtract(position_uv, 0, 5), bitfieldExtract(position_uv, 5, 7), bitfieldExtract(position_uv, 12, 5));
+    if (bitfieldExtract(position_uv, 31, 1) == 1) // the fraction of the position of a smooth surface
+        local_pos += vec3(bitfieldExtract(position_uv, 17, 4), bitfieldExtract(position_uv, 21, 4), bitfieldExtract(position_uv, 25, 4)) / 16.0;
+
+    vec4 pos_out = vec4(local_pos + vec3(origins[gl_BaseInstance].xyz), 1.0);
     gl_Position = pos_out;
 }
\ No newline at end of file
@@ -0,0 +1,26 @@
+// location of the shaders and textures on disk
+// defaults to the crate's directory so the binary works from any working directory during development
+
+use std::{path::{Path, PathBuf}, sync::RwLock};
+
+lazy_static!
+{
+    static ref ASSET_ROOT: RwLock<PathBuf> = RwLock::new(PathBuf::from(env!("CARGO_MANIFEST_DIR")));
+}
+
+/// Must be called before anything loads its assets
+pub fn set_asset_root(root: &Path)
+{
+    *ASSET_ROOT.write().unwrap() = root.to_path_buf();
+}
+
+pub fn get_asset_root() -> PathBuf
+{
+    ASSET_ROOT.read().unwrap().clone()
+}
+
+/// Path of an asset relative to the asset root, ex: asset_path("shaders/default.vert")
+pub fn asset_path(relative: &str) -> String
+{
+    get_asset_root().join(relative).to_string_lossy().into_owned()
+}
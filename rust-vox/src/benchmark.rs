@@ -0,0 +1,88 @@
+// scripted camera flight and frame time statistics, used by the --benchmark mode of rust-vox-main
+
+use std::fmt::{Display, Formatter, self};
+
+use glam::Vec3;
+
+const FLIGHT_SPEED: f32 = 40.0; // world units per second along x
+const SWAY_AMPLITUDE: f32 = 60.0; // the path sways along z so chunks get loaded on both sides
+const SWAY_PERIOD: f32 = 10.0; // seconds
+
+/// A deterministic flight, the same run can be repeated to compare frame times
+pub struct BenchmarkFlight
+{
+    start: Vec3,
+    duration: f32, // seconds
+}
+
+impl BenchmarkFlight
+{
+    pub fn new(start: Vec3, duration: f32) -> Self
+    {
+        Self{start, duration}
+    }
+
+    /// Position of the camera after t seconds
+    pub fn get_position(&self, t: f32) -> Vec3
+    {
+        let phase = t / SWAY_PERIOD * std::f32::consts::TAU;
+        self.start + Vec3::new(FLIGHT_SPEED * t, 0.0, SWAY_AMPLITUDE * phase.sin())
+    }
+
+    /// Direction the camera looks at after t seconds, along the path and slightly down
+    pub fn get_front(&self, t: f32) -> Vec3
+    {
+        let phase = t / SWAY_PERIOD * std::f32::consts::TAU;
+        let velocity = Vec3::new(FLIGHT_SPEED, 0.0, SWAY_AMPLITUDE * std::f32::consts::TAU / SWAY_PERIOD * phase.cos());
+
+        (velocity.normalize() + Vec3::new(0.0, -0.3, 0.0)).normalize()
+    }
+
+    pub fn is_finished(&self, t: f32) -> bool
+    {
+        t >= self.duration
+    }
+}
+
+pub struct FrameTimeStats
+{
+    pub frames: usize,
+    pub average: f32, // all times are in ms
+    pub min: f32,
+    pub max: f32,
+    pub p50: f32,
+    pub p95: f32,
+    pub p99: f32,
+}
+
+impl FrameTimeStats
+{
+    /// Compute the statistics of the frame times, in ms, None if there are no frames
+    pub fn new(frame_times: &[f32]) -> Option<Self>
+    {
+        if frame_times.is_empty()
+        {
+            return None;
+        }
+
+        let mut sorted = frame_times.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+
+        // nearest rank percentile
+        let percentile = |p: f32| sorted[((p / 100.0 * sorted.len() as f32).ceil() as usize).clamp(1, sorted.len()) - 1];
+
+        Some(Self{frames: sorted.len(), average: sorted.iter().sum::<f32>() / sorted.len() as f32,
+            min: sorted[0], max: sorted[sorted.len() - 1], p50: percentile(50.0), p95: percentile(95.0), p99: percentile(99.0)})
+    }
+}
+
+impl Display for FrameTimeStats
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result
+    {
+        writeln!(f, "frames: {}", self.frames)?;
+        writeln!(f, "average: {:.3} ms ({:.1} FPS)", self.average, 1000.0 / self.average)?;
+        writeln!(f, "min: {:.3} ms, max: {:.3} ms", self.min, self.max)?;
+        write!(f, "50%: {:.3} ms, 95%: {:.3} ms, 99%: {:.3} ms", self.p50, self.p95, self.p99)
+    }
+}
@@ -0,0 +1,117 @@
+// command line interface of rust-vox-main
+
+use std::path::PathBuf;
+
+use clap::{Parser, builder::PossibleValuesParser};
+use engine::{settings::{Settings, DisplayMode}, engine::terrain::GENERATOR_NAMES};
+use glam::Vec3;
+
+#[derive(Parser, Debug)]
+#[command(name = "rust-vox-main", about = "Voxel engine prototype", version)]
+pub struct Cli
+{
+    /// Directory of the world, holds its seed and generator, created if missing
+    #[arg(long, value_name = "DIR")]
+    pub world: Option<PathBuf>,
+
+    /// Seed of the terrain generator, overrides the one of the world
+    #[arg(long)]
+    pub seed: Option<u32>,
+
+    /// Terrain generator, overrides the one of the world
+    #[arg(long, value_parser = PossibleValuesParser::new(GENERATOR_NAMES))]
+    pub generator: Option<String>,
+
+    /// Visible distance, in chunks
+    #[arg(long, value_name = "CHUNKS")]
+    pub render_distance: Option<i32>,
+
+    /// Size of the window, ex: 1280x720
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_window_size)]
+    pub window_size: Option<(u32, u32)>,
+
+    /// Start in exclusive fullscreen
+    #[arg(long, conflicts_with = "borderless")]
+    pub fullscreen: bool,
+
+    /// Start in borderless fullscreen
+    #[arg(long)]
+    pub borderless: bool,
+
+    /// Enable vsync
+    #[arg(long, conflicts_with = "no_vsync")]
+    pub vsync: bool,
+
+    /// Disable vsync
+    #[arg(long)]
+    pub no_vsync: bool,
+
+    /// Directory containing the shaders and textures directories
+    #[arg(long, value_name = "DIR")]
+    pub assets: Option<PathBuf>,
+
+    /// Position of the camera at startup, ex: 0,60,0
+    #[arg(long, value_name = "X,Y,Z", value_parser = parse_position, allow_hyphen_values = true)]
+    pub spawn: Option<Vec3>,
+
+    /// Fly along a scripted path for this many seconds, print frame time statistics and exit
+    #[arg(long, value_name = "SECONDS")]
+    pub benchmark: Option<f32>,
+}
+
+impl Cli
+{
+    /// Override the settings with the options given on the command line
+    pub fn apply_overrides(&self, settings: &mut Settings)
+    {
+        if let Some(distance) = self.render_distance
+        {
+            // keep the same margin of loaded chunks around the visible zone
+            settings.loaded_distance = distance + (settings.loaded_distance - settings.visible_distance);
+            settings.visible_distance = distance;
+        }
+
+        if let Some((width, height)) = self.window_size
+        {
+            settings.window_width = width;
+            settings.window_height = height;
+        }
+
+        if self.fullscreen
+        {
+            settings.display_mode = DisplayMode::Exclusive;
+        }
+        else if self.borderless
+        {
+            settings.display_mode = DisplayMode::Borderless;
+        }
+
+        if self.vsync || self.no_vsync
+        {
+            settings.vsync = self.vsync;
+        }
+
+        settings.sanitize();
+    }
+}
+
+fn parse_window_size(value: &str) -> Result<(u32, u32), String>
+{
+    let (width, height) = value.split_once('x').ok_or("expected WIDTHxHEIGHT")?;
+    let width = width.trim().parse::<u32>().map_err(|err| format!("invalid width: {}", err))?;
+    let height = height.trim().parse::<u32>().map_err(|err| format!("invalid height: {}", err))?;
+
+    Ok((width, height))
+}
+
+fn parse_position(value: &str) -> Result<Vec3, String>
+{
+    let coords = value.split(',').map(|coord| coord.trim().parse::<f32>()).collect::<Result<Vec<f32>, _>>()
+        .map_err(|err| format!("invalid coordinate: {}", err))?;
+
+    match coords[..]
+    {
+        [x, y, z] => Ok(Vec3::new(x, y, z)),
+        _ => Err("expected X,Y,Z".to_string()),
+    }
+}
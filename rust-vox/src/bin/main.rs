@@ -1,32 +1,157 @@
 #![warn(clippy::all)]
 #![allow(clippy::too_many_arguments)]
 
-use engine::{DebugData, world::World, camera::Camera, Renderer};
-use glam::Vec3;
+use clap::Parser;
+use engine::{DebugData, UiRenderer, world::{World, WorldInfo}, camera::Camera, Renderer, settings::{Settings, DisplayMode},
+    assets, benchmark::{BenchmarkFlight, FrameTimeStats}, engine::terrain::create_generator, input::{InputMap, Action, Binding, gamepad::{Gamepads, GamepadConfig}}};
+use glam::{Vec2, Vec3};
 use imgui::Context;
 use imgui_sdl2_support::SdlPlatform;
 use sdl2::{
-    event::Event,
-    keyboard,
-    video::{GLProfile, SwapInterval}, mouse::MouseButton,
+    event::{Event, WindowEvent},
+    video::{GLProfile, SwapInterval, Window, FullscreenType},
 };
 
-use std::{time::Instant, f32::consts::PI, rc::Rc, cell::RefCell};
-static MOUSE_SENSITIVITY: f32 = 0.05;
+use std::{time::Instant, rc::Rc, cell::RefCell, path::Path, process, io};
+
+mod cli;
+use cli::Cli;
+
+static CONTROLS_FILE: &str = "controls.toml";
+static SETTINGS_FILE: &str = "settings.toml";
+
+fn set_vsync(window: &Window, vsync: bool)
+{
+    let res = window.subsystem().gl_set_swap_interval(if vsync { SwapInterval::VSync } else { SwapInterval::Immediate });
+
+    if let Err(s) = res {
+        println!("error occured:{}", s);
+    }
+}
+
+fn set_display_mode(window: &mut Window, mode: DisplayMode)
+{
+    let fullscreen_type = match mode
+    {
+        DisplayMode::Windowed => FullscreenType::Off,
+        DisplayMode::Borderless => FullscreenType::Desktop,
+        DisplayMode::Exclusive => FullscreenType::True,
+    };
+
+    if let Err(err) = window.set_fullscreen(fullscreen_type)
+    {
+        println!("error switching to {}: {}", mode.name(), err);
+    }
+}
+
+/// Propagate the size of the window to everything that depends on it
+fn handle_resize(window: &Window, world: &mut World, renderer: &mut Renderer, ui_renderer: &mut UiRenderer)
+{
+    // on HiDPI displays the drawable size in pixels is bigger than the window size in screen coordinates
+    let (width, height) = window.drawable_size();
+    if width == 0 || height == 0 // minimized
+    {
+        return;
+    }
+
+    world.camera.aspect_ratio = width as f32 / height as f32;
+    world.camera.rebuild_frustum();
+    renderer.resize(width as i32, height as i32, &world.camera);
+    ui_renderer.resize(width, height);
+}
+
+/// Apply the settings that changed since old, as far as possible without restarting
+fn apply_settings(old: &Settings, new: &Settings, window: &mut Window, world: &mut World, renderer: &mut Renderer, ui_renderer: &mut UiRenderer)
+{
+    if (old.window_width, old.window_height) != (new.window_width, new.window_height)
+    {
+        if let Err(err) = window.set_size(new.window_width, new.window_height)
+        {
+            println!("error resizing the window: {}", err);
+        }
+        handle_resize(window, world, renderer, ui_renderer);
+    }
+
+    if old.display_mode != new.display_mode
+    {
+        set_display_mode(window, new.display_mode);
+        handle_resize(window, world, renderer, ui_renderer);
+    }
+
+    if old.vsync != new.vsync
+    {
+        set_vsync(window, new.vsync);
+    }
+
+    world.apply_settings(old, new);
+    renderer.apply_settings(old, new, &world.camera);
+}
 
 //TODO: refactor main
 fn main() {
+    let cli = Cli::parse();
+
+    if let Some(root) = &cli.assets
+    {
+        assets::set_asset_root(root);
+    }
+    if !assets::get_asset_root().join("shaders").is_dir()
+    {
+        eprintln!("no shaders directory found in the asset root {}, use --assets", assets::get_asset_root().display());
+        process::exit(1);
+    }
+
+    // the world's seed and generator, stored in the world directory if there is one
+    let mut world_info = match &cli.world
+    {
+        Some(dir) => match WorldInfo::load(dir)
+        {
+            Ok(world_info) => world_info,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => WorldInfo::default(), // a new world
+            Err(err) =>
+            {
+                // the file is left as it is, the world would be generated again with another seed
+                eprintln!("error loading the world info from {}: {}", dir.display(), err);
+                process::exit(1);
+            },
+        },
+        None => WorldInfo::default(),
+    };
+    if let Some(seed) = cli.seed { world_info.seed = seed; }
+    if let Some(generator) = &cli.generator { world_info.generator = generator.clone(); }
+
+    if let Some(dir) = &cli.world
+    {
+        if let Err(err) = world_info.save(dir)
+        {
+            println!("error saving the world info to {}: {}", dir.display(), err);
+        }
+    }
+
+    let generator = create_generator(&world_info.generator, world_info.seed).unwrap_or_else(||
+    {
+        eprintln!("unknown terrain generator \"{}\"", world_info.generator);
+        process::exit(1);
+    });
+
+    // runtime settings, they can be changed from the UI
+    // the command line overrides are not written back to the file
+    let file_settings = Settings::load_or_default(Path::new(SETTINGS_FILE));
+    let mut settings = file_settings.clone();
+    cli.apply_overrides(&mut settings);
+    let overridden_settings = settings.clone();
+
     // initialize SDL and its video subsystem
     let sdl = sdl2::init().unwrap();
     let video_subsystem = sdl.video().unwrap();
 
     let gl_attr = video_subsystem.gl_attr();
-    gl_attr.set_context_version(4, 3);
+    gl_attr.set_context_version(4, 6); // the vertex shaders read gl_BaseInstance, core since 4.6
     gl_attr.set_context_profile(GLProfile::Core);
 
     // create a new window
-    let window = video_subsystem
-        .window("RustVox prototyping", 1700, 900)
+    let mut window = video_subsystem
+        .window("RustVox prototyping", settings.window_width, settings.window_height)
         .allow_highdpi()
         .opengl()
         .position_centered()
@@ -42,12 +167,8 @@ fn main() {
 
     window.gl_make_current(&gl_context).unwrap();
 
-    // enable vsync to cap framerate
-    let res = window.subsystem().gl_set_swap_interval(SwapInterval::VSync);
-
-    if let Err(s) = res {
-        println!("error occured:{}", s);
-    }
+    // vsync caps the framerate
+    set_vsync(&window, settings.vsync);
 
     let mut imgui_context = Context::create();
     imgui_context.set_ini_filename(None);
@@ -59,7 +180,7 @@ fn main() {
     // struct to be owned by all debugged components
     let debug_data = Rc::new(RefCell::new(DebugData::default()));
 
-    let mut ui_renderer = engine::UiRenderer::new(&video_subsystem, &mut imgui_context, &window, &debug_data);
+    let mut ui_renderer = UiRenderer::new(&video_subsystem, &mut imgui_context, &window, &debug_data);
 
     let mut event_pump = sdl.event_pump().unwrap();
 
@@ -67,24 +188,41 @@ fn main() {
     sdl.mouse().capture(false);
 
     let mut voxel_world = World::new(Camera::new(
-        PI / 4.0,
-        1920.0 / 1080.0,
+        settings.fov_y.to_radians(),
+        settings.window_width as f32 / settings.window_height as f32,
         0.1,
-        500.0,
-        Vec3::new(0.0, 60.0, 0.0),
+        settings.far_plane,
+        cli.spawn.unwrap_or(Vec3::new(0.0, 60.0, 0.0)),
         Vec3::new(1.0, 0.3, 0.0),
         Vec3::new(0.0, 1.0, 0.0),
         3.0,
-
-        &debug_data
     ),
+    &settings,
+    generator,
     &debug_data);
 
-    let mut world_renderer = Renderer::new(&video_subsystem, &voxel_world, &debug_data);
+    let mut world_renderer = Renderer::new(&video_subsystem, &voxel_world, &settings, &debug_data);
+
+    set_display_mode(&mut window, settings.display_mode);
+    handle_resize(&window, &mut voxel_world, &mut world_renderer, &mut ui_renderer);
+
+    // key and mouse bindings
+    let mut input_map = InputMap::load_or_default(Path::new(CONTROLS_FILE));
+
+    // game controllers, opened when SDL reports them as connected
+    let mut gamepads = Gamepads::new(sdl.game_controller().unwrap(), GamepadConfig::default());
+    let mut frame_secs = 0.0;
 
     // FIXME: remove this
     let mut is_filled_mode = true; // opengl rendering mode
-    let mut is_vsync_on = true;
+
+    // the settings as they were last applied, to detect the changes made from the UI
+    let mut applied_settings = settings.clone();
+
+    // benchmark mode, the camera follows a scripted flight
+    let benchmark = cli.benchmark.map(|seconds| BenchmarkFlight::new(voxel_world.camera.get_position(), seconds));
+    let benchmark_start = Instant::now();
+    let mut frame_times: Vec<f32> = Vec::new(); // in ms
 
     'main: loop {
         let start = Instant::now();
@@ -95,49 +233,31 @@ fn main() {
             /* pass all events to imgui platfrom */
             platform.handle_event(&mut imgui_context, &event);
 
+            // controllers track their sticks and triggers through events
+            let pressed_trigger = gamepads.handle_event(&event);
+            let pressed = Binding::from_event(&event).or(pressed_trigger);
+
+            // a rebind started from the UI consumes the next pressed input, the clicks on the UI itself excepted
+            let on_ui = matches!(pressed, Some(Binding::Mouse(_))) && imgui_context.io().want_capture_mouse;
+            if !on_ui && pressed.is_some_and(|binding| input_map.capture_rebind(binding))
+            {
+                continue;
+            }
+
             match event {
                 Event::Quit { .. } => break 'main,
-                Event::KeyDown { keycode: Some(s) , .. } => {
-                        match s {
-                            keyboard::Keycode::Num1 => sdl
-                                .mouse()
-                                .set_relative_mouse_mode(!sdl.mouse().relative_mouse_mode()),
-                            keyboard::Keycode::Num2 => {
-                                world_renderer.set_mode(if is_filled_mode {
-                                    gl::LINE
-                                } else {
-                                    gl::FILL
-                                });
-                                is_filled_mode = !is_filled_mode
-                            }
-                            keyboard::Keycode::Num3 => {
-                                window
-                                    .subsystem()
-                                    .gl_set_swap_interval(if is_vsync_on {
-                                        SwapInterval::VSync
-                                    } else {
-                                        SwapInterval::Immediate
-                                    })
-                                    .expect("error setting swap interval");
-                                is_vsync_on = !is_vsync_on
-                            }
-                            keyboard::Keycode::Escape => {
-                                break 'main;
-                            }
-                            keyboard::Keycode::W => voxel_world.camera.move_forward(),
-                            keyboard::Keycode::S => voxel_world.camera.move_backward(),
-                            keyboard::Keycode::A => voxel_world.camera.strafe_left(),
-                            keyboard::Keycode::D => voxel_world.camera.strafe_right(),
-                            _ => (),
-                        };
-                }
-                Event::MouseButtonDown { mouse_btn, .. } =>
+                Event::Window { win_event: WindowEvent::SizeChanged(..), .. } =>
                 {
-                    match mouse_btn
+                    handle_resize(&window, &mut voxel_world, &mut world_renderer, &mut ui_renderer);
+
+                    // remember the size of the window, to restore it on the next start
+                    if settings.display_mode == DisplayMode::Windowed
                     {
-                        MouseButton::Right => {voxel_world.destroy()},
-                        MouseButton::Left => {voxel_world.place()}
-                        _ => (),
+                        let (width, height) = window.size();
+                        settings.window_width = width;
+                        settings.window_height = height;
+                        applied_settings.window_width = width;
+                        applied_settings.window_height = height;
                     }
                 },
                 Event::MouseMotion {
@@ -150,24 +270,116 @@ fn main() {
                     if sdl.mouse().relative_mouse_mode()
                     {
                         voxel_world.camera.change_front_rel(
-                            x_rel as f32 * MOUSE_SENSITIVITY,
-                            y_rel as f32 * MOUSE_SENSITIVITY)
+                            x_rel as f32 * settings.mouse_sensitivity,
+                            y_rel as f32 * settings.mouse_sensitivity)
+                    }
+                },
+                _ =>
+                {
+                    if let Some(action) = pressed.and_then(|binding| input_map.get_action(binding))
+                    {
+                        match action
+                        {
+                            Action::ToggleMouse => sdl
+                                .mouse()
+                                .set_relative_mouse_mode(!sdl.mouse().relative_mouse_mode()),
+                            Action::ToggleWireframe => {
+                                world_renderer.set_mode(if is_filled_mode {
+                                    gl::LINE
+                                } else {
+                                    gl::FILL
+                                });
+                                is_filled_mode = !is_filled_mode
+                            }
+                            Action::ToggleVsync => settings.vsync = !settings.vsync,
+                            Action::ToggleFullscreen => settings.display_mode =
+                                if settings.display_mode == DisplayMode::Exclusive { DisplayMode::Windowed } else { DisplayMode::Exclusive },
+                            Action::ToggleBorderless => settings.display_mode =
+                                if settings.display_mode == DisplayMode::Borderless { DisplayMode::Windowed } else { DisplayMode::Borderless },
+                            Action::Quit => break 'main,
+                            Action::MoveForward => voxel_world.camera.move_forward(),
+                            Action::MoveBackward => voxel_world.camera.move_backward(),
+                            Action::StrafeLeft => voxel_world.camera.strafe_left(),
+                            Action::StrafeRight => voxel_world.camera.strafe_right(),
+                            Action::PlaceBlock => voxel_world.place(),
+                            Action::DestroyBlock => voxel_world.destroy(),
+                        };
                     }
                 },
-                _ => (),
             };
         }
 
+        // analog sticks are applied every frame, scaled by the duration of the last frame
+        let config = &gamepads.state.config;
+        let movement = gamepads.state.get_movement() * config.move_speed * frame_secs;
+        let look = gamepads.state.get_look() * config.look_speed * frame_secs;
+        if movement != Vec2::ZERO
+        {
+            voxel_world.camera.move_rel(movement.y, movement.x);
+        }
+        if look != Vec2::ZERO
+        {
+            voxel_world.camera.change_front_rel(look.x, look.y);
+        }
+
+        if let Some(flight) = &benchmark
+        {
+            let t = benchmark_start.elapsed().as_secs_f32();
+            if flight.is_finished(t)
+            {
+                break 'main;
+            }
+
+            voxel_world.camera.set_position(flight.get_position(t));
+            voxel_world.camera.set_front(flight.get_front(t));
+        }
+
         voxel_world.update();
         // render the world
         world_renderer.draw_world(&voxel_world);
 
         // render the UI
-        ui_renderer.render(&mut voxel_world, &mut world_renderer, &mut platform, &mut imgui_context, &window, &event_pump);
+        ui_renderer.render(&mut voxel_world, &mut world_renderer, &mut input_map, &mut settings, &mut platform, &mut imgui_context, &window, &event_pump);
+
+        // apply the settings changed from the UI or through the key bindings
+        settings.sanitize();
+        if settings != applied_settings
+        {
+            apply_settings(&applied_settings, &settings, &mut window, &mut voxel_world, &mut world_renderer, &mut ui_renderer);
+            applied_settings = settings.clone();
+        }
 
         window.gl_swap_window();
         let end = start.elapsed();
+        frame_secs = end.as_secs_f32();
+        if benchmark.is_some()
+        {
+            frame_times.push(end.as_secs_f32() * 1000.0);
+        }
         debug_data.borrow_mut().add_calculation_time(end.as_secs_f32());
         debug_data.borrow_mut().frame_time = end.as_micros();
     }
+
+    if benchmark.is_some()
+    {
+        // a benchmark run doesn't touch the config files
+        match FrameTimeStats::new(&frame_times)
+        {
+            Some(stats) => println!("benchmark results:\n{}", stats),
+            None => println!("benchmark results: no frames rendered"),
+        }
+        return;
+    }
+
+    // persist the bindings, they could have been changed from the UI
+    if let Err(err) = input_map.save(Path::new(CONTROLS_FILE))
+    {
+        println!("error saving bindings to {}: {}", CONTROLS_FILE, err);
+    }
+
+    let settings = settings.without_overrides(&file_settings, &overridden_settings);
+    if let Err(err) = settings.save(Path::new(SETTINGS_FILE))
+    {
+        println!("error saving settings to {}: {}", SETTINGS_FILE, err);
+    }
 }
@@ -1,8 +1,6 @@
-use std::{cell::RefCell, rc::Rc};
+use glam::{DVec3, IVec3, Mat4, Vec3};
 
-use glam::{Vec3, Mat4};
-
-use crate::DebugData;
+use super::chunk::{CHUNK_SIZE_X, CHUNK_SIZE_Z};
 
 pub struct Camera
 {
@@ -13,7 +11,7 @@ pub struct Camera
     pub far_plane: f32,
 
     // camera
-    position: Vec3,
+    position: DVec3, // in double precision, the world is rendered relative to the camera's chunk
     front: Vec3,
     up: Vec3,
 
@@ -26,35 +24,45 @@ pub struct Camera
 
     // Frustum
     frustum: Frustum,
-
-    // debug data
-    debug_data: Rc<RefCell<DebugData>>
 }
 
 impl Camera
 {
-    pub fn new(fov_y: f32, aspect_ratio: f32, near_plane: f32, far_plane: f32, position: Vec3 , front: Vec3 , up: Vec3 , speed: f32, debug_data: &Rc<RefCell<DebugData>>) -> Self
+    pub fn new(fov_y: f32, aspect_ratio: f32, near_plane: f32, far_plane: f32, position: Vec3 , front: Vec3 , up: Vec3 , speed: f32) -> Self
     {
         let frustum =  Frustum::new(position, front, up, near_plane, far_plane, fov_y, aspect_ratio);
 
-        let debug_data = debug_data.clone();
-        debug_data.borrow_mut().player_pos = position;
-        debug_data.borrow_mut().front = front;
-
-        Self { fov_y, aspect_ratio, near_plane, far_plane, position, front , up, speed , pitch: 0.0, yaw: -89.9, frustum, debug_data}
+        Self { fov_y, aspect_ratio, near_plane, far_plane, position: position.as_dvec3(), front , up, speed , pitch: 0.0, yaw: -89.9, frustum}
     }
 
     pub fn get_position(&self) -> Vec3
     {
-        self.position
+        self.position.as_vec3()
     }
 
     pub fn set_position(&mut self, pos: Vec3 )
+    {
+        self.set_position_f64(pos.as_dvec3());
+    }
+
+    fn set_position_f64(&mut self, pos: DVec3)
     {
         self.position = pos;
         self.rebuild_frustum();
-        // update the debug data
-        self.debug_data.borrow_mut().player_pos = self.position;
+    }
+
+    /// Origin of the chunk containing the camera, in blocks, the rendering is relative to it
+    pub fn get_render_origin(&self) -> IVec3
+    {
+        let chunk_x = (self.position.x / CHUNK_SIZE_X as f64).floor() as i32;
+        let chunk_z = (self.position.z / CHUNK_SIZE_Z as f64).floor() as i32;
+        IVec3::new(chunk_x * CHUNK_SIZE_X as i32, 0, chunk_z * CHUNK_SIZE_Z as i32)
+    }
+
+    /// Position of the camera relative to its render origin
+    pub fn get_relative_position(&self) -> Vec3
+    {
+        (self.position - self.get_render_origin().as_dvec3()).as_vec3()
     }
 
     pub fn get_front(&self) -> Vec3
@@ -66,28 +74,33 @@ impl Camera
     {
         self.front = front;
         self.rebuild_frustum();
-        // update the debug data
-        self.debug_data.borrow_mut().front = self.front;
     }
 
     pub fn move_forward(&mut self)
     {
-        self.set_position(self.position + self.front * self.speed);
+        self.set_position_f64(self.position + (self.front * self.speed).as_dvec3());
     }
 
     pub fn move_backward(&mut self)
     {
-        self.set_position(self.position - self.front * self.speed);
+        self.set_position_f64(self.position - (self.front * self.speed).as_dvec3());
     }
 
     pub fn strafe_left(&mut self)
     {
-        self.set_position(self.position - Vec3::cross(self.front, self.up).normalize() * self.speed);
+        self.set_position_f64(self.position - (Vec3::cross(self.front, self.up).normalize() * self.speed).as_dvec3());
     }
 
     pub fn strafe_right(&mut self)
     {
-        self.set_position(self.position + Vec3::cross(self.front, self.up).normalize() * self.speed);
+        self.set_position_f64(self.position + (Vec3::cross(self.front, self.up).normalize() * self.speed).as_dvec3());
+    }
+
+    /// Move by a distance along the front and right directions, used for analog input
+    pub fn move_rel(&mut self, forward: f32, right: f32)
+    {
+        let right_dir = Vec3::cross(self.front, self.up).normalize();
+        self.set_position_f64(self.position + (self.front * forward + right_dir * right).as_dvec3());
     }
 
     /// Change the Camera's direction 
@@ -111,7 +124,14 @@ impl Camera
 
     pub fn get_look_at(&self) -> Mat4
     {
-        Mat4::look_at_rh(self.position,self.position+self.front,self.up)
+        Mat4::look_at_rh(self.get_position(),self.get_position()+self.front,self.up)
+    }
+
+    /// View transformation of the world translated by minus the render origin
+    pub fn get_relative_look_at(&self) -> Mat4
+    {
+        let position = self.get_relative_position();
+        Mat4::look_at_rh(position,position+self.front,self.up)
     }
 
     pub fn get_persp_trans(&self) -> Mat4
@@ -121,7 +141,7 @@ impl Camera
 
     pub fn rebuild_frustum(&mut self)
     {
-        self.frustum = Frustum::new(self.position, self.front, self.up, self.near_plane, self.far_plane, self.fov_y, self.aspect_ratio);
+        self.frustum = Frustum::new(self.get_position(), self.front, self.up, self.near_plane, self.far_plane, self.fov_y, self.aspect_ratio);
     }
 
     pub fn is_visible<T> (&self, geometry: &T) -> bool
@@ -131,6 +151,7 @@ impl Camera
     }
 }
 
+#[derive(Clone, Copy, Debug)]
 pub struct AABB
 {
     pub min: Vec3,
@@ -143,6 +164,12 @@ impl AABB
     {
         Self{min,max}
     }
+
+    /// The box moved by offset
+    pub fn translated(&self, offset: Vec3) -> Self
+    {
+        Self{min: self.min + offset, max: self.max + offset}
+    }
 }
 
 pub trait BoundingBox
@@ -150,6 +177,14 @@ pub trait BoundingBox
     fn get_aabb(&self) -> AABB;
 }
 
+impl BoundingBox for AABB
+{
+    fn get_aabb(&self) -> AABB
+    {
+        *self
+    }
+}
+
 pub struct Frustum
 {
     // 6 frustum planes: near,far,left,right,top,bottom
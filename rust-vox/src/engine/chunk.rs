@@ -39,6 +39,7 @@ pub struct Chunk
 {
     pub voxels: [[[Voxel; CHUNK_SIZE_Z] ; CHUNK_SIZE_Y] ; CHUNK_SIZE_X],
     pos: IVec2,
+    occupied_y: Option<(usize, usize)>, // lowest and highest layers with a filled voxel, None if the chunk is empty
 }
 
 impl Chunk
@@ -64,7 +65,9 @@ impl Chunk
             }
         }
 
-        Self{ pos , voxels}
+        let mut chunk = Self{ pos , voxels, occupied_y: None};
+        chunk.update_occupied_y();
+        chunk
     }
 
     pub fn get_voxel(&self, pos: IVec3) -> Option<Voxel>
@@ -92,6 +95,30 @@ impl Chunk
         }
 
         self.voxels[pos.x as usize][pos.y as usize][pos.z as usize] = voxel;
+
+        let y = pos.y as usize;
+        match self.occupied_y
+        {
+            _ if voxel.is_filled() => self.occupied_y = Some(self.occupied_y.map_or((y, y), |(min, max)| (min.min(y), max.max(y)))),
+            // removing a voxel of the lowest or highest layer may shrink the range
+            Some((min, max)) if y == min || y == max => self.update_occupied_y(),
+            _ => (),
+        }
+    }
+
+    /// Recompute the range of layers holding filled voxels
+    fn update_occupied_y(&mut self)
+    {
+        let is_layer_filled = |y: usize| self.voxels.iter().any(|x_row| x_row[y].iter().any(|voxel| voxel.is_filled()));
+
+        self.occupied_y = (0..CHUNK_SIZE_Y).find(|y| is_layer_filled(*y))
+            .map(|min| (min, (min..CHUNK_SIZE_Y).rev().find(|y| is_layer_filled(*y)).unwrap()));
+    }
+
+    /// Lowest and highest layers with a filled voxel, None if the chunk is empty
+    pub fn get_occupied_y(&self) -> Option<(usize, usize)>
+    {
+        self.occupied_y
     }
 
     // FIXME: refactor
@@ -114,15 +141,25 @@ impl Chunk
 
 impl BoundingBox for Chunk
 {
+    /// Only covers the layers holding filled voxels, an empty chunk has a flat box at the bottom of its column
     fn get_aabb(&self) -> AABB
+    {
+        let column = Self::get_column_aabb(self.pos);
+        let (min_y, max_y) = self.occupied_y.map_or((0.0, 0.0), |(min, max)| (min as f32 * VOXEL_SIZE, (max + 1) as f32 * VOXEL_SIZE));
+
+        AABB::new(Vec3::new(column.min.x, min_y, column.min.z), Vec3::new(column.max.x, max_y, column.max.z))
+    }
+}
+
+impl Chunk
+{
+    /// AABB of the chunk at pos, known before its voxels are generated
+    pub fn get_column_aabb(pos: IVec2) -> AABB
     {
         // Calculate the AABB for the chunk
         let size = Vec3::new(CHUNK_SIZE_X as f32, CHUNK_SIZE_Y as f32, CHUNK_SIZE_Z as f32) * VOXEL_SIZE;
-        let world_pos = self.pos_world_space(); // FIXME: may be wrong, test again
-        let ret = AABB::new(world_pos, world_pos + size);
-
-        // println!("AAAB min {} max {}", ret.min, ret.max);
+        let world_pos = Vec3::new((pos.x * CHUNK_SIZE_X as i32) as f32, 0.0, (pos.y * CHUNK_SIZE_Z as i32) as f32); // FIXME: may be wrong, test again
 
-        ret
+        AABB::new(world_pos, world_pos + size)
     }
 }
\ No newline at end of file
@@ -1,22 +1,41 @@
 use core::panic;
-use std::{cell::{RefCell}, rc::Rc, collections::HashMap, sync::{Arc, Mutex}, mem};
+use std::{cell::{RefCell}, rc::Rc, collections::{HashMap, HashSet}, sync::{Arc, mpsc::{channel, Sender, Receiver}}, mem, time::Instant};
 use glam::{Vec3, IVec2, IVec3};
-use crate::{threadpool::ThreadPool, ui::DebugData, engine::chunk::{CHUNK_SIZE_Y, MOORE_NEIGHBORHOOD_OFFSET}, generational_vec::{GenerationalArena, GenerationIndex, ReadLock}};
-use super::{terrain::{PerlinGenerator, TerrainGenerator}, chunk::{Chunk, CHUNK_SIZE_Z, CHUNK_SIZE_X, NEIGHBOR_OFFSET}, geometry::{meshing::{greedy_mesher::GreedyMesher, voxel_fetcher::{FetcherFactory}}, voxel::{Voxel, VoxelType}, voxel_vertex::VoxelVertex, chunk_mesh::{ChunkMesh}}, renderer::allocators::{default_allocator::DefaultAllocator}};
+use crate::{jobs::{JobSystem, JobHandle}, ui::DebugData, settings::Settings, engine::chunk::MOORE_NEIGHBORHOOD_OFFSET, generational_vec::{GenerationalArena, GenerationIndex, GenerationErr, ReadLock}};
+use super::{camera::Camera, frame_budget::FrameBudget, visibility::{self, ChunkVisibility}, terrain::TerrainGenerator, chunk::{Chunk, CHUNK_SIZE_Z, CHUNK_SIZE_X, NEIGHBOR_OFFSET}, geometry::{meshing::{voxel_fetcher::{FetcherFactory}, lod_mesher::{ChunkLod, LOD_SCALES}, chunk_mesher::{MesherType, MesherReport}}, voxel::{Voxel, VoxelType}, voxel_vertex::VoxelVertex, mesh::Mesh, chunk_mesh::{ChunkMesh}}, renderer::allocators::{vertex_pool_allocator::VertexPoolAllocator, MeshAllocator}};
 
 // length are in chunks
-const NO_UPDATE: i32 = 2;
-const VISIBLE: i32 = 10; // engulfes NO_UPDATE_SQUARE
-const NO_VISIBLE_STILL_LOADED: i32 = VISIBLE + 8;
+// the render distances are runtime settings, see Settings, this is the upper bound of the loaded zone the arena is sized for
+pub const MAX_LOADED_DISTANCE: i32 = 32;
 
-// const UPLOAD_LIMIT_FRAME: usize = 10; // maximum number of chunks that can be uploaded per frame
+const JOB_QUEUE_CAPACITY: usize = 256; // generation and meshing jobs waiting for a worker
+const COMPARISON_JOBS: usize = 8; // chunks meshed by every mesher at once, the streaming keeps the rest of the queue
 
-// Needed to be able to pass the generator as a &'static to the spawned threads
-lazy_static!
-{
-    static ref GENERATOR: Box<dyn TerrainGenerator> = Box::new(PerlinGenerator::default());
-    static ref CHUNKS: GenerationalArena<ChunkManageUnit> = GenerationalArena::new((NO_VISIBLE_STILL_LOADED * NO_VISIBLE_STILL_LOADED) as usize * 4);
-}
+// streaming order, see get_streaming_score()
+const OUTSIDE_VIEW_PENALTY: f32 = 2.0; // the chunks outside of the frustum count as this many times farther
+const VELOCITY_WEIGHT: f32 = 0.5; // up to how much closer the chunks ahead of the player count
+const FAST_SPEED: f32 = 40.0; // world units per second, the velocity has its full weight from this speed on
+const VELOCITY_SMOOTHING: f32 = 0.1;
+const REPRIORITIZE_INTERVAL: f32 = 0.25; // s, the waiting jobs are ordered again this often, or as soon as the camera moved a chunk away
+const EDIT_PRIORITY: f32 = f32::MIN; // the meshes of the edited chunks go before everything else
+
+// past this many planes touched by the edits, the chunk is meshed again instead of patched
+const MAX_PATCHED_PLANES: usize = 32;
+
+// the meshes read the voxels of their neighbors up to this far from their borders, on X and Z
+// the faces read the voxels just before the chunk, the Surface Nets one more layer after it and the diagonal voxels around their vertices
+const MESH_READ_BEFORE: i32 = 1;
+const MESH_READ_AFTER: i32 = 2;
+
+// initial size of the vertex pool, in elements, it grows when needed
+const POOL_VERTICES: usize = 1 << 20;
+const POOL_INDICES: usize = POOL_VERTICES / 4 * 6; // the meshes are made of quads
+
+// number of slots of the arena of each chunk manager, enough for the largest loaded zone
+const ARENA_SIZE: usize = ((MAX_LOADED_DISTANCE + 1) * (MAX_LOADED_DISTANCE + 1)) as usize * 2;
+
+/// Storage of the chunks, shared with the worker threads
+pub type ChunkArena = GenerationalArena<ChunkManageUnit>;
 
 pub struct RenderedChunk
 {
@@ -37,28 +56,45 @@ struct ToBeRenderedChunk
     pub index: GenerationIndex,
     pub chunk_pos: IVec2,
     pub sent_to_upload: bool,
-    pub sent_to_mesh: bool,
+    pub mesh_job: Option<JobHandle>, // set once the chunk is sent to be meshed
 }
 
 impl ToBeRenderedChunk
 {
     fn new(index: GenerationIndex, chunk_pos: IVec2) -> Self
     {
-        Self{index, chunk_pos, sent_to_mesh:false, sent_to_upload:false}
+        Self{index, chunk_pos, mesh_job: None, sent_to_upload:false}
     }
 }
 
+/// A voxel written by the player, applied on the next update
+struct VoxelEdit
+{
+    pos: IVec3, // global coord
+    voxel: Voxel,
+    time: Instant,
+}
+
+/// The edits of a chunk not drawn yet
+#[derive(Default)]
+struct EditedChunk
+{
+    version: u32, // number of edits applied to the voxels of the chunk, the meshes are tagged with the version they show
+    voxels: Vec<(u32, IVec3, Instant)>, // the version, position in the chunk and time of the edits newer than the drawn mesh
+}
+
 pub struct ChunkManageUnit // Used only by the chunk manager
 {
     pub chunk: Option<Chunk>,
     pub chunk_mesh: Option<ChunkMesh>,
+    pub next_mesh: Option<ChunkMesh>, // at a new level of detail, waiting for its upload while chunk_mesh is drawn
 }
 
 impl ChunkManageUnit
 {
     pub fn default() -> Self
     {
-        Self{chunk: None, chunk_mesh: None}
+        Self{chunk: None, chunk_mesh: None, next_mesh: None}
     }
 
     pub fn set_chunk(&mut self, chunk: Chunk)
@@ -72,13 +108,31 @@ impl ChunkManageUnit
     }
 }
 
-pub struct ChunkManager
+pub struct ChunkManager<A = VertexPoolAllocator<VoxelVertex>>
 {
-    pub allocator: DefaultAllocator<VoxelVertex>,
-    threadpool: ThreadPool,
-
-    chunks_finished_generation: Arc<Mutex<Vec<Chunk>>>, // chunks that exist here are not necessarily in the chunks list
-    chunks_finished_meshing: Arc<Mutex<Vec<(GenerationIndex, ChunkMesh)>>>,
+    pub allocator: A,
+    jobs: JobSystem,
+
+    chunks: Arc<ChunkArena>,
+    generator: Arc<dyn TerrainGenerator>,
+
+    // completion channels of the jobs, the chunks received are not necessarily in the chunks list anymore
+    generation_sender: Sender<Chunk>,
+    chunks_finished_generation: Receiver<Chunk>,
+    meshing_sender: Sender<(GenerationIndex, Option<ChunkMesh>)>, // None if the meshing could not be done
+    chunks_finished_meshing: Receiver<(GenerationIndex, Option<ChunkMesh>)>,
+    meshes_to_install: Vec<(GenerationIndex, ChunkMesh)>, // received while their unit was locked
+    comparison_sender: Sender<Vec<MesherReport>>, // the reports of one chunk
+    comparisons_finished: Receiver<Vec<MesherReport>>,
+
+    chunks_to_generate: Vec<IVec2>, // registered chunks waiting for room in the job queue
+    generation_jobs: HashMap<IVec2, JobHandle>,
+    remesh_jobs: HashMap<IVec2, (GenerationIndex, JobHandle)>, // rendered chunks meshed again at a new level of detail, with another mesher or after edits
+
+    voxel_edits: Vec<VoxelEdit>, // waiting for the next update, or for their chunk to be unlocked
+    pending_writes: HashMap<IVec2, Vec<VoxelEdit>>, // into chunks not loaded or generated yet, applied once they are generated
+    edited_chunks: HashMap<IVec2, EditedChunk>,
+    edit_time: f32, // ms spent on the main thread for the edits this frame
 
     chunk_map: HashMap<IVec2, GenerationIndex>, // maps IVec2 chunk position -> index into chunks Vec
 
@@ -86,7 +140,9 @@ pub struct ChunkManager
     pub chunks_rendered: Vec<RenderedChunk>,
     chunks_to_be_rendered: Vec<ToBeRenderedChunk>, // temp before chunks are added to the chunks_rendered list
 
-    chunks_to_upload: Vec<GenerationIndex>,
+    chunks_to_upload: Vec<(GenerationIndex, IVec2)>,
+    chunks_to_sort: Vec<IVec2>, // transparent faces waiting to be sorted again, the closest first
+    upload_budget: FrameBudget, // time given each frame to the uploads and re-sorts
 
     // Holds chunks that are not rendered, but are still present in GPU and CPU memory
     // chunks_not_visible: Vec<Rc<RefCell<ChunkManageUnit>>>,
@@ -100,6 +156,22 @@ pub struct ChunkManager
     last_chunks_pos: IVec2, // chunks position in last update
     last_voxel_pos: IVec3, // voxel position in last update, global coord
     last_player_pos: Vec3,
+    last_update: Instant,
+    player_velocity: Vec3, // smoothed, in world units per second
+    last_reprioritize: (Instant, Vec3), // when the waiting jobs were last ordered, and where the camera was
+    reload_needed: bool, // forces a reload of the zones on the next update, even if the player did not move
+
+    // zones around the anchor point, in chunks
+    no_update: i32,
+    visible: i32, // engulfes the no update zone
+    no_visible_still_loaded: i32, // engulfes the visible zone
+    lod_distances: [i32; 3], // distances from the anchor point past which the chunks are meshed at the next level of detail
+    mesher: MesherType,
+
+    // comparison of the meshers, the reports add up as the chunks are meshed
+    mesher_reports: Vec<MesherReport>,
+    chunks_to_compare: Vec<GenerationIndex>,
+    comparison_jobs: usize,
 
     // debug
     debug_data: Rc<RefCell<DebugData>>
@@ -107,41 +179,152 @@ pub struct ChunkManager
 
 impl ChunkManager
 {
-    pub fn new( theadcount: usize, debug_data: &Rc<RefCell<DebugData>>) -> Self
+    pub fn new(settings: &Settings, generator: Box<dyn TerrainGenerator>, debug_data: &Rc<RefCell<DebugData>>) -> Self
     {
+        let allocator = VertexPoolAllocator::new(POOL_VERTICES, POOL_INDICES);
+        Self::with_allocator(settings, generator, allocator, debug_data)
+    }
+}
+
+impl<A> ChunkManager<A>
+    where A: MeshAllocator<VoxelVertex>
+{
+    /// The meshes are handed to the allocator, which does not need to upload them anywhere, ex: headless
+    pub fn with_allocator(settings: &Settings, generator: Box<dyn TerrainGenerator>, allocator: A, debug_data: &Rc<RefCell<DebugData>>) -> Self
+    {
+        let chunks = Arc::new(GenerationalArena::new(ARENA_SIZE));
         let chunk_map = HashMap::new();
 
-        // let allocator = VertexPoolAllocator::new(100*100, 5000, 3000); // TODO: needs adjustment
-        let allocator = DefaultAllocator::new();
         // create the fields
-        let chunks_finished_generation = Arc::new(Mutex::new(Vec::new()));
-        let chunks_finished_meshing = Arc::new(Mutex::new(Vec::new()));
+        let (generation_sender, chunks_finished_generation) = channel();
+        let (meshing_sender, chunks_finished_meshing) = channel();
+        let (comparison_sender, comparisons_finished) = channel();
         let chunks_rendered = Vec::new();
         let chunks_to_be_rendered = Vec::new();
         let chunks_to_upload = Vec::new();
         let chunks_to_unload = Vec::new();
 
-        Self{allocator, chunk_map, chunks_finished_generation, chunks_rendered, chunks_to_be_rendered, last_player_pos: Vec3::ZERO,
-            chunks_to_upload, chunks_to_unload, anchor_point: IVec2::new(i32::MAX, i32::MAX), // anchor point is setup this way to initially trigger a reload in update()
+        Self{allocator, chunks, generator: Arc::from(generator), chunk_map, generation_sender, chunks_finished_generation, meshing_sender, meshes_to_install: Vec::new(), comparison_sender, comparisons_finished,
+            chunks_to_generate: Vec::new(), generation_jobs: HashMap::new(), remesh_jobs: HashMap::new(), voxel_edits: Vec::new(), pending_writes: HashMap::new(), edited_chunks: HashMap::new(), edit_time: 0.0, chunks_rendered, chunks_to_be_rendered, last_player_pos: Vec3::ZERO, last_update: Instant::now(), player_velocity: Vec3::ZERO, last_reprioritize: (Instant::now(), Vec3::ZERO),
+            chunks_to_upload, chunks_to_sort: Vec::new(), upload_budget: FrameBudget::new(settings.target_frame_time), chunks_to_unload, anchor_point: IVec2::new(i32::MAX, i32::MAX), // anchor point is setup this way to initially trigger a reload in update()
             last_chunks_pos: IVec2::ZERO, last_voxel_pos: IVec3::new(i32::MAX, i32::MAX, i32::MAX), // last_voxel_pos to max to force sort on load
-            threadpool: ThreadPool::new(theadcount), debug_data:debug_data.clone(),
-            chunks_finished_meshing}
+            jobs: JobSystem::new(settings.thread_count, JOB_QUEUE_CAPACITY), debug_data:debug_data.clone(),
+            chunks_finished_meshing, reload_needed: false, no_update: settings.no_update_distance, visible: settings.visible_distance,
+            no_visible_still_loaded: settings.loaded_distance, lod_distances: settings.lod_distances, mesher: settings.mesher,
+            mesher_reports: Vec::new(), chunks_to_compare: Vec::new(), comparison_jobs: 0}
+    }
+
+    /// Change the size of the zones around the player, chunks are loaded and unloaded on the next update
+    pub fn set_render_distances(&mut self, no_update: i32, visible: i32, no_visible_still_loaded: i32)
+    {
+        self.no_update = no_update;
+        self.visible = visible;
+        self.no_visible_still_loaded = no_visible_still_loaded.min(MAX_LOADED_DISTANCE);
+        self.reload_needed = true;
+    }
+
+    /// The chunks that changed level are meshed again on the next updates
+    pub fn set_lod_distances(&mut self, lod_distances: [i32; 3])
+    {
+        self.lod_distances = lod_distances;
+    }
+
+    /// The rendered chunks are meshed again on the next updates, their current mesh is drawn until then
+    pub fn set_mesher(&mut self, mesher: MesherType)
+    {
+        self.mesher = mesher;
+    }
+
+    pub fn get_mesher(&self) -> MesherType
+    {
+        self.mesher
+    }
+
+    /// Replace the worker threads, the queued jobs are kept for the new workers
+    pub fn set_thread_count(&mut self, thread_count: usize)
+    {
+        self.jobs.set_thread_count(thread_count);
+    }
+
+    /// Frame time the upload budget adapts to, in ms
+    pub fn set_target_frame_time(&mut self, target_frame_time: f32)
+    {
+        self.upload_budget.set_target_frame_time(target_frame_time);
+    }
+
+    pub fn get_generator(&self) -> &Arc<dyn TerrainGenerator>
+    {
+        &self.generator
+    }
+
+    /// Replace the terrain generator, every chunk is unloaded and generated again on the next update
+    pub fn set_generator(&mut self, generator: Box<dyn TerrainGenerator>)
+    {
+        self.generator = Arc::from(generator);
+
+        for job in self.generation_jobs.values().chain(self.chunks_to_be_rendered.iter().filter_map(|struc| struc.mesh_job.as_ref()))
+            .chain(self.remesh_jobs.values().map(|(_, job)| job))
+        {
+            job.cancel();
+        }
+        self.generation_jobs.clear();
+        self.remesh_jobs.clear();
+
+        // the jobs already running send their results into the old channels, which are dropped
+        (self.generation_sender, self.chunks_finished_generation) = channel();
+        (self.meshing_sender, self.chunks_finished_meshing) = channel();
+        (self.comparison_sender, self.comparisons_finished) = channel();
+        self.meshes_to_install.clear();
+        self.chunks_to_compare.clear();
+        self.comparison_jobs = 0;
+        self.voxel_edits.clear();
+        self.pending_writes.clear();
+        self.edited_chunks.clear();
+
+        self.chunks_to_generate.clear();
+        self.chunks_rendered.clear();
+        self.chunks_to_be_rendered.clear();
+        self.chunks_to_upload.clear();
+        self.chunks_to_sort.clear();
+        self.chunks_to_unload.extend(self.chunk_map.drain().map(|(_, index)| index));
+
+        self.reload_needed = true;
     }
 
     /// Everything related to updating the chunks list, loading new chunks, unloading chunks...
     /// 
     /// Called every frame
-    pub fn update(&mut self , player_pos: Vec3)
+    pub fn update(&mut self , camera: &Camera)
     {
+        let player_pos = camera.get_position();
+
+        // estimate where the player is heading
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_update).as_secs_f32();
+        if elapsed > 0.0 && self.anchor_point.x != i32::MAX
+        {
+            let velocity = (player_pos - self.last_player_pos) / elapsed;
+            self.player_velocity = self.player_velocity.lerp(velocity, VELOCITY_SMOOTHING);
+        }
+        self.last_update = now;
+
+        if self.anchor_point.x != i32::MAX
+        {
+            self.upload_budget.adapt(elapsed * 1000.0);
+        }
+
         self.last_player_pos = player_pos; // update player pos
 
         // in which chunk are we ?
-        let current_chunk = Self::world_to_chunk_coord(player_pos);
+        let current_chunk = ChunkManager::world_to_chunk_coord(player_pos);
 
         // did we change chunks and are now outside the no-update zone ?
-        if (current_chunk.x - self.anchor_point.x).abs() > NO_UPDATE/2 ||  // in x
-                    (current_chunk.y - self.anchor_point.y).abs() > NO_UPDATE/2 // in z
+        if (current_chunk.x - self.anchor_point.x).abs() > self.no_update/2 ||  // in x
+                    (current_chunk.y - self.anchor_point.y).abs() > self.no_update/2 || // in z
+                    self.reload_needed
         {
+            self.reload_needed = false;
+
             // update new anchor point
             self.anchor_point = current_chunk;
             println!("Now in chunk {:?}", self.anchor_point);
@@ -153,63 +336,80 @@ impl ChunkManager
             self.handle_deallocs();
         }
 
-        self.handle_to_be_rendered(player_pos);
+        // order the waiting jobs again for the new view, rebuilding the queue every frame would hold its lock too long
+        let (last_time, last_pos) = self.last_reprioritize;
+        if now.duration_since(last_time).as_secs_f32() >= REPRIORITIZE_INTERVAL || player_pos.distance(last_pos) >= CHUNK_SIZE_X as f32
+        {
+            let velocity = self.player_velocity;
+            self.jobs.reprioritize(|pos| Self::get_job_priority(&self.edited_chunks, camera, velocity, pos));
+            self.last_reprioritize = (now, player_pos);
+        }
 
-        self.handle_transparency_reorders(player_pos);
+        self.handle_generation_submits(camera);
 
-        // check the chunks that have had their voxels generated that are returned from the threadpool
-        if let Ok(mut vec) = self.chunks_finished_generation.try_lock()
-        {
-            for chunk in vec.drain(..)
-            {
-                let pos = chunk.pos_chunk_space();
-                // println!("chunks at pos {} finished generating", pos);
+        self.handle_finished_jobs();
 
-                // add to the list of chunks
-                // if the chunk with the pos is not found, it should have been unloaded while a thread was generating it, dump the result
-                if let Some(index) = self.chunk_map.get(&pos)
-                {
-                    // add the chunk to the unit
-                    CHUNKS.get_mut(*index).unwrap().set_chunk(chunk);
-                }
-            }
-        }
+        self.edit_time = 0.0;
+        self.handle_edits();
 
-        if let Ok(mut vec) = self.chunks_finished_meshing.try_lock()
-        {
-            let mut i = 0 ;
-            while i < vec.len()
-            {
-                let index = vec[i].0;
-                match CHUNKS.get_mut(index)
-                {
-                    Ok(mut lock) =>
-                    {
-                        // println!("mesh installed for chunk at index: {:?}", index);
-                        let unit = vec.remove(i);
-                        lock.chunk_mesh = Some(unit.1);
-                    },
-                    Err(_) => i += 1,
-                }
-            }
-        }
+        self.handle_to_be_rendered(camera);
+
+        self.handle_lod_transitions(camera);
+
+        self.handle_mesher_comparisons(camera);
+
+        self.handle_transparency_reorders(player_pos);
+
+        // the re-sorts and uploads share the budget of the frame, what doesn't fit waits for the next frames
+        let deadline = self.upload_budget.get_deadline(Instant::now());
+        self.handle_transparency_sorts(player_pos, deadline);
 
-        let new_loads = self.handle_chunk_uploads();
+        let new_loads = self.handle_chunk_uploads(camera, deadline);
         if new_loads { self.update_debug(); }
+
+        self.allocator.update();
+
+        let mut debug_data = self.debug_data.borrow_mut();
+        debug_data.upload_budget = self.upload_budget.get_budget();
+        debug_data.queued_jobs = self.jobs.get_num_queued();
+        debug_data.chunks_to_generate = self.chunks_to_generate.len();
+        debug_data.chunks_to_upload = self.chunks_to_upload.len();
+        debug_data.chunks_to_sort = self.chunks_to_sort.len();
+        debug_data.edit_time = self.edit_time;
+        debug_data.pending_edits = self.voxel_edits.len() + self.pending_writes.values().map(Vec::len).sum::<usize>() + self.edited_chunks.values().map(|edited| edited.voxels.len()).sum::<usize>();
     }
 
     pub fn get_rendered_chunks(&self) -> impl Iterator<Item = ReadLock<ChunkManageUnit>>
     {
         self.chunks_rendered.iter().map(|f|
         {
-            CHUNKS.get(f.index).unwrap()
+            self.chunks.get(f.index).unwrap()
         })
     }
 
-    fn register_chunk(chunk_map: &mut HashMap<IVec2,GenerationIndex>, unit: ChunkManageUnit, chunks_pos: IVec2)
+    /// Chunks that can be seen from the camera through the non opaque voxels, within the visible zone
+    pub fn get_reachable_chunks(&self, camera_pos: Vec3) -> HashSet<IVec2>
+    {
+        let (chunk_pos, _) = ChunkManager::get_local_voxel_coord(camera_pos.floor().as_ivec3());
+        let camera_section = visibility::get_section_pos(chunk_pos, camera_pos.y.floor() as i32);
+
+        visibility::get_reachable_chunks(camera_section, self.visible / 2, |pos|
+        {
+            let index = self.chunk_map.get(&pos)?;
+            match self.chunks.get(*index)
+            {
+                // the chunks not meshed yet, or locked, hide nothing
+                Ok(unit) => Some(unit.chunk_mesh.as_ref().map_or(ChunkVisibility::open(), |chunk_mesh| chunk_mesh.visibility)),
+                Err(GenerationErr::Locked) => Some(ChunkVisibility::open()),
+                Err(_) => None,
+            }
+        })
+    }
+
+    fn register_chunk(chunks: &ChunkArena, chunk_map: &mut HashMap<IVec2,GenerationIndex>, unit: ChunkManageUnit, chunks_pos: IVec2)
     {
         // store inside arena
-        match CHUNKS.try_insert(unit)
+        match chunks.try_insert(unit)
         {
             Ok(index) => 
             {
@@ -236,9 +436,9 @@ impl ChunkManager
     fn load_chunks_around_anchor(&mut self)
     {
         // load every chunk that falls within the NOT_VISIBLE square
-        for x in (self.anchor_point.x -NO_VISIBLE_STILL_LOADED/2) .. (self.anchor_point.x + NO_VISIBLE_STILL_LOADED/2 + 1)
+        for x in (self.anchor_point.x -self.no_visible_still_loaded/2) .. (self.anchor_point.x + self.no_visible_still_loaded/2 + 1)
         {
-            for z in (self.anchor_point.y -NO_VISIBLE_STILL_LOADED/2) .. (self.anchor_point.y + NO_VISIBLE_STILL_LOADED/2 + 1)
+            for z in (self.anchor_point.y -self.no_visible_still_loaded/2) .. (self.anchor_point.y + self.no_visible_still_loaded/2 + 1)
             {
                 let pos = IVec2::new(x,z);
                 // check if the chunks have already been created
@@ -247,8 +447,8 @@ impl ChunkManager
                     Some(_) => (), // already loaded, do nothing
                     None => // Needs to be created
                     {
-                        Self::register_chunk(&mut self.chunk_map, ChunkManageUnit::default(), pos);
-                        self.create_chunk(pos,GENERATOR.as_ref());
+                        Self::register_chunk(&self.chunks, &mut self.chunk_map, ChunkManageUnit::default(), pos);
+                        self.chunks_to_generate.push(pos);
                     }
                 };
             }
@@ -281,8 +481,14 @@ impl ChunkManager
             // make sure the chunk is outside the not visible but still loaded zone
             // and we always have the only reference to it
             // it could happen that the chunk is queued in some other list, it will be deallocated on the next pass
-            if Self::chunk_outside(self.anchor_point, NO_VISIBLE_STILL_LOADED, *pos)
+            if Self::chunk_outside(self.anchor_point, self.no_visible_still_loaded, *pos)
             {
+                // no need to generate it anymore
+                if let Some(job) = self.generation_jobs.remove(pos)
+                {
+                    job.cancel();
+                }
+
                 self.chunks_to_unload.push(*index);
                 false
             }
@@ -292,10 +498,12 @@ impl ChunkManager
             }
         });
 
+        self.edited_chunks.retain(|pos, _| self.chunk_map.contains_key(pos));
+
         // unload the chunks
         self.chunks_to_unload.retain(|index|
         {
-            match CHUNKS.try_remove(*index)
+            match self.chunks.try_remove(*index)
             {
                 Ok(unit) => 
                 {
@@ -322,9 +530,9 @@ impl ChunkManager
 
         // populate chunks_render list with chunks that are already uploaded
         // chunks that haven't been uploaded are queued for uploading
-        for x in (self.anchor_point.x -NO_VISIBLE_STILL_LOADED/2) .. (self.anchor_point.x + NO_VISIBLE_STILL_LOADED/2 + 1)
+        for x in (self.anchor_point.x -self.no_visible_still_loaded/2) .. (self.anchor_point.x + self.no_visible_still_loaded/2 + 1)
         {
-            for z in (self.anchor_point.y -NO_VISIBLE_STILL_LOADED/2) .. (self.anchor_point.y + NO_VISIBLE_STILL_LOADED/2 + 1)
+            for z in (self.anchor_point.y -self.no_visible_still_loaded/2) .. (self.anchor_point.y + self.no_visible_still_loaded/2 + 1)
             {
                 let chunk_pos = IVec2::new(x,z);
                 // check if the chunks have already been created
@@ -360,8 +568,8 @@ impl ChunkManager
     /// handles everything related to reordering the transparent faces in the world when the player moves
     fn handle_transparency_reorders(&mut self, player_pos: Vec3)
     {
-        let current_voxel = Self::get_voxel_pos(player_pos);
-        let current_chunk = Self::get_chunk_pos(player_pos);
+        let current_voxel = ChunkManager::get_voxel_pos(player_pos);
+        let current_chunk = ChunkManager::get_chunk_pos(player_pos);
 
         if current_chunk != self.last_chunks_pos
         {
@@ -371,60 +579,176 @@ impl ChunkManager
 
         if current_voxel != self.last_voxel_pos
         {
-            // reorder the faces inside the chunk's moore neighborhood            
-            let index = self.chunk_map.get(&current_chunk).unwrap();
+            // reorder the faces inside the chunk's moore neighborhood, the chunk of the player first
+            // the sorts still waiting were for an older position, they are replaced
+            self.chunks_to_sort.clear();
+            self.chunks_to_sort.push(current_chunk);
+            self.chunks_to_sort.extend(MOORE_NEIGHBORHOOD_OFFSET.iter().map(|offset| *offset + current_chunk));
+        }
 
-            match CHUNKS.get_mut(*index)
+        self.last_chunks_pos = current_chunk;
+        self.last_voxel_pos = current_voxel;
+    }
+
+    /// Sort the transparent faces of the waiting chunks until the deadline, at least one chunk is sorted
+    fn handle_transparency_sorts(&mut self, player_pos: Vec3, deadline: Instant)
+    {
+        let mut sorted = 0;
+        while !self.chunks_to_sort.is_empty() && (sorted == 0 || Instant::now() < deadline)
+        {
+            let pos = self.chunks_to_sort.remove(0);
+
+            // the chunk can be missing if the player is moving too fast accross the world, and the chunk generation can't keep up,
+            // skip the reorder in this case
+            let Some(index) = self.chunk_map.get(&pos) else { continue; };
+
+            if let Ok(mut unit) = self.chunks.get_mut(*index)
             {
-                Ok(mut unit) =>
+                if let Some(chunk_mesh) = unit.chunk_mesh.as_mut()
                 {
-                    if let Some(chunk_mesh) = unit.chunk_mesh.as_mut()
-                    {
-                        chunk_mesh.sort_transparent(player_pos);
-                        Self::realloc(&mut self.allocator, &mut unit);
-                    } // else the chunk mesh is still loading, can't do anything now
+                    chunk_mesh.sort_transparent(player_pos);
+                    Self::realloc(&mut self.allocator, &mut unit);
+                    sorted += 1;
+                } // else the chunk mesh is still loading, it is sorted when uploaded
+            }
+        }
+    }
+
+    /// Send the chunks waiting for room in the job queue to be generated, in streaming order
+    fn handle_generation_submits(&mut self, camera: &Camera)
+    {
+        let score = |pos: IVec2| ChunkManager::get_streaming_score(camera, self.player_velocity, pos);
+
+        // sorted from the last to the first, the first are popped first
+        self.chunks_to_generate.retain(|pos| self.chunk_map.contains_key(pos));
+        self.chunks_to_generate.sort_by(|a, b| score(*b).total_cmp(&score(*a)));
+
+        while let Some(pos) = self.chunks_to_generate.pop()
+        {
+            match self.create_chunk(pos, score(pos))
+            {
+                Some(job) =>
+                {
+                    self.generation_jobs.insert(pos, job);
                 },
+                None => // the queue is full, try again on the next update
+                {
+                    self.chunks_to_generate.push(pos);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Install the chunks and meshes returned by the workers
+    fn handle_finished_jobs(&mut self)
+    {
+        for mut chunk in self.chunks_finished_generation.try_iter()
+        {
+            let pos = chunk.pos_chunk_space();
+            self.generation_jobs.remove(&pos);
+
+            // add to the list of chunks
+            // if the chunk with the pos is not found, it should have been unloaded while a thread was generating it, dump the result
+            if let Some(index) = self.chunk_map.get(&pos)
+            {
+                // the writes made before the generation go over the terrain, ex: decorations
+                for edit in self.pending_writes.remove(&pos).unwrap_or_default()
+                {
+                    chunk.set_voxel(ChunkManager::world_voxel_to_chunk_voxel_coord(pos, edit.pos), edit.voxel);
+                    Self::mark_edited(&mut self.edited_chunks, &self.chunk_map, &edit);
+                }
 
-                // this can happen if the player is moving too fast accross the world, and the chunk generation can't keep up,
-                // skip the reorder in this case
-                Err(_) => return,
+                // add the chunk to the unit
+                self.chunks.get_mut(*index).unwrap().set_chunk(chunk);
             }
+        }
+
+        for (index, chunk_mesh) in self.chunks_finished_meshing.try_iter()
+        {
+            self.remesh_jobs.retain(|_, (job_index, _)| *job_index != index);
 
-            // update neighbors as well
-            for offset in MOORE_NEIGHBORHOOD_OFFSET
+            match chunk_mesh
             {
-                let pos = offset + current_chunk;
-                let index = self.chunk_map.get(&pos).unwrap();
+                Some(chunk_mesh) => self.meshes_to_install.push((index, chunk_mesh)),
+                None => // the meshing failed, it will be sent again
+                {
+                    if let Some(struc) = self.chunks_to_be_rendered.iter_mut().find(|struc| struc.index == index)
+                    {
+                        struc.mesh_job = None;
+                    }
+                }
+            }
+        }
 
-                match CHUNKS.get_mut(*index)
+        let mut i = 0;
+        while i < self.meshes_to_install.len()
+        {
+            match self.chunks.get_mut(self.meshes_to_install[i].0)
+            {
+                Ok(mut unit) =>
                 {
-                    Ok(mut unit) => 
+                    let (index, mut chunk_mesh) = self.meshes_to_install.swap_remove(i);
+
+                    // built before the last installed mesh, which shows newer edits
+                    let latest = unit.next_mesh.as_ref().or(unit.chunk_mesh.as_ref()).map_or(0, |latest| latest.version);
+                    if chunk_mesh.version < latest
                     {
-                        if let Some(chunk_mesh) = unit.chunk_mesh.as_mut()
+                        continue;
+                    }
+
+                    // the edits shown by the mesh are done, its upload ends their latency
+                    if let Some(edited) = self.edited_chunks.get_mut(&unit.chunk.as_ref().unwrap().pos_chunk_space())
+                    {
+                        chunk_mesh.edit_time = edited.voxels.iter().filter(|(version, _, _)| *version <= chunk_mesh.version).map(|(_, _, time)| *time).min();
+                        edited.voxels.retain(|(version, _, _)| *version > chunk_mesh.version);
+                    }
+
+                    // a drawn mesh is replaced once the new one is uploaded
+                    if unit.chunk_mesh.as_ref().is_some_and(|current| current.is_mesh_alloc())
+                    {
+                        if unit.next_mesh.replace(chunk_mesh).is_none()
                         {
-                            chunk_mesh.sort_transparent(player_pos);
-                            Self::realloc(&mut self.allocator, &mut unit);
-                        } // else the chunk mesh is still loading, can't do anything now
-                    },
-                    Err(_) => continue, // skip this neighbor
+                            self.chunks_to_upload.push((index, unit.chunk.as_ref().unwrap().pos_chunk_space()));
+                        }
+                    }
+                    else
+                    {
+                        unit.chunk_mesh = Some(chunk_mesh);
+                    }
+                },
+                Err(GenerationErr::Locked) => i += 1, // try again on the next update
+                Err(GenerationErr::NotPresent) => // unloaded in the meantime
+                {
+                    self.meshes_to_install.swap_remove(i);
                 }
             }
         }
+    }
 
-        self.last_chunks_pos = current_chunk;
-        self.last_voxel_pos = current_voxel;
+    /// The chunk and its Moore neighbors have their voxels, needed for meshing
+    fn is_neighborhood_generated(chunks: &ChunkArena, chunk_map: &HashMap<IVec2,GenerationIndex>, chunk_pos: IVec2) -> bool
+    {
+        std::iter::once(IVec2::ZERO).chain(MOORE_NEIGHBORHOOD_OFFSET).all(|offset|
+        {
+            chunk_map.get(&(chunk_pos + offset))
+                .and_then(|index| chunks.get(*index).ok())
+                .is_some_and(|unit| unit.chunk.is_some())
+        })
     }
 
     // TODO: refactor
-    fn handle_to_be_rendered(&mut self, player_pos: Vec3)
+    fn handle_to_be_rendered(&mut self, camera: &Camera)
     {
+        let player_pos = camera.get_position();
+
         // check for the chunks that are destined to be rendered
 
         self.chunks_to_be_rendered.retain_mut(|struc|
         {
             // check if the chunk is ready to be rendered
 
-            let result = CHUNKS.get(struc.index);
+            let result = self.chunks.get(struc.index);
             if result.is_err() // the chunk is no longer there, must habe been unloaded
             {
                 return false; // remove from list
@@ -435,30 +759,35 @@ impl ChunkManager
             let chunk_pos = struc.chunk_pos;
 
             // has the chunk moved outside the visible zone
-            if Self::chunk_outside(self.anchor_point, VISIBLE, chunk_pos)
+            if Self::chunk_outside(self.anchor_point, self.visible, chunk_pos)
             {
+                if let Some(job) = struc.mesh_job.as_ref()
+                {
+                    job.cancel();
+                }
                 return false;
             }
             
             if unit.chunk_mesh.is_some() && unit.chunk_mesh.as_ref().unwrap().is_mesh_alloc() // chunk can now be rendered
             {
-                Self::add_rendered_chunk(&mut self.chunks_rendered, index, player_pos);
+                Self::add_rendered_chunk(&self.chunks, &mut self.chunks_rendered, index, player_pos);
                 return false;
             }
 
             if unit.chunk_mesh.is_some() && !unit.chunk_mesh.as_ref().unwrap().is_mesh_alloc() && !struc.sent_to_upload
             {
-                self.chunks_to_upload.push(index); // send chunk to be uploaded
+                self.chunks_to_upload.push((index, chunk_pos)); // send chunk to be uploaded
                 struc.sent_to_upload = true;
                 return true;
             }
 
-            if unit.chunk_mesh.is_none() && unit.chunk.is_some() && !struc.sent_to_mesh
+            if unit.chunk_mesh.is_none() && struc.mesh_job.is_none() && Self::is_neighborhood_generated(&self.chunks, &self.chunk_map, chunk_pos)
             {
-                // send the chunk to be meshed
-                Self::create_chunk_mesh(&mut self.chunks_finished_meshing, &self.chunk_map,&self.threadpool,
-                    struc.index);
-                struc.sent_to_mesh = true;
+                // send the chunk to be meshed, stays unsent if the job queue is full
+                let lod = Self::get_chunk_lod(self.anchor_point, &self.lod_distances, chunk_pos);
+                let version = self.edited_chunks.get(&chunk_pos).map_or(0, |edited| edited.version);
+                struc.mesh_job = Self::create_chunk_mesh(&self.chunks, &self.meshing_sender, &self.chunk_map, &self.jobs,
+                    struc.index, chunk_pos, ChunkManager::get_streaming_score(camera, self.player_velocity, chunk_pos), lod, self.mesher, version);
                 return true;
             }
             true 
@@ -466,15 +795,70 @@ impl ChunkManager
 
     }
 
+    /// Mesh again the rendered chunks whose level of detail or mesher changed, their current mesh is drawn until the new one is uploaded
+    fn handle_lod_transitions(&mut self, camera: &Camera)
+    {
+        let mut lod_chunks = [0; LOD_SCALES.len()];
+
+        for rendered in self.chunks_rendered.iter()
+        {
+            let Ok(unit) = self.chunks.get(rendered.index) else { continue };
+            let (Some(chunk), Some(chunk_mesh)) = (unit.chunk.as_ref(), unit.chunk_mesh.as_ref()) else { continue };
+
+            lod_chunks[chunk_mesh.lod.level] += 1;
+
+            let chunk_pos = chunk.pos_chunk_space();
+            let lod = Self::get_chunk_lod(self.anchor_point, &self.lod_distances, chunk_pos);
+            let is_current = |mesh: &ChunkMesh| Self::is_mesh_current(mesh, lod, self.mesher);
+            // a finished mesh waits for the chunk to be unlocked to be installed
+            let is_installing = self.meshes_to_install.iter().any(|(index, mesh)| *index == rendered.index && is_current(mesh));
+            if is_current(chunk_mesh) || unit.next_mesh.as_ref().is_some_and(is_current) || self.remesh_jobs.contains_key(&chunk_pos) || is_installing
+                || !Self::is_neighborhood_generated(&self.chunks, &self.chunk_map, chunk_pos)
+            {
+                continue;
+            }
+
+            // stays unsent if the job queue is full, tried again on the next update
+            let priority = ChunkManager::get_streaming_score(camera, self.player_velocity, chunk_pos);
+            let version = self.edited_chunks.get(&chunk_pos).map_or(0, |edited| edited.version);
+            if let Some(job) = Self::create_chunk_mesh(&self.chunks, &self.meshing_sender, &self.chunk_map, &self.jobs, rendered.index, chunk_pos, priority, lod, self.mesher, version)
+            {
+                self.remesh_jobs.insert(chunk_pos, (rendered.index, job));
+            }
+        }
+
+        self.debug_data.borrow_mut().lod_chunks = lod_chunks;
+    }
+
+    /// The mesh is at the level of detail, and built by the mesher at full resolution, the other levels don't use it
+    fn is_mesh_current(mesh: &ChunkMesh, lod: ChunkLod, mesher: MesherType) -> bool
+    {
+        mesh.lod == lod && (lod.level > 0 || mesh.mesher == mesher)
+    }
+
+    /// Level of detail of the chunk at chunk_pos, and of its seams with the neighbors
+    fn get_chunk_lod(anchor_point: IVec2, lod_distances: &[i32; 3], chunk_pos: IVec2) -> ChunkLod
+    {
+        let get_level = |pos: IVec2| lod_distances.iter().filter(|distance| (pos - anchor_point).abs().max_element() > **distance).count();
+
+        let level = get_level(chunk_pos);
+        if level == 0
+        {
+            // the full resolution meshes ignore their seams, a change of the neighbors must not mesh them again
+            return ChunkLod::default();
+        }
+        ChunkLod{level, seams: NEIGHBOR_OFFSET.map(|offset| Some(get_level(chunk_pos + offset)).filter(|neighbor_level| *neighbor_level != level))}
+    }
+
     /// Add the chunks to the list of rendered chunks
-    fn add_rendered_chunk(rendered_list: &mut Vec<RenderedChunk>, index: GenerationIndex, center: Vec3)
+    fn add_rendered_chunk(chunks: &ChunkArena, rendered_list: &mut Vec<RenderedChunk>, index: GenerationIndex, center: Vec3)
     {
         // the chunks must be added in order into the rendered list
         // rendered from back to front
         let mut wrapper = RenderedChunk::new(index);
 
         // calculate the distance from the camera
-        wrapper.distance = center.distance(CHUNKS.get(index).unwrap().chunk.as_ref().unwrap().pos_world_space()); // TODO: consider using taxi cab distance with x y only
+        wrapper.distance = center.distance(chunks.get(index).unwrap().chunk.as_ref().unwrap().pos_world_space()); // TODO: consider using taxi cab distance with x y only
 
         // find the index at which we must insert = index of the first chunks that has a smaller distance
         let mut index = 0;
@@ -502,7 +886,7 @@ impl ChunkManager
         // re-calculate all the chunk distances from the center's POV
         for wrapper in self.chunks_rendered.iter_mut()
         {
-            wrapper.distance = center.distance(CHUNKS.get(wrapper.index).unwrap().chunk.as_ref().unwrap().pos_world_space());
+            wrapper.distance = center.distance(self.chunks.get(wrapper.index).unwrap().chunk.as_ref().unwrap().pos_world_space());
         }
 
         // back to front
@@ -516,27 +900,6 @@ impl ChunkManager
         // }
     }
 
-    /// Transforms from world coordinates to Chunk coordinates
-    pub fn world_to_chunk_coord(pos: Vec3) -> IVec2
-    {
-        let chunk_x = pos.x as i32 / CHUNK_SIZE_X as i32;
-        let chunk_z = pos.z as i32 / CHUNK_SIZE_Z as i32;
-        IVec2::new(chunk_x,chunk_z)
-    }
-
-    pub fn voxel_to_chunk_coord(pos: IVec3) -> IVec2
-    {
-        let chunk_x = pos.x / CHUNK_SIZE_X as i32;
-        let chunk_z = pos.z / CHUNK_SIZE_Z as i32;
-        IVec2::new(chunk_x, chunk_z)
-    }
-
-    /// assumes that the voxel is indeed inside the chunk given as pos
-    pub fn world_voxel_to_chunk_voxel_coord(chunk_pos: IVec2, voxel_world_pos: IVec3) -> IVec3
-    {
-        voxel_world_pos - IVec3::new(chunk_pos.x * CHUNK_SIZE_X as i32, 0 , chunk_pos.y * CHUNK_SIZE_Z as i32)
-    } 
-
     /// Checks if the chunk at position "checked_pos" is outside the square of center "center" and side length "length", if yes, the action() is applied
     fn chunk_outside (center: IVec2, length: i32, checked_pos: IVec2) -> bool
     {
@@ -544,25 +907,60 @@ impl ChunkManager
         (checked_pos.y - center.y).abs() > length/2 // in z
     }
 
-    /// Checks the to load list for any chunks to be loaded and loads them
-    fn handle_chunk_uploads(&mut self) -> bool
+    /// Checks the to load list for any chunks to be loaded and loads them, in streaming order
+    /// Upload the waiting meshes, closest first, until the deadline, at least one mesh is uploaded
+    fn handle_chunk_uploads(&mut self, camera: &Camera, deadline: Instant) -> bool
     {
         let mut new_loads = false;
 
+        let velocity = self.player_velocity;
+        let priority = |(index, pos): &(GenerationIndex, IVec2)|
+        {
+            // the meshes of the edits go first
+            let is_edited = self.chunks.get(*index).is_ok_and(|unit| unit.next_mesh.as_ref().is_some_and(|next_mesh| next_mesh.edit_time.is_some()));
+            if is_edited { EDIT_PRIORITY } else { ChunkManager::get_streaming_score(camera, velocity, *pos) }
+        };
+        self.chunks_to_upload.sort_by(|a, b| priority(a).total_cmp(&priority(b)));
+
         let mut i = 0;
-        while i < self.chunks_to_upload.len()
+        while i < self.chunks_to_upload.len() && (!new_loads || Instant::now() < deadline)
         {
-            let index = self.chunks_to_upload[i];
+            let (index, _) = self.chunks_to_upload[i];
 
-            match CHUNKS.get_mut(index)
+            match self.chunks.get_mut(index)
             {
                 Ok(mut unit) =>
                 {
                     new_loads = true;
-                    Self::alloc_chunk_mesh(&mut self.allocator, unit.chunk_mesh.as_mut().unwrap());
+
+                    // the mesh at the new level of detail replaces the drawn one
+                    if let Some(mut next_mesh) = unit.next_mesh.take()
+                    {
+                        let start = Instant::now();
+                        let edit_time = next_mesh.edit_time.take();
+
+                        next_mesh.sort_transparent(camera.get_position());
+                        Self::alloc_chunk_mesh(&mut self.allocator, &mut next_mesh);
+                        if let Some(mut chunk_mesh) = unit.chunk_mesh.replace(next_mesh)
+                        {
+                            Self::dealloc_chunk_mesh(&mut self.allocator, &mut chunk_mesh);
+                        }
+
+                        // the edits are now drawn
+                        if let Some(edit_time) = edit_time
+                        {
+                            self.edit_time += start.elapsed().as_secs_f32() * 1000.0;
+                            self.debug_data.borrow_mut().edit_latency = edit_time.elapsed().as_secs_f32() * 1000.0;
+                        }
+                    }
+                    else if let Some(chunk_mesh) = unit.chunk_mesh.as_mut().filter(|chunk_mesh| !chunk_mesh.is_mesh_alloc())
+                    {
+                        Self::alloc_chunk_mesh(&mut self.allocator, chunk_mesh);
+                    }
                     self.chunks_to_upload.remove(i);
                 },
-                Err(_) => i += 1,
+                Err(GenerationErr::Locked) => i += 1,
+                Err(_) => { self.chunks_to_upload.remove(i); }, // unloaded while waiting
             }
         }
 
@@ -576,7 +974,7 @@ impl ChunkManager
         // is this chunk loaded
         if let Some(index) = self.chunk_map.get(&chunk_pos)
         {
-            let unit = CHUNKS.get(*index).unwrap();
+            let unit = self.chunks.get(*index).unwrap();
 
             if let Some(chunk) = unit.chunk.as_ref()
             {
@@ -593,120 +991,134 @@ impl ChunkManager
         }
     }
 
-    /// determines which chunk this voxel belongs to, and it's coordinates within that chunk
-    // TODO: rewrite this mess
-    pub fn get_local_voxel_coord(pos: IVec3) -> (IVec2,IVec3)
+    /// Re-mesh all the chunks in the world and upload them
+    pub fn rebuild_chunk_meshes(&mut self)
     {
-        let (chunk_pos_x , voxel_pos_x) = Self::adjust_direction(pos.x, CHUNK_SIZE_X);
-        let (chunk_pos_z, voxel_pos_z) = Self::adjust_direction(pos.z, CHUNK_SIZE_Z);
-        let voxel_pos_y = pos.y;
+        for (chunk_pos, index) in self.chunk_map.iter()
+        {
+            let lod = Self::get_chunk_lod(self.anchor_point, &self.lod_distances, *chunk_pos);
+            let edited = self.edited_chunks.entry(*chunk_pos).or_default();
+            Self::refresh_mesh(&self.chunks, &mut self.allocator, *index, &self.chunk_map, self.last_player_pos, lod, self.mesher, edited.version);
+            edited.voxels.clear();
+        }
+    }
 
-        (IVec2::new(chunk_pos_x,chunk_pos_z),IVec3::new(voxel_pos_x,voxel_pos_y,voxel_pos_z))
+    /// Get the number of chunks that are currently rendered
+    pub fn get_num_chunks_to_render(&self) -> usize
+    {
+        self.chunks_rendered.len()
     }
 
-    pub fn adjust_direction(pos:i32, chunk_size: usize) -> (i32,i32)
+    /// Every chunk of the visible zone is generated, meshed and allocated, and the edits of the meshed chunks are drawn
+    pub fn is_fully_loaded(&self) -> bool
     {
-        let chunk_pos;
-        let voxel_pos;
+        // the chunks out of the visible zone are not meshed, their edits show once they are
+        let edits_drawn = self.voxel_edits.is_empty() && self.edited_chunks.iter().all(|(pos, edited)| edited.voxels.is_empty()
+            || self.chunk_map.get(pos).and_then(|index| self.chunks.get(*index).ok()).is_some_and(|unit| unit.chunk_mesh.is_none()));
 
-        if pos < 0
-        {
-            chunk_pos = ((pos+1) / chunk_size as i32) - 1;
-            voxel_pos = pos - chunk_pos * chunk_size as i32;
-        }
-        else
-        {
-            chunk_pos = pos / chunk_size as i32;
-            voxel_pos = pos - chunk_pos * chunk_size as i32;
-        }
+        // the meshes of another mesher are built again on the next update
+        let meshers_current = self.chunks_rendered.iter().filter_map(|rendered| self.chunks.get(rendered.index).ok())
+            .all(|unit| unit.chunk_mesh.as_ref().is_none_or(|chunk_mesh| Self::is_mesh_current(chunk_mesh, chunk_mesh.lod, self.mesher)));
 
-        (chunk_pos,voxel_pos)
+        self.anchor_point.x != i32::MAX && !self.reload_needed && self.chunks_to_be_rendered.is_empty() && self.chunks_to_upload.is_empty() && self.remesh_jobs.is_empty()
+            && edits_drawn && meshers_current
     }
 
-    // TODO: refactor
-    /// Transforms from world coordinates to chunk coordinates
-    pub fn get_chunk_pos(pos: Vec3) -> IVec2
+    /// Index of the allocation holding the mesh of the chunk, None if the chunk or its mesh is not there
+    pub fn get_mesh_alloc_index(&self, chunk_pos: IVec2) -> Option<u32>
     {
-        // in what chunk is this voxel ?
-        let mut pos_x = pos.x as i32 / CHUNK_SIZE_X as i32;
-        if pos.x < 0.0 {pos_x -= 1;} // if we are < 0 along this axis, the chunk coordinate is -= 1 what we have calculated
-        // since it takes +CHUNK_SIZE_X to be in chunk (1,0) whereas it takes just -1 to in chunk(-1,0) and -CHUNK_SIZE_X to be in chunk (-2,0)
-        let mut pos_z = pos.z as i32 / CHUNK_SIZE_Z as i32;
-        if pos.z < 0.0 {pos_z -= 1;}
-
-        IVec2::new(pos_x,pos_z)
+        let unit = self.chunks.get(*self.chunk_map.get(&chunk_pos)?).ok()?;
+        let index = unit.chunk_mesh.as_ref()?.mesh.alloc_token.as_ref()?.index;
+        Some(index)
     }
 
-    // from a point in world coordinate to world voxel coordinates
-    pub fn get_voxel_pos(pos: Vec3) -> IVec3
+    /// Level of detail of the mesh drawn for the chunk
+    pub fn get_mesh_lod(&self, chunk_pos: IVec2) -> Option<ChunkLod>
     {
-        let pos_x = if pos.x < 0.0 {pos.x.floor() -1.0} else {pos.x.floor()};
-        let pos_y = pos.y.floor();
-        let pos_z = if pos.z < 0.0 {pos.z.floor() -1.0} else {pos.z.floor()};
-
-        IVec3::new(pos_x as i32,pos_y as i32,pos_z as i32)
+        let unit = self.chunks.get(*self.chunk_map.get(&chunk_pos)?).ok()?;
+        Some(unit.chunk_mesh.as_ref()?.lod)
     }
 
-    pub fn chunk_to_world_coord(chunk_pos: IVec2) -> IVec3
+    /// Mesh the rendered chunks with every mesher at full resolution on the workers, the reports add up as the chunks are meshed
+    ///
+    /// The chunks being written to are left out
+    pub fn compare_meshers(&mut self)
     {
-        IVec3::new(chunk_pos.x * CHUNK_SIZE_X as i32, 0, chunk_pos.y * CHUNK_SIZE_Z as i32)
+        self.mesher_reports = MesherType::ALL.iter().map(|mesher| MesherReport{mesher: *mesher, num_chunks: 0, num_vertices: 0, num_triangles: 0, mesh_time: 0.0}).collect();
+        self.chunks_to_compare = self.chunks_rendered.iter().map(|rendered| rendered.index).collect();
     }
 
-    /// Re-mesh all the chunks in the world and upload them
-    pub fn rebuild_chunk_meshes(&mut self)
+    /// The reports of the last comparison of the meshers, partial until is_comparing_meshers() is false
+    pub fn get_mesher_reports(&self) -> &[MesherReport]
     {
-        for index in self.chunk_map.values()
-        {
-            Self::refresh_mesh(&mut self.allocator, *index, &self.chunk_map, self.last_player_pos);
-        }
+        &self.mesher_reports
     }
 
-    /// Get the number of chunks that are currently rendered
-    pub fn get_num_chunks_to_render(&self) -> usize
+    pub fn is_comparing_meshers(&self) -> bool
     {
-        self.chunks_rendered.len()
+        !self.chunks_to_compare.is_empty() || self.comparison_jobs > 0
     }
 
-    /// Sets the voxel and refreshed the mesh
-    fn chunk_set_voxel(&mut self, chunk_pos: IVec2, voxel_pos: IVec3, new_voxel: Voxel)
+    /// Add up the reports of the compared chunks, and send the next ones to the workers
+    fn handle_mesher_comparisons(&mut self, camera: &Camera)
     {
-        // is the chunk present ?
-        let index = self.chunk_map.get(&chunk_pos); // y is actually z
-
-        if index.is_none() // chunk is not there
+        for reports in self.comparisons_finished.try_iter()
         {
-            println!("chunk is not here!");
-            return;
+            self.comparison_jobs -= 1;
+            for (total, report) in self.mesher_reports.iter_mut().zip(reports)
+            {
+                total.num_chunks += report.num_chunks;
+                total.num_vertices += report.num_vertices;
+                total.num_triangles += report.num_triangles;
+                total.mesh_time += report.mesh_time;
+            }
         }
 
-        let index = index.unwrap();
-    
+        while self.comparison_jobs < COMPARISON_JOBS
         {
-            let mut unit = CHUNKS.get_mut(*index).unwrap();
-            unit.chunk.as_mut().unwrap().set_voxel(voxel_pos, new_voxel);
-        } // makes rust drop the write lock
+            let Some(index) = self.chunks_to_compare.pop() else { break };
 
-        self.refresh_chunk(*index);
-    }
+            // the chunks unloaded since are left out
+            let Some(chunk_pos) = self.chunks.get(index).ok().and_then(|unit| Some(unit.chunk.as_ref()?.pos_chunk_space())) else { continue };
+            if !Self::is_neighborhood_generated(&self.chunks, &self.chunk_map, chunk_pos)
+            {
+                continue;
+            }
 
-    /// Simply re-mesh and re-upload the chunk
-    fn refresh_chunk(&mut self, index: GenerationIndex)
-    {
-        Self::refresh_mesh(&mut self.allocator, index, &self.chunk_map, self.last_player_pos);
+            let factory = Self::get_fetcher_factory(&self.chunks, index, &self.chunk_map);
+            let priority = Self::get_job_priority(&self.edited_chunks, camera, self.player_velocity, chunk_pos);
+            if self.jobs.submit(priority, chunk_pos, &self.comparison_sender, move || Some(ChunkManager::compare_chunk(&factory))).is_none()
+            {
+                self.chunks_to_compare.push(index);
+                break;
+            }
+            self.comparison_jobs += 1;
+        }
     }
 
     /// Places the voxel adjacent to the <face> of the voxel at <pos>
+    ///
+    /// The voxel is set on the next update, its chunk is meshed again on a worker, its current mesh is drawn until then
     pub fn place_voxel(&mut self, pos: IVec3, face: IVec3)
     {
         println!("place voxel on pos {} called!", pos);
         // get the voxel adjacent ot the face
         let voxel_pos = pos + face;
-        let (chunk_pos,voxel_pos) = ChunkManager::get_local_voxel_coord(voxel_pos);
-        
-        self.chunk_set_voxel(chunk_pos, voxel_pos, Voxel::new(VoxelType::Glass));
+
+        self.set_voxels([(voxel_pos, Voxel::new(VoxelType::Glass))]);
+    }
+
+    /// Write the voxels at their global positions, ex: the structures or decorations placed over the terrain
+    ///
+    /// Like place_voxel(), every chunk whose mesh reads one of the voxels is meshed again.
+    /// The writes into chunks not loaded or generated yet are applied over their terrain once they are generated
+    pub fn set_voxels(&mut self, voxels: impl IntoIterator<Item = (IVec3, Voxel)>)
+    {
+        let time = Instant::now();
+        self.voxel_edits.extend(voxels.into_iter().map(|(pos, voxel)| VoxelEdit{pos, voxel, time}));
     }
 
-    pub fn dealloc_chunk_mesh(allocator: &mut DefaultAllocator<VoxelVertex>,chunk_mesh: &mut ChunkMesh)
+    pub fn dealloc_chunk_mesh(allocator: &mut A, chunk_mesh: &mut ChunkMesh)
     {
         if let Some(token) = chunk_mesh.mesh.release_token()
         {
@@ -714,81 +1126,141 @@ impl ChunkManager
         }
     }
 
-    pub fn alloc_chunk_mesh(allocator: &mut DefaultAllocator<VoxelVertex>, chunk_mesh: &mut ChunkMesh)
+    pub fn alloc_chunk_mesh(allocator: &mut A, chunk_mesh: &mut ChunkMesh)
     {
-        allocator.alloc(&mut chunk_mesh.mesh);
+        let num_transparent = chunk_mesh.get_num_trans_indices();
+        allocator.alloc_with_transparent(&mut chunk_mesh.mesh, num_transparent);
     }
 
     /// Dealloc, Rebuild, Allocate mesh
-    pub fn refresh_mesh(allocator: &mut DefaultAllocator<VoxelVertex>, index: GenerationIndex, chunk_map: &HashMap<IVec2,GenerationIndex>, player_pos: Vec3)
+    #[allow(clippy::too_many_arguments)]
+    pub fn refresh_mesh(chunks: &Arc<ChunkArena>, allocator: &mut A, index: GenerationIndex, chunk_map: &HashMap<IVec2,GenerationIndex>, player_pos: Vec3, lod: ChunkLod, mesher: MesherType, version: u32)
     {
         {
-            let mut unit = CHUNKS.get_mut(index).unwrap();
+            let mut unit = chunks.get_mut(index).unwrap();
             let mut chunk_mesh = unit.chunk_mesh.take().unwrap();
             Self::dealloc_chunk_mesh(allocator, &mut chunk_mesh);
+            unit.next_mesh = None; // built from the old voxels
         } // write lock dropped here
 
-        let factory = Self::get_fetcher_factory(index, chunk_map);
-        let mut chunk_mesh = ChunkMesh::new::<GreedyMesher>(factory.get_fetcher().unwrap());
+        let factory = Self::get_fetcher_factory(chunks, index, chunk_map);
+        let mut chunk_mesh = ChunkMesh::with_lod(factory.get_fetcher().unwrap(), lod, mesher);
+        chunk_mesh.version = version;
         chunk_mesh.sort_transparent(player_pos);
 
         Self::alloc_chunk_mesh(allocator, &mut chunk_mesh);
-        let mut unit = CHUNKS.get_mut(index).unwrap();
+        let mut unit = chunks.get_mut(index).unwrap();
         unit.chunk_mesh = Some(chunk_mesh);
     }
 
     /// Dealloc then Realloc
-    pub fn realloc(allocator: &mut DefaultAllocator<VoxelVertex>, unit: &mut ChunkManageUnit)
+    pub fn realloc(allocator: &mut A, unit: &mut ChunkManageUnit)
     {
         let chunk_mesh = unit.chunk_mesh.as_mut().unwrap();
         Self::dealloc_chunk_mesh(allocator, chunk_mesh);
         Self::alloc_chunk_mesh(allocator, chunk_mesh);
     }
 
-    // TODO: refactor this shit
+    /// Removes the voxel at <pos> on the next update, like place_voxel()
     pub fn remove_voxel(&mut self, pos: IVec3)
     {
         println!("Remove voxel on pos:{} called", pos);
-        let (chunk_pos,voxel_pos) = ChunkManager::get_local_voxel_coord(pos);
+        self.set_voxels([(pos, Voxel::new(VoxelType::Air))]);
+    }
 
-        println!("Voxel will be removed from chunk {} voxel pos: {}", chunk_pos, voxel_pos);
+    /// Apply the edits of the voxels, and send the edited chunks to be meshed again
+    ///
+    /// A chunk waits for its previous mesh to be done, the edits made meanwhile are meshed together
+    fn handle_edits(&mut self)
+    {
+        let start = Instant::now();
 
-        let new_voxel = Voxel::new(VoxelType::Air);
+        // the edits of a locked chunk, or of a chunk not generated yet, wait for the next update
+        // and so do the next edits of the chunk to keep their order
+        let mut waiting = HashSet::new();
+        let edits = mem::take(&mut self.voxel_edits);
+        for edit in edits
+        {
+            let (chunk_pos, voxel_pos) = ChunkManager::get_local_voxel_coord(edit.pos);
+            if waiting.contains(&chunk_pos)
+            {
+                self.voxel_edits.push(edit);
+                continue;
+            }
 
-        self.chunk_set_voxel(chunk_pos, voxel_pos, new_voxel);
+            let result = self.chunk_map.get(&chunk_pos).map(|index| self.chunks.get_mut(*index));
+            match result
+            {
+                Some(Ok(mut unit)) if unit.chunk.is_some() => unit.chunk.as_mut().unwrap().set_voxel(voxel_pos, edit.voxel),
+                Some(Err(GenerationErr::Locked)) =>
+                {
+                    waiting.insert(chunk_pos);
+                    self.voxel_edits.push(edit);
+                    continue;
+                },
+                _ => // applied once the chunk is generated
+                {
+                    self.pending_writes.entry(chunk_pos).or_default().push(edit);
+                    continue;
+                },
+            }
 
-        let mut chunk_dir = IVec2::ZERO;
+            Self::mark_edited(&mut self.edited_chunks, &self.chunk_map, &edit);
+        }
 
-        // if the voxel is a the chunk-chunk boundary, the other chunk has to be rebuilt as well
-        if voxel_pos.x == 0 || voxel_pos.x == CHUNK_SIZE_X as i32 -1 || voxel_pos.z == 0 || voxel_pos.z == CHUNK_SIZE_Z as i32 -1
+        // one job at a time for each chunk, patched from its latest mesh
+        for (chunk_pos, edited) in self.edited_chunks.iter()
         {
-            if voxel_pos.x == 0
+            let Some(index) = self.chunk_map.get(chunk_pos).copied() else { continue };
+            if edited.voxels.is_empty() || self.remesh_jobs.contains_key(chunk_pos) || self.meshes_to_install.iter().any(|(installed, _)| *installed == index)
+                || !Self::is_neighborhood_generated(&self.chunks, &self.chunk_map, *chunk_pos)
             {
-                chunk_dir.x = -1
+                continue;
             }
-            else if voxel_pos.x == CHUNK_SIZE_X as i32 - 1
+
+            let Ok(unit) = self.chunks.get(index) else { continue };
+            let Some(base) = unit.next_mesh.as_ref().or(unit.chunk_mesh.as_ref()) else { continue }; // meshed with the edits once in the visible zone
+
+            let voxels: Vec<IVec3> = edited.voxels.iter().filter(|(version, _, _)| *version > base.version).map(|(_, pos, _)| *pos).collect();
+            if voxels.is_empty()
             {
-                chunk_dir.x = 1
+                continue;
             }
-            else if voxel_pos.y == 0
+
+            let lod = Self::get_chunk_lod(self.anchor_point, &self.lod_distances, *chunk_pos);
+            let job = if base.is_patchable() && base.lod == lod && base.mesher == self.mesher && ChunkMesh::get_edited_planes(&voxels).len() <= MAX_PATCHED_PLANES
             {
-                chunk_dir.y = -1;
+                let factory = Self::get_fetcher_factory(&self.chunks, index, &self.chunk_map);
+                let base = base.mesh.vertices.clone();
+                let (mesher, version) = (self.mesher, edited.version);
+
+                self.jobs.submit(EDIT_PRIORITY, *chunk_pos, &self.meshing_sender, move ||
+                {
+                    // fails if one of the chunks is being written to
+                    let chunk_mesh = factory.get_fetcher().map(|fetcher|
+                    {
+                        let mut chunk_mesh = ChunkMesh::patched(fetcher, &base, &voxels, mesher);
+                        chunk_mesh.version = version;
+                        chunk_mesh
+                    });
+                    Some((index, chunk_mesh))
+                })
             }
-            else if voxel_pos.y == CHUNK_SIZE_Y as i32 - 1
+            else
             {
-                chunk_dir.y = 1;
-            }
-    
-            let neighbor_pos = chunk_pos + chunk_dir;
+                drop(unit);
+                Self::create_chunk_mesh(&self.chunks, &self.meshing_sender, &self.chunk_map, &self.jobs, index, *chunk_pos, EDIT_PRIORITY, lod, self.mesher, edited.version)
+            };
 
-            println!("chunk as pos {} will be rebuilt as well", neighbor_pos);
-            // is the chunk present ?
-            if let Some(index) = self.chunk_map.get(&neighbor_pos) // y is actually z
+            // stays unsent if the job queue is full, tried again on the next update
+            if let Some(job) = job
             {
-                self.refresh_chunk(*index);
+                self.remesh_jobs.insert(*chunk_pos, (index, job));
             }
         }
-    }   
+
+        self.edit_time += start.elapsed().as_secs_f32() * 1000.0;
+    }
 
     /// Get the voxel irrespective of which chunk it is in
     // pub fn world_get_voxel(chunks: HashMap<IVec2, Arc<RefCell<ChunkManageUnit>>>, pos: IVec3) -> Option<Voxel>
@@ -805,65 +1277,87 @@ impl ChunkManager
 
     /// Inits the voxels for chunks using the generator, and then appends them to the general list of chunks
     /// 
-    /// Uses a threadpool
+    /// Uses the job system, None if the job queue is full
     /// 
     /// ### Note: Does not Upload the mesh
-    fn create_chunk(&self, chunk_pos: IVec2, generator: &'static dyn TerrainGenerator)
+    fn create_chunk(&self, chunk_pos: IVec2, priority: f32) -> Option<JobHandle>
     {
-        let vec = Arc::clone(&self.chunks_finished_generation);
-        
-        self.threadpool.execute(move ||
+        let generator = Arc::clone(&self.generator);
+
+        self.jobs.submit(priority, chunk_pos, &self.generation_sender, move ||
         {
-            let chunk = Chunk::new(chunk_pos, generator);
-            // append the chunk to the list of chunks to be loaded
-            vec.lock().unwrap().push(chunk);
-        });
+            Some(Chunk::new(chunk_pos, generator.as_ref()))
+        })
+    }
+
+    /// The chunks whose meshes read the voxel of the edit are meshed again, the loaded ones
+    fn mark_edited(edited_chunks: &mut HashMap<IVec2, EditedChunk>, chunk_map: &HashMap<IVec2,GenerationIndex>, edit: &VoxelEdit)
+    {
+        for pos in ChunkManager::get_dependent_chunks(edit.pos).into_iter().filter(|pos| chunk_map.contains_key(pos))
+        {
+            let edited = edited_chunks.entry(pos).or_default();
+            edited.version += 1;
+            edited.voxels.push((edited.version, edit.pos - ChunkManager::chunk_to_world_coord(pos), edit.time));
+        }
     }
 
     /// Constructs the mesh for chunks
     /// 
-    /// Uses a threadpool
+    /// Uses the job system, None if the job queue is full
     /// 
     /// ### Note: Does not Upload the mesh
-    fn create_chunk_mesh(to_add: &mut Arc<Mutex<Vec<(GenerationIndex, ChunkMesh)>>>, chunk_map: &HashMap<IVec2,GenerationIndex>, threadpool: &ThreadPool, chunk_index: GenerationIndex)
+    /// The mesh is tagged with the version of the edits of the chunk
+    #[allow(clippy::too_many_arguments)]
+    fn create_chunk_mesh(chunks: &Arc<ChunkArena>, completion: &Sender<(GenerationIndex, Option<ChunkMesh>)>, chunk_map: &HashMap<IVec2,GenerationIndex>, jobs: &JobSystem,
+        chunk_index: GenerationIndex, chunk_pos: IVec2, priority: f32, lod: ChunkLod, mesher: MesherType, version: u32) -> Option<JobHandle>
     {
-        // To generate the mesh of a chunk, not only do we need the voxels of the Chunk, but the voxels of its Von Neumann neighbors as well
+        // To generate the mesh of a chunk, not only do we need the voxels of the Chunk, but the voxels of its Moore neighbors as well
         // We could have resorted to only using the voxels of the current chunk and assumed that the neighboring voxels are Air voxels, which will cause the outer faces to be generated
         // This will produce a problem with transparent voxels such as water where a water body which crosses Chunk boundaries will have "Water Walls" appearing inside the body, where a chunk boundary occurs
         // Assuming that the neighboring voxels are solid to avoid generating the outer faces will incur other problems
 
-        // we will pass 5 generational indices into the thread, that of the center chunk and the 4 Von Neumann neighbors
-        let vec = Arc::clone(to_add);
-        let factory = Self::get_fetcher_factory(chunk_index, chunk_map);
+        // we will pass 9 generational indices into the thread, that of the center chunk and the 8 Moore neighbors
+        let factory = Self::get_fetcher_factory(chunks, chunk_index, chunk_map);
 
-        threadpool.execute(move || 
+        jobs.submit(priority, chunk_pos, completion, move ||
         {
-            match factory.get_fetcher()
+            // fails if one of the chunks is being written to
+            let chunk_mesh = factory.get_fetcher().map(|fetcher|
             {
-                Some(fetcher) =>
-                {
-                    let mesh = ChunkMesh::new::<GreedyMesher>(fetcher);
-        
-                    vec.lock().unwrap().push((chunk_index, mesh)); // put the generated mesh into the list
-                },
-                None => println!("Thread meshing halted"),
-            }
-        });
+                let mut chunk_mesh = ChunkMesh::with_lod(fetcher, lod, mesher);
+                chunk_mesh.version = version;
+                chunk_mesh
+            });
+            Some((chunk_index, chunk_mesh))
+        })
     }
 
-    fn get_fetcher_factory(chunk_index: GenerationIndex, chunk_map: &HashMap<IVec2,GenerationIndex>) -> FetcherFactory
+    fn get_fetcher_factory(chunks: &Arc<ChunkArena>, chunk_index: GenerationIndex, chunk_map: &HashMap<IVec2,GenerationIndex>) -> FetcherFactory
     {
-        let mut indices: [GenerationIndex; 5] = unsafe { mem::MaybeUninit::zeroed().assume_init()} ; // center + neighbor order as specified in chunk
+        let mut indices: [GenerationIndex; 9] = unsafe { mem::MaybeUninit::zeroed().assume_init()} ; // center + Moore neighbor order as specified in chunk
 
-        let chunk_pos = CHUNKS.get(chunk_index).unwrap().chunk.as_ref().unwrap().pos_chunk_space();
+        let chunk_pos = chunks.get(chunk_index).unwrap().chunk.as_ref().unwrap().pos_chunk_space();
         indices[0] = chunk_index;
-        for (index, offset) in NEIGHBOR_OFFSET.iter().enumerate()
+        for (index, offset) in MOORE_NEIGHBORHOOD_OFFSET.iter().enumerate()
         {
             let neighbor_pos = *offset + chunk_pos;
             indices[index+1] = *chunk_map.get(&neighbor_pos).unwrap();
         }
 
-        FetcherFactory::new(indices, &CHUNKS)
+        FetcherFactory::new(indices, Arc::clone(chunks))
+    }
+
+    /// The edited chunks are meshed first, then the chunks in streaming order
+    fn get_job_priority(edited_chunks: &HashMap<IVec2, EditedChunk>, camera: &Camera, velocity: Vec3, chunk_pos: IVec2) -> f32
+    {
+        if edited_chunks.get(&chunk_pos).is_some_and(|edited| !edited.voxels.is_empty())
+        {
+            EDIT_PRIORITY
+        }
+        else
+        {
+            ChunkManager::get_streaming_score(camera, velocity, chunk_pos)
+        }
     }
 
     //TODO: refactor
@@ -889,4 +1383,153 @@ impl ChunkManager
         debug_data.num_vertices = num_vertices;
         debug_data.chunk_size_bytes = chunk_sizes;
     }
-}
\ No newline at end of file
+}
+
+// helpers that don't depend on the allocator
+impl ChunkManager
+{
+    // the chunk meshed by every mesher, the reports count no chunk if one of its neighbors is being written to
+    fn compare_chunk(factory: &FetcherFactory) -> Vec<MesherReport>
+    {
+        let mut reports: Vec<MesherReport> = MesherType::ALL.iter().map(|mesher| MesherReport{mesher: *mesher, num_chunks: 0, num_vertices: 0, num_triangles: 0, mesh_time: 0.0}).collect();
+
+        // one fetcher for each mesher, the same chunks are meshed by all of them
+        let Some(fetchers) = MesherType::ALL.iter().map(|_| factory.get_fetcher()).collect::<Option<Vec<_>>>() else { return reports };
+
+        for (report, fetcher) in reports.iter_mut().zip(fetchers)
+        {
+            let mut mesh = Mesh::<VoxelVertex>::default();
+            let mut trans_faces = Vec::new();
+
+            let start = Instant::now();
+            report.mesher.generate_mesh(fetcher, &mut mesh, &mut trans_faces);
+            report.mesh_time += start.elapsed().as_secs_f32() * 1000.0;
+
+            report.num_chunks += 1;
+            report.num_vertices += mesh.vertices.len();
+            report.num_triangles += mesh.indices.len() / 3;
+        }
+
+        reports
+    }
+
+    /// Order in which the chunks are generated, meshed and uploaded, lower first
+    ///
+    /// The distance to the player in chunks, larger for the chunks outside of the view
+    /// and smaller for the chunks in the direction the player is moving to
+    pub fn get_streaming_score(camera: &Camera, velocity: Vec3, chunk_pos: IVec2) -> f32
+    {
+        let aabb = Chunk::get_column_aabb(chunk_pos);
+        let to_chunk = ((aabb.min + aabb.max) / 2.0 - camera.get_position()) * Vec3::new(1.0, 0.0, 1.0);
+
+        let mut score = to_chunk.length() / CHUNK_SIZE_X as f32;
+
+        if !camera.is_visible(&aabb)
+        {
+            score *= OUTSIDE_VIEW_PENALTY;
+        }
+
+        // only the horizontal movement matters
+        let velocity = velocity * Vec3::new(1.0, 0.0, 1.0);
+        let speed_factor = (velocity.length() / FAST_SPEED).min(1.0);
+        let alignment = to_chunk.normalize_or_zero().dot(velocity.normalize_or_zero());
+        score *= 1.0 - VELOCITY_WEIGHT * speed_factor * alignment;
+
+        score
+    }
+
+    /// The chunks whose meshes read the voxel at pos, global coord, its own chunk first
+    ///
+    /// The voxels close to a border are read by the neighbors on that side, and the voxels close to a corner by the diagonal neighbor as well
+    pub fn get_dependent_chunks(pos: IVec3) -> Vec<IVec2>
+    {
+        let (chunk_pos, _) = ChunkManager::get_local_voxel_coord(pos);
+        let is_read = |local: i32, size: usize| (-MESH_READ_BEFORE..size as i32 + MESH_READ_AFTER).contains(&local);
+
+        std::iter::once(chunk_pos).chain(MOORE_NEIGHBORHOOD_OFFSET.map(|offset| chunk_pos + offset)).filter(|chunk|
+        {
+            let local = pos - ChunkManager::chunk_to_world_coord(*chunk);
+            is_read(local.x, CHUNK_SIZE_X) && is_read(local.z, CHUNK_SIZE_Z)
+        }).collect()
+    }
+
+    /// Transforms from world coordinates to Chunk coordinates
+    pub fn world_to_chunk_coord(pos: Vec3) -> IVec2
+    {
+        let chunk_x = pos.x as i32 / CHUNK_SIZE_X as i32;
+        let chunk_z = pos.z as i32 / CHUNK_SIZE_Z as i32;
+        IVec2::new(chunk_x,chunk_z)
+    }
+
+    pub fn voxel_to_chunk_coord(pos: IVec3) -> IVec2
+    {
+        let chunk_x = pos.x / CHUNK_SIZE_X as i32;
+        let chunk_z = pos.z / CHUNK_SIZE_Z as i32;
+        IVec2::new(chunk_x, chunk_z)
+    }
+
+    /// assumes that the voxel is indeed inside the chunk given as pos
+    pub fn world_voxel_to_chunk_voxel_coord(chunk_pos: IVec2, voxel_world_pos: IVec3) -> IVec3
+    {
+        voxel_world_pos - IVec3::new(chunk_pos.x * CHUNK_SIZE_X as i32, 0 , chunk_pos.y * CHUNK_SIZE_Z as i32)
+    } 
+
+    /// determines which chunk this voxel belongs to, and it's coordinates within that chunk
+    // TODO: rewrite this mess
+    pub fn get_local_voxel_coord(pos: IVec3) -> (IVec2,IVec3)
+    {
+        let (chunk_pos_x , voxel_pos_x) = Self::adjust_direction(pos.x, CHUNK_SIZE_X);
+        let (chunk_pos_z, voxel_pos_z) = Self::adjust_direction(pos.z, CHUNK_SIZE_Z);
+        let voxel_pos_y = pos.y;
+
+        (IVec2::new(chunk_pos_x,chunk_pos_z),IVec3::new(voxel_pos_x,voxel_pos_y,voxel_pos_z))
+    }
+
+    pub fn adjust_direction(pos:i32, chunk_size: usize) -> (i32,i32)
+    {
+        let chunk_pos;
+        let voxel_pos;
+
+        if pos < 0
+        {
+            chunk_pos = ((pos+1) / chunk_size as i32) - 1;
+            voxel_pos = pos - chunk_pos * chunk_size as i32;
+        }
+        else
+        {
+            chunk_pos = pos / chunk_size as i32;
+            voxel_pos = pos - chunk_pos * chunk_size as i32;
+        }
+
+        (chunk_pos,voxel_pos)
+    }
+
+    // TODO: refactor
+    /// Transforms from world coordinates to chunk coordinates
+    pub fn get_chunk_pos(pos: Vec3) -> IVec2
+    {
+        // in what chunk is this voxel ?
+        let mut pos_x = pos.x as i32 / CHUNK_SIZE_X as i32;
+        if pos.x < 0.0 {pos_x -= 1;} // if we are < 0 along this axis, the chunk coordinate is -= 1 what we have calculated
+        // since it takes +CHUNK_SIZE_X to be in chunk (1,0) whereas it takes just -1 to in chunk(-1,0) and -CHUNK_SIZE_X to be in chunk (-2,0)
+        let mut pos_z = pos.z as i32 / CHUNK_SIZE_Z as i32;
+        if pos.z < 0.0 {pos_z -= 1;}
+
+        IVec2::new(pos_x,pos_z)
+    }
+
+    // from a point in world coordinate to world voxel coordinates
+    pub fn get_voxel_pos(pos: Vec3) -> IVec3
+    {
+        let pos_x = if pos.x < 0.0 {pos.x.floor() -1.0} else {pos.x.floor()};
+        let pos_y = pos.y.floor();
+        let pos_z = if pos.z < 0.0 {pos.z.floor() -1.0} else {pos.z.floor()};
+
+        IVec3::new(pos_x as i32,pos_y as i32,pos_z as i32)
+    }
+
+    pub fn chunk_to_world_coord(chunk_pos: IVec2) -> IVec3
+    {
+        IVec3::new(chunk_pos.x * CHUNK_SIZE_X as i32, 0, chunk_pos.y * CHUNK_SIZE_Z as i32)
+    }
+}
@@ -0,0 +1,57 @@
+// Time given each frame to the work that can be spread over several frames, ex: mesh uploads and transparency re-sorts
+// The budget grows slowly while the frames are faster than the target and is cut as soon as one is slower
+
+use std::time::{Instant, Duration};
+
+// in ms
+pub const MIN_BUDGET: f32 = 0.25;
+pub const MAX_BUDGET: f32 = 8.0;
+const START_BUDGET: f32 = 2.0;
+const GROWTH: f32 = 0.05; // added after each frame under the target
+const SHRINK: f32 = 0.5; // applied after each frame over the target
+
+pub struct FrameBudget
+{
+    budget: f32, // ms
+    target_frame_time: f32, // ms
+}
+
+impl FrameBudget
+{
+    pub fn new(target_frame_time: f32) -> Self
+    {
+        Self{budget: START_BUDGET, target_frame_time}
+    }
+
+    pub fn set_target_frame_time(&mut self, target_frame_time: f32)
+    {
+        self.target_frame_time = target_frame_time;
+    }
+
+    /// In ms
+    pub fn get_budget(&self) -> f32
+    {
+        self.budget
+    }
+
+    /// Adjust the budget to the duration of the last frame, in ms
+    pub fn adapt(&mut self, frame_time: f32)
+    {
+        if frame_time > self.target_frame_time
+        {
+            self.budget *= SHRINK;
+        }
+        else
+        {
+            self.budget += GROWTH;
+        }
+
+        self.budget = self.budget.clamp(MIN_BUDGET, MAX_BUDGET);
+    }
+
+    /// Until when the budgeted work of a frame started at start can run
+    pub fn get_deadline(&self, start: Instant) -> Instant
+    {
+        start + Duration::from_secs_f32(self.budget / 1000.0)
+    }
+}
@@ -1,5 +1,9 @@
-use glam::{Vec3};
-use super::{mesh::Mesh, voxel_vertex::VoxelVertex, meshing::{chunk_mesher::ChunkMesher, voxel_fetcher::VoxelFetcher}};
+use std::{collections::HashSet, time::Instant};
+use glam::{Vec3, IVec3};
+use crate::engine::{visibility::ChunkVisibility, chunk::CHUNK_SIZE, camera::AABB};
+
+use super::{mesh::Mesh, voxel_vertex::VoxelVertex, voxel::{Voxel, VoxelType}, meshing::{chunk_mesher::{MesherType, VOXEL_SIZE, add_transparent_face}, voxel_fetcher::VoxelFetcher,
+    lod_mesher::{ChunkLod, LodMesher}, greedy_mesher::GreedyMesher}};
 
 #[derive(Debug)]
 pub struct Face
@@ -21,22 +25,114 @@ pub struct ChunkMesh
 {
     pub mesh: Mesh<VoxelVertex>, // holds all geometry
     pub trans_faces: Vec<Face>, // holds references into the transparent faces stored in the mesh, used for transparency sorting
+    pub visibility: ChunkVisibility, // which faces of the sections of the chunk see each other, used for the cave culling
+    pub lod: ChunkLod, // the mesh is built again when the chunk changes level
+    pub mesher: MesherType, // or when another mesher is chosen
+    pub version: u32, // edits of the voxels of the chunk shown by the mesh, an older mesh never replaces a newer one
+    pub edit_time: Option<Instant>, // of the oldest edit the mesh was built for, to measure the edit latency
 }
 
 impl ChunkMesh
 {
     /// Generates the chunk mesh
-    pub fn new<T> (voxel_fetcher: VoxelFetcher) -> Self
-    where T: ChunkMesher
+    pub fn new(voxel_fetcher: VoxelFetcher, mesher: MesherType) -> Self
+    {
+        Self::with_lod(voxel_fetcher, ChunkLod::default(), mesher)
+    }
+
+    /// Generates the mesh of the chunk at a level of detail, the mesher builds the full resolution meshes
+    pub fn with_lod(voxel_fetcher: VoxelFetcher, lod: ChunkLod, mesher: MesherType) -> Self
+    {
+        let mut mesh = Mesh::<VoxelVertex>::default();
+        let mut trans_faces = Vec::new();
+        let visibility = ChunkVisibility::new(voxel_fetcher.get_center_chunk());
+
+        LodMesher::generate_mesh(voxel_fetcher, lod, mesher, &mut mesh, &mut trans_faces);
+
+        Self{mesh, trans_faces, visibility, lod, mesher, version: 0, edit_time: None}
+    }
+
+    /// The mesh of a greedy mesher can be patched, the meshes at another level of detail are built again
+    pub fn is_patchable(&self) -> bool
+    {
+        self.lod.level == 0 && matches!(self.mesher, MesherType::Greedy | MesherType::BinaryGreedy)
+    }
+
+    /// The box of the mesh from the one of its chunk's voxels, the Surface Nets place the vertices of the seams with the +X and +Z neighbors
+    /// in their first voxels
+    pub fn get_aabb(&self, chunk_aabb: AABB) -> AABB
+    {
+        if self.lod.level == 0 && self.mesher == MesherType::SurfaceNets
+        {
+            return AABB::new(chunk_aabb.min, chunk_aabb.max + Vec3::new(VOXEL_SIZE, 0.0, VOXEL_SIZE));
+        }
+
+        chunk_aabb
+    }
+
+    /// The mesh after the edit of the voxels at edited, in the chunk, only the planes of quads touching them are meshed again
+    ///
+    /// The base is the vertices of a patchable mesh, its other quads are kept
+    pub fn patched(voxel_fetcher: VoxelFetcher, base: &[VoxelVertex], edited: &[IVec3], mesher: MesherType) -> Self
     {
+        let planes = Self::get_edited_planes(edited);
+
+        let mut quads: Vec<VoxelVertex> = base.chunks(4).filter(|quad| !planes.contains(&Self::get_quad_plane(quad))).flatten().copied().collect();
+        for (dir, plane) in planes.iter()
+        {
+            GreedyMesher::mesh_plane(&voxel_fetcher, *dir, *plane, &mut quads);
+        }
+
+        // the transparent faces are kept in front, like the meshers do
+        let chunk_world_pos = voxel_fetcher.get_center_chunk_pos().as_vec3();
         let mut mesh = Mesh::<VoxelVertex>::default();
         let mut trans_faces = Vec::new();
+        for quad in quads.chunks(4)
+        {
+            mesh.add_quad(quad[0], quad[1], quad[2], quad[3]);
+            if Voxel::new(VoxelType::ALL[quad[0].get_texture_index() as usize]).is_transparent()
+            {
+                let center = quad.iter().map(|vertex| vertex.get_position().as_vec3()).sum::<Vec3>() / 4.0;
+                add_transparent_face(&mut mesh, &mut trans_faces, chunk_world_pos + center);
+            }
+        }
 
-        // mesh opaque geometry
-        T::generate_mesh(voxel_fetcher, &mut mesh, &mut trans_faces);
-        // mesh transparent geometry
+        let visibility = ChunkVisibility::new(voxel_fetcher.get_center_chunk());
+        Self{mesh, trans_faces, visibility, lod: ChunkLod::default(), mesher, version: 0, edit_time: None}
+    }
+
+    /// The planes of quads touching the edited voxels, along each axis, the voxels can be in the neighbors
+    ///
+    /// A voxel touches the planes on both of its sides, the planes go from 0 to the size of the chunk
+    pub fn get_edited_planes(edited: &[IVec3]) -> HashSet<(usize, i32)>
+    {
+        let mut planes = HashSet::new();
+        for pos in edited
+        {
+            for dir in 0usize..3
+            {
+                // the quads of a plane are in the chunk on the other axes
+                let inside = |axis: usize| pos[axis] >= 0 && pos[axis] < CHUNK_SIZE[axis] as i32;
+                if !inside((dir+1) % 3) || !inside((dir+2) % 3)
+                {
+                    continue;
+                }
+
+                for plane in [pos[dir], pos[dir] + 1].into_iter().filter(|plane| (0..=CHUNK_SIZE[dir] as i32).contains(plane))
+                {
+                    planes.insert((dir, plane));
+                }
+            }
+        }
+
+        planes
+    }
 
-        Self{mesh, trans_faces}
+    // the direction and the plane of the quad, from its normal and its position
+    fn get_quad_plane(quad: &[VoxelVertex]) -> (usize, i32)
+    {
+        let dir = quad[0].get_normal_index() as usize % 3;
+        (dir, quad[0].get_position()[dir])
     }
 
     /// Sort the transparent Faces with w.r.t their distances from pos
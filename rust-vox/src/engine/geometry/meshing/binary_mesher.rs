@@ -0,0 +1,229 @@
+// Greedy meshing with bitmasks
+// The chunk and a border of one voxel taken from its neighbors are copied once into a padded buffer. Along each axis, the
+// columns of the buffer become bitmasks of their opaque and transparent voxels, the faces of a whole column are found with
+// a shift and a few bitwise operations. The faces are sorted into rows of bits, per slice and per kind of face, then merged
+// into quads with bit scans. The quads are emitted in the order of the GreedyMesher, both build the same mesh
+// The chunk is 100 voxels high, the columns along Y do not fit in a u64, every bitmask is a u128
+
+use glam::IVec3;
+
+use crate::engine::{chunk::{Chunk, CHUNK_SIZE, CHUNK_SIZE_X, CHUNK_SIZE_Y, CHUNK_SIZE_Z, NEIGHBOR_OFFSET}, geometry::{voxel_vertex::VoxelVertex, mesh::Mesh, voxel::{Voxel, VoxelType}, chunk_mesh::Face}};
+use super::{chunk_mesher::ChunkMesher, greedy_mesher::{GreedyMesher, FaceState, SliceFace, SliceQuad}, voxel_fetcher::VoxelFetcher};
+
+const PADDED_SIZE: [usize; 3] = [CHUNK_SIZE_X + 2, CHUNK_SIZE_Y + 2, CHUNK_SIZE_Z + 2];
+const NUM_KINDS: usize = 2 * (VoxelType::ALL.len() - 1); // the faces facing each direction of the axis, for each filled voxel type
+
+// classes of the voxels, the bit of their column mask
+const OPAQUE: u8 = 1;
+const TRANSPARENT: u8 = 2; // filled, ex: water
+
+/// A copy of the voxels of a chunk, with a border of one voxel around it
+pub struct PaddedChunk
+{
+    voxels: Vec<Voxel>, // from -1 to size on each axis, in the x, y, z order
+}
+
+impl PaddedChunk
+{
+    /// The border is taken from the neighbors, in the order of NEIGHBOR_OFFSET, it stays empty without a neighbor
+    pub fn new(chunk: &Chunk, neighbors: [Option<&Chunk>; 4]) -> Self
+    {
+        let mut voxels = vec![Voxel::new(VoxelType::Air); PADDED_SIZE.iter().product()];
+
+        for (x, x_row) in chunk.voxels.iter().enumerate()
+        {
+            for (y, y_row) in x_row.iter().enumerate()
+            {
+                let start = Self::get_index(IVec3::new(x as i32, y as i32, 0));
+                voxels[start..start + CHUNK_SIZE_Z].copy_from_slice(y_row);
+            }
+        }
+
+        // the voxels of the neighbor touching the chunk
+        for (side, neighbor) in neighbors.into_iter().enumerate()
+        {
+            if let Some(neighbor) = neighbor
+            {
+                let offset = NEIGHBOR_OFFSET[side];
+                for i in 0..CHUNK_SIZE_X // the chunk is square
+                {
+                    let (x, neighbor_x) = Self::get_border(offset.x, i, CHUNK_SIZE_X);
+                    let (z, neighbor_z) = Self::get_border(offset.y, i, CHUNK_SIZE_Z);
+
+                    for y in 0..CHUNK_SIZE_Y
+                    {
+                        voxels[Self::get_index(IVec3::new(x, y as i32, z))] = neighbor.voxels[neighbor_x][y][neighbor_z];
+                    }
+                }
+            }
+        }
+
+        Self{voxels}
+    }
+
+    pub fn from_fetcher(voxels: &VoxelFetcher) -> Self
+    {
+        Self::new(voxels.get_center_chunk(), [0, 1, 2, 3].map(|side| Some(voxels.get_neighbor_chunk(side))))
+    }
+
+    /// pos is in the chunk, from -1 to size on each axis
+    pub fn get_voxel(&self, pos: IVec3) -> Voxel
+    {
+        self.voxels[Self::get_index(pos)]
+    }
+
+    fn get_index(pos: IVec3) -> usize
+    {
+        ((pos.x + 1) as usize * PADDED_SIZE[1] + (pos.y + 1) as usize) * PADDED_SIZE[2] + (pos.z + 1) as usize
+    }
+
+    // position along an axis in the padded chunk and in the neighbor, for the index i along the side
+    fn get_border(offset: i32, i: usize, size: usize) -> (i32, usize)
+    {
+        match offset
+        {
+            1 => (size as i32, 0),
+            -1 => (-1, size - 1),
+            _ => (i as i32, i),
+        }
+    }
+}
+
+pub struct BinaryGreedyMesher;
+
+impl ChunkMesher for BinaryGreedyMesher
+{
+    fn generate_mesh(voxels: VoxelFetcher, mesh: &mut Mesh<VoxelVertex>, trans_faces: &mut Vec<Face>)
+    {
+        let chunk_world_pos = voxels.get_center_chunk_pos();
+        BinaryGreedyMesher::mesh_padded(&PaddedChunk::from_fetcher(&voxels), chunk_world_pos, mesh, trans_faces);
+    }
+}
+
+impl BinaryGreedyMesher
+{
+    pub fn mesh_padded(padded: &PaddedChunk, chunk_world_pos: IVec3, mesh: &mut Mesh<VoxelVertex>, trans_faces: &mut Vec<Face>)
+    {
+        // bits of the faces of each slice, kind and row, reused for each axis
+        let mut rows: Vec<u128> = Vec::new();
+
+        // the class of each voxel of the padded chunk, and the steps between the voxels along each axis
+        let strides = [PADDED_SIZE[1] * PADDED_SIZE[2], PADDED_SIZE[2], 1];
+        let classes: Vec<u8> = padded.voxels.iter().map(|voxel| match (voxel.is_filled(), voxel.is_transparent())
+        {
+            (true, false) => OPAQUE,
+            (true, true) => TRANSPARENT,
+            _ => 0,
+        }).collect();
+
+        for dir in 0usize..3 // 0 is X, 1 is Y, 2 is Z
+        {
+            let n_dir = (dir+1) % 3; // the rows of a slice
+            let nn_dir = (dir+2) % 3; // the bits of a row
+
+            let num_slices = CHUNK_SIZE[dir] + 1; // the slice k is between the voxels k-1 and k
+            let num_rows = CHUNK_SIZE[n_dir];
+            let row_index = |slice: usize, kind: usize, row: usize| (slice * NUM_KINDS + kind) * num_rows + row;
+
+            rows.clear();
+            rows.resize(num_slices * NUM_KINDS * num_rows, 0);
+
+            // Step 1: find the faces of each column along the axis, and sort them into the rows of their slices
+            let slices_mask: u128 = (1 << num_slices) - 1;
+            for j in 0..num_rows
+            {
+                for i in 0..CHUNK_SIZE[nn_dir]
+                {
+                    let column = (j + 1) * strides[n_dir] + (i + 1) * strides[nn_dir];
+
+                    // the bit k is the voxel k-1 of the column
+                    let mut opaque: u128 = 0;
+                    let mut transparent: u128 = 0; // filled and transparent, ex: water
+                    for k in 0..PADDED_SIZE[dir]
+                    {
+                        let class = classes[column + k * strides[dir]];
+                        opaque |= u128::from(class & OPAQUE) << k;
+                        transparent |= u128::from(class >> 1) << k;
+                    }
+                    let filled = opaque | transparent;
+
+                    // a voxel shows a face to an empty voxel, an opaque voxel also to a transparent one
+                    let positive = ((opaque & !(opaque >> 1)) | (transparent & !(filled >> 1))) & slices_mask; // facing +dir, from the voxel before the slice
+                    let negative = (((opaque >> 1) & !opaque) | ((transparent >> 1) & !filled)) & slices_mask; // facing -dir, from the voxel after the slice
+
+                    for (mut faces, face_state, voxel_offset) in [(positive, FaceState::OppositeDirection, 0), (negative, FaceState::CurrentDirection, 1)]
+                    {
+                        while faces != 0
+                        {
+                            let slice = faces.trailing_zeros() as usize;
+                            faces &= faces - 1;
+
+                            let voxel = padded.voxels[column + (slice + voxel_offset) * strides[dir]];
+                            rows[row_index(slice, Self::get_kind(SliceFace{face_state, voxel}), j)] |= 1 << i;
+                        }
+                    }
+                }
+            }
+
+            // Step 2: merge the faces of each slice, row by row, the first face left in the row starts the next quad
+            for slice in 0..num_slices
+            {
+                for j in 0..num_rows
+                {
+                    loop
+                    {
+                        let row: u128 = (0..NUM_KINDS).fold(0, |row, kind| row | rows[row_index(slice, kind, j)]);
+                        if row == 0
+                        {
+                            break;
+                        }
+
+                        let i = row.trailing_zeros();
+                        let kind = (0..NUM_KINDS).find(|kind| rows[row_index(slice, *kind, j)] >> i & 1 == 1).unwrap();
+                        let face = Self::get_face(kind);
+
+                        // only the opaque faces are merged, the widest quad first, then the highest with this width
+                        let mut width = 1;
+                        let mut height = 1;
+                        if !face.voxel.is_transparent()
+                        {
+                            width = (rows[row_index(slice, kind, j)] >> i).trailing_ones();
+                            let bits = ((1 << width) - 1) << i;
+                            while j + height < num_rows && rows[row_index(slice, kind, j + height)] & bits == bits
+                            {
+                                height += 1;
+                            }
+                        }
+
+                        let bits: u128 = ((1 << width) - 1) << i;
+                        for h in 0..height
+                        {
+                            rows[row_index(slice, kind, j + h)] &= !bits;
+                        }
+
+                        let mut pos = IVec3::ZERO;
+                        pos[dir] = slice as i32;
+                        pos[n_dir] = j as i32;
+                        pos[nn_dir] = i as i32;
+
+                        let quad = SliceQuad{face, dir, pos, width: width as i32, height: height as i32};
+                        GreedyMesher::emit_quad(quad, 1, chunk_world_pos, mesh, trans_faces);
+                    }
+                }
+            }
+        }
+    }
+
+    // the faces facing -dir first, then those facing +dir, each by voxel type
+    fn get_kind(face: SliceFace) -> usize
+    {
+        let direction = if face.face_state == FaceState::CurrentDirection { 0 } else { 1 };
+        direction * NUM_KINDS / 2 + face.voxel.voxel_type as usize
+    }
+
+    fn get_face(kind: usize) -> SliceFace
+    {
+        let face_state = if kind < NUM_KINDS / 2 { FaceState::CurrentDirection } else { FaceState::OppositeDirection };
+        SliceFace{face_state, voxel: Voxel::new(VoxelType::ALL[kind % (NUM_KINDS / 2)])}
+    }
+}
@@ -1,8 +1,9 @@
-use glam::{IVec2};
+use glam::{Vec3};
+use serde::{Serialize, Deserialize};
 
 use crate::engine::{geometry::{voxel_vertex::VoxelVertex, mesh::Mesh, chunk_mesh::Face}};
 
-use super::voxel_fetcher::VoxelFetcher;
+use super::{voxel_fetcher::VoxelFetcher, culling_mesher::CullingMesher, greedy_mesher::GreedyMesher, binary_mesher::BinaryGreedyMesher, surface_nets_mesher::SurfaceNetsMesher};
 
 #[derive(PartialEq)]
 pub enum MeshingOption
@@ -19,6 +20,71 @@ pub trait ChunkMesher
     fn generate_mesh(voxels: VoxelFetcher, mesh: &mut Mesh<VoxelVertex>, trans_faces: &mut Vec<Face>);
 }
 
+/// The meshers the chunks can be built with, chosen at runtime
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum MesherType
+{
+    Culling, // a quad per visible face
+    Greedy,
+    #[default]
+    BinaryGreedy, // same mesh as Greedy, faster
+    SurfaceNets, // smooth terrain
+}
+
+impl MesherType
+{
+    pub const ALL: [MesherType; 4] = [MesherType::Culling, MesherType::Greedy, MesherType::BinaryGreedy, MesherType::SurfaceNets];
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            MesherType::Culling => "Culling",
+            MesherType::Greedy => "Greedy",
+            MesherType::BinaryGreedy => "Binary Greedy",
+            MesherType::SurfaceNets => "Surface Nets",
+        }
+    }
+
+    pub fn generate_mesh(&self, voxels: VoxelFetcher, mesh: &mut Mesh<VoxelVertex>, trans_faces: &mut Vec<Face>)
+    {
+        match self
+        {
+            MesherType::Culling => CullingMesher::generate_mesh(voxels, mesh, trans_faces),
+            MesherType::Greedy => GreedyMesher::generate_mesh(voxels, mesh, trans_faces),
+            MesherType::BinaryGreedy => BinaryGreedyMesher::generate_mesh(voxels, mesh, trans_faces),
+            MesherType::SurfaceNets => SurfaceNetsMesher::generate_mesh(voxels, mesh, trans_faces),
+        }
+    }
+}
+
+/// Size of the meshes built by a mesher, and the time it took
+#[derive(Clone, Copy, Debug)]
+pub struct MesherReport
+{
+    pub mesher: MesherType,
+    pub num_chunks: usize,
+    pub num_vertices: usize,
+    pub num_triangles: usize,
+    pub mesh_time: f32, // in ms, for all the chunks
+}
+
+/// Register the last quad of the mesh as a transparent face at face_pos
+///
+/// The indices of the transparent faces are kept in front of the index list, in the order of trans_faces
+pub fn add_transparent_face(mesh: &mut Mesh<VoxelVertex>, trans_faces: &mut Vec<Face>, face_pos: Vec3)
+{
+    trans_faces.push(Face::new(face_pos, trans_faces.len() * 6));
+
+    // swap the quad indices at index trans_faces.len() in the index buffer with the last inserted quad indices
+    let dst = (trans_faces.len()-1) * 6;
+    let src = mesh.indices.len() - 6;
+    for i in 0..6
+    {
+        mesh.indices.swap(dst+i, src+i);
+    }
+}
+
 pub const VOXEL_SIZE: f32 = 1.0;
 
 pub const VOXEL_FACE_VALUES : [(i32,i32,i32);6] = 
@@ -1,14 +1,17 @@
-use glam::{Vec3, IVec3, IVec2};
+use glam::{Vec3, IVec3};
 
-use crate::engine::{geometry::{voxel::{Voxel, VoxelType}, voxel_vertex::VoxelVertex, mesh::Mesh, chunk_mesh::Face}, chunk::{Chunk, CHUNK_SIZE_X, CHUNK_SIZE_Y, CHUNK_SIZE_Z}};
+use crate::engine::{geometry::{voxel::{Voxel, VoxelType}, voxel_vertex::VoxelVertex, mesh::Mesh, chunk_mesh::Face}, chunk::{CHUNK_SIZE_X, CHUNK_SIZE_Y, CHUNK_SIZE_Z}};
 
-use super::{chunk_mesher::{NormalDirection, ChunkMesher, VOXEL_SIZE, VOXEL_FACE_VALUES}, voxel_fetcher::VoxelFetcher};
+use super::{chunk_mesher::{NormalDirection, ChunkMesher, VOXEL_SIZE, VOXEL_FACE_VALUES, add_transparent_face}, voxel_fetcher::VoxelFetcher};
 
 pub struct CullingMesher;
 
 impl CullingMesher
 {
-    fn append_voxel_mesh_faces(voxel: Voxel, faces: &[bool;6], pos: Vec3 , mesh: &mut Mesh<VoxelVertex>)
+    /// Append the faces of the voxel at pos in the chunk, in the order of VOXEL_FACE_VALUES
+    ///
+    /// The transparent faces are registered at their position in the world
+    fn append_voxel_mesh_faces(voxel: Voxel, faces: &[bool;6], pos: Vec3, chunk_world_pos: Vec3, mesh: &mut Mesh<VoxelVertex>, trans_faces: &mut Vec<Face>)
     {
         // generate the 8 vertices to draw the voxel
         //bottom
@@ -23,116 +26,90 @@ impl CullingMesher
         let p7 = Vec3::new(pos.x + VOXEL_SIZE,pos.y + VOXEL_SIZE,pos.z);
         let p8 = Vec3::new(pos.x + VOXEL_SIZE,pos.y + VOXEL_SIZE,pos.z + VOXEL_SIZE);
 
-       if faces[0] 
+        // the corners of each face and its normal, in the order of VOXEL_FACE_VALUES
+        let quads = [
+            ([p5, p6, p7, p8], NormalDirection::Posy), // top
+            ([p3, p2, p1, p4], NormalDirection::Negy), // bottom
+            ([p1, p5, p8, p4], NormalDirection::Posz), // front
+            ([p7, p6, p2, p3], NormalDirection::Negz), // back
+            ([p4, p8, p7, p3], NormalDirection::Posx), // right
+            ([p6, p5, p1, p2], NormalDirection::Negx), // left
+        ];
+
+        for (face, (corners, normal)) in quads.into_iter().enumerate().filter(|(face, _)| faces[*face])
         {
-            // add the 2 top triangles
+            // add the 2 triangles of the face
             mesh.add_quad(
-                VoxelVertex::new( p5, NormalDirection::Posy,(0,0), voxel),
-                VoxelVertex::new( p6, NormalDirection::Posy, (0,1), voxel),
-                VoxelVertex::new( p7, NormalDirection::Posy, (1,1), voxel),
-                VoxelVertex::new( p8, NormalDirection::Posy, (1,0), voxel)
+                VoxelVertex::new(corners[0], normal, (0,0), voxel),
+                VoxelVertex::new(corners[1], normal, (0,1), voxel),
+                VoxelVertex::new(corners[2], normal, (1,1), voxel),
+                VoxelVertex::new(corners[3], normal, (1,0), voxel)
             );
+
+            if voxel.is_transparent()
+            {
+                add_transparent_face(mesh, trans_faces, chunk_world_pos + pos + Self::get_face_center(face));
+            }
         }
+    }
+
+    // center of the face of a voxel, from its lower corner
+    fn get_face_center(face: usize) -> Vec3
+    {
+        let (x, y, z) = VOXEL_FACE_VALUES[face];
+        (Vec3::ONE + Vec3::new(x as f32, y as f32, z as f32)) * VOXEL_SIZE / 2.0
+    }
 
-        if faces[1]
+    /// Append the visible faces of the voxel at pos in the chunk
+    pub(super) fn mesh_voxel(voxels: &VoxelFetcher, pos: IVec3, mesh: &mut Mesh<VoxelVertex>, trans_faces: &mut Vec<Face>)
+    {
+        let chunk_world_pos = voxels.get_center_chunk_pos();
+        let voxel = voxels.get_voxel(chunk_world_pos + pos).unwrap();
+        if !voxel.is_filled()
         {
-            // add the 2 bottom triangles
-            mesh.add_quad(
-                VoxelVertex::new( p3, NormalDirection::Negy, (0,0), voxel),
-                VoxelVertex::new( p2, NormalDirection::Negy, (0,1), voxel),
-                VoxelVertex::new( p1, NormalDirection::Negy, (1,1), voxel),
-                VoxelVertex::new( p4, NormalDirection::Negy, (1,0), voxel)
-            );
+            return;
         }
-        
-        if faces[2]
+
+        // the neighbors above and below the chunk are empty
+        let faces = VOXEL_FACE_VALUES.map(|(x, y, z)|
         {
-            // add the 2 front triangles
-            mesh.add_quad(
-                VoxelVertex::new( p1, NormalDirection::Posz, (0,0), voxel),
-                VoxelVertex::new( p5, NormalDirection::Posz, (0,1), voxel),
-                VoxelVertex::new( p8, NormalDirection::Posz, (1,1), voxel),
-                VoxelVertex::new( p4, NormalDirection::Posz, (1,0), voxel)
-            );
-        }
+            let neighbor = voxels.get_voxel(chunk_world_pos + pos + IVec3::new(x, y, z)).unwrap_or(Voxel::new(VoxelType::Air));
+            Self::is_face_visible(voxel, neighbor)
+        });
 
-        if faces[3]
+        if faces.contains(&true)
         {
-            // add the 2 back triangles
-            mesh.add_quad(
-                VoxelVertex::new( p7, NormalDirection::Negz, (0,0), voxel),
-                VoxelVertex::new( p6, NormalDirection::Negz, (0,1), voxel),
-                VoxelVertex::new( p2, NormalDirection::Negz, (1,1), voxel),
-                VoxelVertex::new( p3, NormalDirection::Negz, (1,0), voxel)
-            );
+            CullingMesher::append_voxel_mesh_faces(voxel, &faces, pos.as_vec3(), chunk_world_pos.as_vec3(), mesh, trans_faces);
         }
+    }
 
-        if faces[4]
+    // a face is drawn if the neighbor does not hide it: empty, or transparent in front of an opaque voxel
+    fn is_face_visible(voxel: Voxel, neighbor: Voxel) -> bool
+    {
+        if voxel.is_transparent()
         {
-            // add the 2 right triangles
-            mesh.add_quad(
-                VoxelVertex::new( p4, NormalDirection::Posx, (0,0), voxel),
-                VoxelVertex::new( p8, NormalDirection::Posx, (0,1), voxel),
-                VoxelVertex::new( p7, NormalDirection::Posx, (1,1), voxel),
-                VoxelVertex::new( p3, NormalDirection::Posx, (1,0), voxel)
-            );
+            !neighbor.is_filled()
         }
-
-        if faces[5]
+        else
         {
-            // add the 2 left triangles
-            mesh.add_quad(
-                VoxelVertex::new( p6, NormalDirection::Negx, (0,0), voxel),
-                VoxelVertex::new( p5, NormalDirection::Negx, (0,1), voxel),
-                VoxelVertex::new( p1, NormalDirection::Negx, (1,1), voxel),
-                VoxelVertex::new( p2, NormalDirection::Negx, (1,0), voxel)
-            );
+            neighbor.is_transparent()
         }
-
     }
 }
 
 impl ChunkMesher for CullingMesher
 {
     fn generate_mesh(voxels: VoxelFetcher, mesh: &mut Mesh<VoxelVertex>, trans_faces: &mut Vec<Face>)
-    {        
-        // //Generate the directly in here, good enough for now
-        // // for now render the mesh of all the voxels as is
-        // for x in 0..CHUNK_SIZE_X
-        // {
-        //     for y in 0..CHUNK_SIZE_Y
-        //     {
-        //         for z in 0..CHUNK_SIZE_Z
-        //         {
-        //             if chunk.voxels[x][y][z].is_filled()
-        //             {
-        //                 let mut faces_to_render: [bool;6] = [false;6];
-
-        //                 // TODO: refactor
-        //                 for (index, offset) in VOXEL_FACE_VALUES.iter().enumerate()
-        //                 {
-        //                     // is the neighbor of the current voxel in the given direction filled ? 
-        //                     let pos = IVec3::new(x as i32 + offset.0,y as i32 + offset.1,z as i32 + offset.2);
-        //                     if let Some(neighbor) = chunk.get_voxel(pos)
-        //                     {
-        //                         if !neighbor.is_filled()
-        //                         {
-        //                             faces_to_render[index] = true ;
-        //                         }
-        //                     }
-        //                     else
-        //                     {
-        //                         faces_to_render[index] = true;    
-        //                     }
-        //                 }
-
-        //                 CullingMesher::append_voxel_mesh_faces(chunk.voxels[x][y][z],
-        //                     &faces_to_render,
-        //                     chunk.pos_world_space() + Vec3::new(x as f32,y as f32,z as f32),
-        //                     mesh);
-        //             }
-        //         }
-        //     }
-        // }
+    {
+        for x in 0..CHUNK_SIZE_X as i32
+        {
+            for y in 0..CHUNK_SIZE_Y as i32
+            {
+                for z in 0..CHUNK_SIZE_Z as i32
+                {
+                    CullingMesher::mesh_voxel(&voxels, IVec3::new(x, y, z), mesh, trans_faces);
+                }
+            }
+        }
     }
-}
\ No newline at end of file
+}
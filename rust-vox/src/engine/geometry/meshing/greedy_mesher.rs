@@ -1,6 +1,6 @@
 use glam::{Vec3, IVec3};
 use crate::engine::{chunk::{CHUNK_SIZE, CHUNK_SIZE_Y, CHUNK_SIZE_X}, geometry::{voxel_vertex::VoxelVertex, mesh::Mesh, voxel::{Voxel,VoxelType}, chunk_mesh::Face}};
-use super::{chunk_mesher::{ChunkMesher, VOXEL_SIZE, NormalDirection}, voxel_fetcher::VoxelFetcher};
+use super::{chunk_mesher::{ChunkMesher, VOXEL_SIZE, NormalDirection, add_transparent_face}, voxel_fetcher::VoxelFetcher};
 
 pub struct GreedyMesher;
 
@@ -60,21 +60,39 @@ impl GreedyMesher
         // if the face is transparent, add it to the transparent faces list
         if face.voxel.is_transparent()
         {          
-            trans_faces.push(Face::new(face_pos, trans_faces.len() * 6));
-            // make sure the transparent indices are grouped in front in the index list
-            // swap the quad indices at index trans_faces.len() in the index buffer with the current inserted quad indices
-            let dst = (trans_faces.len()-1) * 6;
-            let src = mesh.indices.len() - 6;
-            for i in 0..6
-            {
-                mesh.indices.swap(dst+i, src+i);
-            }
+            add_transparent_face(mesh, trans_faces, face_pos);
         }
     }
+
+    /// Append the quad to the mesh, the vertices are in the chunk
+    pub(super) fn emit_quad(quad: SliceQuad, scale: i32, chunk_world_pos: IVec3, mesh: &mut Mesh<VoxelVertex>, trans_faces: &mut Vec<Face>)
+    {
+        // position of a corner of the cells in the chunk, the last cells are cut at the border of the chunk
+        let to_chunk_space = |corner: IVec3| (corner * scale).min(IVec3::new(CHUNK_SIZE[0] as i32, CHUNK_SIZE[1] as i32, CHUNK_SIZE[2] as i32));
+
+        let n_dir = (quad.dir+1) % 3;
+        let nn_dir = (quad.dir+2) % 3;
+
+        let mut du = IVec3::ZERO;
+        du[nn_dir] = quad.width;
+
+        let mut dv = IVec3::ZERO;
+        dv[n_dir] = quad.height;
+
+        let upper_left = to_chunk_space(quad.pos + dv).as_vec3();
+        let lower_left = to_chunk_space(quad.pos).as_vec3();
+        let lower_right = to_chunk_space(quad.pos + du).as_vec3();
+        let upper_right = to_chunk_space(quad.pos + du + dv).as_vec3();
+
+        // add the face to the Transparent Face Vector
+        let face_pos = chunk_world_pos.as_vec3() + to_chunk_space(quad.pos).as_vec3() + FACE_POSITION[quad.dir] * scale as f32; // only relevant for transparent faces which are never merged together
+
+        GreedyMesher::add_quad(mesh, trans_faces, face_pos, quad.face, quad.dir, nn_dir, n_dir, lower_left, upper_left, upper_right, lower_right);
+    }
 }
 
 #[derive(Clone,Copy,PartialOrd,PartialEq)]
-enum FaceState
+pub(super) enum FaceState
 {
     NotPresent, // face is not present because it is culled, present between two voxels that are not Air
     CurrentDirection, // facing us in the current direction
@@ -82,17 +100,65 @@ enum FaceState
 }
 
 #[derive(Clone,Copy,PartialEq,PartialOrd)]
-struct SliceFace
+pub(super) struct SliceFace
 {
     pub face_state: FaceState,
     pub voxel: Voxel,
 }
 
+/// Faces of a slice merged into a quad, width faces along the second axis after dir and height faces along the first
+pub(super) struct SliceQuad
+{
+    pub face: SliceFace,
+    pub dir: usize, // 0 is X, 1 is Y, 2 is Z
+    pub pos: IVec3, // lower left corner, in cells
+    pub width: i32,
+    pub height: i32,
+}
+
 impl ChunkMesher for GreedyMesher
 {
     fn generate_mesh(voxels: VoxelFetcher, mesh: &mut Mesh<VoxelVertex>, trans_faces: &mut Vec<Face>)
     {
         let chunk_world_pos = voxels.get_center_chunk_pos();
+        let get_voxel = |pos: IVec3| voxels.get_voxel(pos + chunk_world_pos).unwrap_or(Voxel::new(VoxelType::Air));
+
+        GreedyMesher::mesh_grid(get_voxel, CHUNK_SIZE, 1, chunk_world_pos, mesh, trans_faces);
+    }
+}
+
+impl GreedyMesher
+{
+    /// Append the quads of a single plane of the chunk, between the voxels plane-1 and plane along dir
+    ///
+    /// The quads are those of the mesh of the whole chunk, 4 vertices each, used to patch the mesh after an edit
+    pub fn mesh_plane(voxels: &VoxelFetcher, dir: usize, plane: i32, quads: &mut Vec<VoxelVertex>)
+    {
+        let chunk_world_pos = voxels.get_center_chunk_pos();
+        let mut offset = IVec3::ZERO;
+        offset[dir] = plane;
+        let get_voxel = |pos: IVec3| voxels.get_voxel(pos + offset + chunk_world_pos).unwrap_or(Voxel::new(VoxelType::Air));
+
+        // a grid of a single layer of voxels has the planes on both of its sides, the second one and the quads along the
+        // other directions are left out
+        let mut size = CHUNK_SIZE;
+        size[dir] = 1;
+        let mut layer = Mesh::<VoxelVertex>::default();
+        GreedyMesher::mesh_grid(get_voxel, size, 1, chunk_world_pos + offset, &mut layer, &mut Vec::new());
+
+        for quad in layer.vertices.chunks(4).filter(|quad| quad[0].get_normal_index() as usize % 3 == dir && quad[0].get_position()[dir] == 0)
+        {
+            quads.extend(quad.iter().map(|vertex| vertex.translated(offset)));
+        }
+    }
+
+    /// Mesh a grid of size cells, each cell being a cube of scale voxels, ex: downsampled voxels
+    ///
+    /// get_cell is called with the positions of the cells from -1 to size, the cells outside of the grid are only used to cull
+    /// the faces at the border. The last cells are cut at the border of the chunk if the chunk's size is not a multiple of scale
+    pub fn mesh_grid<F>(get_cell: F, size: [usize; 3], scale: i32, chunk_world_pos: IVec3, mesh: &mut Mesh<VoxelVertex>, trans_faces: &mut Vec<Face>)
+        where F: Fn(IVec3) -> Voxel
+    {
         // sweep over each axis separately (X,Y,Z)
 
         //TODO: better documentation
@@ -113,31 +179,22 @@ impl ChunkMesher for GreedyMesher
             offset[current_dir] = 1;
 
             // check each slice of the chunk one at a time
-            for slice in -1..CHUNK_SIZE[current_dir] as i32
+            for slice in -1..size[current_dir] as i32
             {
                 // Step 1: populate the mask for the current slice
                 let mut mask_index: usize = 0;
                 current_pos[current_dir] = slice;
 
-                for mask_y in 0..CHUNK_SIZE[n_dir] as i32
+                for mask_y in 0..size[n_dir] as i32
                 {
                     current_pos[n_dir] = mask_y;
-                    for mask_x in 0..CHUNK_SIZE[nn_dir] as i32
+                    for mask_x in 0..size[nn_dir] as i32
                     {
                         current_pos[nn_dir] = mask_x;
 
                         // get the current voxel and the next one in the current direction if any
-                        let current_voxel = match voxels.get_voxel(current_pos + chunk_world_pos)
-                        {
-                            Some(voxel) => voxel,
-                            None => Voxel::new(VoxelType::Air),
-                        };
-
-                        let next_voxel = match voxels.get_voxel(current_pos + offset + chunk_world_pos)
-                        {
-                            Some(voxel) => voxel,
-                            None => Voxel::new(VoxelType::Air),
-                        };
+                        let current_voxel = get_cell(current_pos);
+                        let next_voxel = get_cell(current_pos + offset);
 
                         // TODO: refactor jesus
                         if current_voxel.is_filled() == next_voxel.is_filled() && current_voxel.is_transparent() == next_voxel.is_transparent() // covers all no face emitted cases
@@ -170,10 +227,10 @@ impl ChunkMesher for GreedyMesher
                 // Step 2: use the mask and iterate over every block in this slice of the chunk
                 // iterate over the faces of the slice
                 let mut mask_index = 0;
-                for j in 0..CHUNK_SIZE[n_dir]
+                for j in 0..size[n_dir]
                 {
                     let mut i = 0;
-                    while i < CHUNK_SIZE[nn_dir]
+                    while i < size[nn_dir]
                     {
                         let reference_face = mask[mask_index];
                         if reference_face.face_state !=  FaceState::NotPresent// if current face is visible and not transparent = it can be joined with other faces
@@ -186,19 +243,19 @@ impl ChunkMesher for GreedyMesher
 
                             if !reference_face.voxel.is_transparent() // only opaque faces can be merged
                             {
-                                while (i + width) < CHUNK_SIZE[nn_dir] && mask[mask_index+width] == reference_face // they must also be of the same type
+                                while (i + width) < size[nn_dir] && mask[mask_index+width] == reference_face // they must also be of the same type
                                 {
                                     width += 1;
                                 }
     
                                 // we have the biggest width, compure the biggest height that we can have while still maintaining the current width
                                 // there should be no holes in the resulting quad generated
-                                'outer: while height + j < CHUNK_SIZE[n_dir]
+                                'outer: while height + j < size[n_dir]
                                 {
                                     // for each height, loop over all the faces in the width making sure there are no holes
                                     for w in 0..width
                                     {
-                                        if mask[mask_index + w + height * CHUNK_SIZE[nn_dir]] != reference_face // carefull
+                                        if mask[mask_index + w + height * size[nn_dir]] != reference_face // carefull
                                         {
                                             break 'outer;
                                         }
@@ -214,31 +271,15 @@ impl ChunkMesher for GreedyMesher
                             current_pos[n_dir] = j as i32;
                             current_pos[nn_dir] = i as i32;
 
-                            let mut du: [i32;3] = [0,0,0];
-                            du[nn_dir] = width as i32;
-
-                            let mut dv: [i32;3] = [0,0,0];
-                            dv[n_dir] = height as i32;
-
-                            let chunk_world_pos = chunk_world_pos.as_vec3();
-
-                            // append the quad
-                            let upper_left = IVec3::new(current_pos[0] + dv[0],current_pos[1] + dv[1],current_pos[2] + dv[2]).as_vec3() + chunk_world_pos;
-                            let lower_left = IVec3::new(current_pos[0],current_pos[1],current_pos[2]).as_vec3() + chunk_world_pos;
-                            let lower_right = IVec3::new(current_pos[0] + du[0],current_pos[1] + du[1],current_pos[2] + du[2]).as_vec3() + chunk_world_pos;
-                            let upper_right = IVec3::new(current_pos[0] + du[0] + dv[0],current_pos[1] + du[1] + dv[1],current_pos[2] + du[2] + dv[2]).as_vec3() + chunk_world_pos;
-
-                            // add the face to the Transparent Face Vector
-                            let face_pos = chunk_world_pos + current_pos.as_vec3() + FACE_POSITION[current_dir]; // only relevant for transparent faces which are never merged together
-
-                            GreedyMesher::add_quad(mesh, trans_faces, face_pos, reference_face, current_dir, nn_dir, n_dir, lower_left, upper_left, upper_right, lower_right);
+                            let quad = SliceQuad{face: reference_face, dir: current_dir, pos: current_pos, width: width as i32, height: height as i32};
+                            GreedyMesher::emit_quad(quad, scale, chunk_world_pos, mesh, trans_faces);
 
                             // clear the mask for each face that was used
                             for w in 0..width
                             {
                                 for h in 0..height
                                 {
-                                    mask[mask_index + w + h * CHUNK_SIZE[nn_dir]].face_state = FaceState::NotPresent; // careful
+                                    mask[mask_index + w + h * size[nn_dir]].face_state = FaceState::NotPresent; // careful
                                 }
                             }
 
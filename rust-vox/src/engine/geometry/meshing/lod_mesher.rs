@@ -0,0 +1,177 @@
+// Meshes of the distant chunks, built from downsampled voxels
+// Each cell of LOD_SCALES[level] voxels takes the type of its most common voxel, the cells are then greedy meshed
+// On the sides facing a neighbor of another level, the cells of the neighbor are downsampled at its own level. The side
+// whose cell is opaque where the other one is not puts a wall on the seam, so the cracks between the levels are hidden
+// and each wall is built by one side only. The full resolution chunks are built by the selected mesher and put no walls,
+// their coarser neighbors build the walls of the seam
+
+use std::ops::Range;
+use glam::{IVec2, IVec3};
+
+use crate::engine::{chunk::{CHUNK_SIZE, NEIGHBOR_OFFSET}, geometry::{voxel_vertex::VoxelVertex, mesh::Mesh, voxel::{Voxel, VoxelType}, chunk_mesh::Face}};
+use super::{chunk_mesher::MesherType, greedy_mesher::GreedyMesher, voxel_fetcher::VoxelFetcher};
+
+/// Size of the cells of each level, in voxels
+pub const LOD_SCALES: [i32; 4] = [1, 2, 4, 8];
+
+/// Level of detail of the mesh of a chunk
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct ChunkLod
+{
+    pub level: usize, // index into LOD_SCALES
+    pub seams: [Option<usize>; 4], // level of the neighbor on the sides facing another level, in the order of NEIGHBOR_OFFSET
+}
+
+pub struct LodMesher;
+
+impl LodMesher
+{
+    /// The full resolution meshes are built by the mesher whatever their seams, the cells of the other levels are always greedy meshed
+    pub fn generate_mesh(voxels: VoxelFetcher, lod: ChunkLod, mesher: MesherType, mesh: &mut Mesh<VoxelVertex>, trans_faces: &mut Vec<Face>)
+    {
+        if lod.level == 0
+        {
+            mesher.generate_mesh(voxels, mesh, trans_faces);
+            return;
+        }
+
+        let chunk_world_pos = voxels.get_center_chunk_pos();
+        let scale = LOD_SCALES[lod.level];
+        let chunk_size = IVec3::new(CHUNK_SIZE[0] as i32, CHUNK_SIZE[1] as i32, CHUNK_SIZE[2] as i32);
+        let size = (chunk_size + scale - 1) / scale; // the last cells are cut if the chunk is not a multiple of scale
+
+        // the cells from -1 to size, those around the chunk are taken from the neighbors, the seams are closed afterwards
+        let stride = size + 2;
+        let index = |cell: IVec3| (((cell.x + 1) * stride.y + cell.y + 1) * stride.z + cell.z + 1) as usize;
+        let mut cells = vec![Voxel::new(VoxelType::Air); (stride.x * stride.y * stride.z) as usize];
+
+        let mut seams = Vec::new();
+        for x in -1..=size.x
+        {
+            for z in -1..=size.z
+            {
+                let offset = IVec2::new(Self::get_border_offset(x, size.x), Self::get_border_offset(z, size.z));
+                if offset != IVec2::ZERO
+                {
+                    // the corners are never used to cull a face
+                    match NEIGHBOR_OFFSET.iter().position(|neighbor| *neighbor == offset).map(|side| lod.seams[side])
+                    {
+                        Some(None) => (),
+                        Some(Some(level)) =>
+                        {
+                            seams.push((x, z, level));
+                            continue;
+                        },
+                        None => continue,
+                    }
+                }
+
+                let range_x = Self::get_cell_range(x, scale, chunk_size.x);
+                let range_z = Self::get_cell_range(z, scale, chunk_size.z);
+
+                for y in 0..size.y
+                {
+                    let range_y = y * scale .. ((y + 1) * scale).min(chunk_size.y);
+                    cells[index(IVec3::new(x, y, z))] = Self::get_dominant(&voxels, chunk_world_pos, [range_x.clone(), range_y, range_z.clone()]);
+                }
+            }
+        }
+
+        // an opaque cell on a seam gets a wall if one of the neighbor's cells along it is not opaque, the other cells get none
+        // and leave the wall to the neighbor
+        let is_opaque = |cell: Voxel| cell.is_filled() && !cell.is_transparent();
+        for (x, z, level) in seams
+        {
+            let neighbor_scale = LOD_SCALES[level];
+            let inside = IVec2::new(x.clamp(0, size.x - 1), z.clamp(0, size.z - 1));
+
+            // the neighbor's cells along the seam, and its first cell across it
+            let get_ranges = |cell: i32, inside_cell: i32, chunk_size: i32|
+            {
+                if cell == inside_cell
+                {
+                    Self::get_covering_ranges(Self::get_cell_range(cell, scale, chunk_size), neighbor_scale, chunk_size)
+                }
+                else
+                {
+                    let border_cell = if cell < 0 { -1 } else { (chunk_size + neighbor_scale - 1) / neighbor_scale };
+                    vec![Self::get_cell_range(border_cell, neighbor_scale, chunk_size)]
+                }
+            };
+            let ranges_x = get_ranges(x, inside.x, chunk_size.x);
+            let ranges_z = get_ranges(z, inside.y, chunk_size.z);
+
+            for y in 0..size.y
+            {
+                let cell = cells[index(IVec3::new(inside.x, y, inside.y))];
+                cells[index(IVec3::new(x, y, z))] = if is_opaque(cell)
+                {
+                    let ranges_y = Self::get_covering_ranges(Self::get_cell_range(y, scale, chunk_size.y), neighbor_scale, chunk_size.y);
+                    let mut neighbor_cells = ranges_x.iter().flat_map(|range_x| ranges_z.iter().flat_map(|range_z| ranges_y.iter()
+                        .map(|range_y| Self::get_dominant(&voxels, chunk_world_pos, [range_x.clone(), range_y.clone(), range_z.clone()]))));
+                    neighbor_cells.find(|neighbor_cell| !is_opaque(*neighbor_cell)).unwrap_or(cell)
+                }
+                else
+                {
+                    cell
+                };
+            }
+        }
+
+        let size = [size.x as usize, size.y as usize, size.z as usize];
+        GreedyMesher::mesh_grid(|cell| cells[index(cell)], size, scale, chunk_world_pos, mesh, trans_faces);
+    }
+
+    // the most common type of the voxels in the ranges, the filled types win the ties
+    fn get_dominant(voxels: &VoxelFetcher, chunk_world_pos: IVec3, ranges: [Range<i32>; 3]) -> Voxel
+    {
+        let mut counts = [0; VoxelType::ALL.len()];
+        for voxel_x in ranges[0].clone()
+        {
+            for voxel_y in ranges[1].clone()
+            {
+                for voxel_z in ranges[2].clone()
+                {
+                    if let Some(voxel) = voxels.get_voxel(chunk_world_pos + IVec3::new(voxel_x, voxel_y, voxel_z))
+                    {
+                        counts[voxel.voxel_type as usize] += 1;
+                    }
+                }
+            }
+        }
+
+        let dominant = VoxelType::ALL.into_iter().rev().max_by_key(|voxel_type| counts[*voxel_type as usize]).unwrap();
+        Voxel::new(dominant)
+    }
+
+    // the voxels of the cells of scale voxels covering the range, in the chunk
+    fn get_covering_ranges(range: Range<i32>, scale: i32, chunk_size: i32) -> Vec<Range<i32>>
+    {
+        (range.start / scale ..= (range.end - 1) / scale).map(|cell| cell * scale .. ((cell + 1) * scale).min(chunk_size)).collect()
+    }
+
+    /// Voxels of the cell along an axis of the chunk, the cells -1 and size are the border cells of the neighbors
+    fn get_cell_range(cell: i32, scale: i32, chunk_size: i32) -> Range<i32>
+    {
+        let num_cells = (chunk_size + scale - 1) / scale;
+
+        if cell < 0 // the last cell of the previous chunk
+        {
+            (num_cells - 1) * scale - chunk_size .. 0
+        }
+        else if cell >= num_cells // the first cell of the next chunk
+        {
+            chunk_size .. chunk_size + scale.min(chunk_size)
+        }
+        else
+        {
+            cell * scale .. ((cell + 1) * scale).min(chunk_size)
+        }
+    }
+
+    // -1 for the cells before the chunk, 1 for those after
+    fn get_border_offset(cell: i32, size: i32) -> i32
+    {
+        if cell < 0 { -1 } else if cell >= size { 1 } else { 0 }
+    }
+}
@@ -1,4 +1,7 @@
+pub mod binary_mesher;
 pub mod chunk_mesher;
 pub mod culling_mesher;
 pub mod greedy_mesher;
-pub mod voxel_fetcher;
\ No newline at end of file
+pub mod lod_mesher;
+pub mod surface_nets_mesher;
+pub mod voxel_fetcher;
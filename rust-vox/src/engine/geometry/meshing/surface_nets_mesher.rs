@@ -0,0 +1,286 @@
+// Naive Surface Nets
+// The density of the terrain is sampled on the corners of the voxels, it is the part of the 8 voxels around a corner that is
+// opaque. Each voxel crossed by the surface gets one vertex, at the mean of the points where the surface crosses its edges,
+// and each edge of the grid crossed by the surface joins the vertices of the 4 voxels around it into a quad.
+// The chunk also computes the vertices of the first voxels of its neighbors on the +X and +Z sides, both chunks see the same
+// densities there and place the same vertices, the seams are closed. The transparent voxels keep their cubes
+
+use glam::{IVec3, Vec3};
+
+use crate::engine::{chunk::{CHUNK_SIZE, CHUNK_SIZE_X, CHUNK_SIZE_Y, CHUNK_SIZE_Z}, geometry::{voxel_vertex::VoxelVertex, mesh::Mesh, voxel::{Voxel, VoxelType}, chunk_mesh::Face}};
+use super::{chunk_mesher::ChunkMesher, culling_mesher::CullingMesher, voxel_fetcher::VoxelFetcher};
+
+const ISO_LEVEL: f32 = 0.5; // a corner is inside the terrain above this density
+const NUM_MATERIALS: usize = VoxelType::Air as usize;
+const _: () = assert!(NUM_MATERIALS <= 4, "the shaders weight the materials of the smooth surfaces in a vec4");
+
+pub struct SurfaceNetsMesher;
+
+/// The voxels around the chunk and the densities on their corners
+struct DensityGrid
+{
+    voxels: Vec<Voxel>, // from -1 to size+1 on X and Z, from -1 to size on Y
+    densities: Vec<f32>, // on the corners, from 0 to size+1 on X and Z, from 0 to size on Y
+}
+
+impl DensityGrid
+{
+    const VOXELS_SIZE: [usize; 3] = [CHUNK_SIZE_X + 3, CHUNK_SIZE_Y + 2, CHUNK_SIZE_Z + 3];
+    const CORNERS_SIZE: [usize; 3] = [CHUNK_SIZE_X + 2, CHUNK_SIZE_Y + 1, CHUNK_SIZE_Z + 2];
+
+    fn new(fetcher: &VoxelFetcher) -> Self
+    {
+        let chunk_world_pos = fetcher.get_center_chunk_pos();
+        let [size_x, size_y, size_z] = Self::VOXELS_SIZE;
+
+        // the ground goes on below the chunk, the sky above it
+        let mut voxels = Vec::with_capacity(size_x * size_y * size_z);
+        for x in -1..size_x as i32 - 1
+        {
+            for y in -1..size_y as i32 - 1
+            {
+                for z in -1..size_z as i32 - 1
+                {
+                    let voxel = match y
+                    {
+                        -1 => Voxel::default(),
+                        y if y == CHUNK_SIZE_Y as i32 => Voxel::new(VoxelType::Air),
+                        _ => fetcher.get_voxel(chunk_world_pos + IVec3::new(x, y, z)).unwrap(),
+                    };
+                    voxels.push(voxel);
+                }
+            }
+        }
+
+        let mut grid = Self{voxels, densities: Vec::with_capacity(Self::CORNERS_SIZE.iter().product())};
+        for x in 0..Self::CORNERS_SIZE[0] as i32
+        {
+            for y in 0..Self::CORNERS_SIZE[1] as i32
+            {
+                for z in 0..Self::CORNERS_SIZE[2] as i32
+                {
+                    let corner = IVec3::new(x, y, z);
+                    let num_opaque = Self::get_around(corner).filter(|pos| Self::is_opaque(grid.get_voxel(*pos))).count();
+                    grid.densities.push(num_opaque as f32 / 8.0);
+                }
+            }
+        }
+
+        grid
+    }
+
+    /// pos is in the chunk
+    fn get_voxel(&self, pos: IVec3) -> Voxel
+    {
+        let [_, size_y, size_z] = Self::VOXELS_SIZE;
+        self.voxels[((pos.x + 1) as usize * size_y + (pos.y + 1) as usize) * size_z + (pos.z + 1) as usize]
+    }
+
+    /// corner is in the chunk, the corner (0,0,0) is the lower corner of the voxel (0,0,0)
+    fn get_density(&self, corner: IVec3) -> f32
+    {
+        let [_, size_y, size_z] = Self::CORNERS_SIZE;
+        self.densities[(corner.x as usize * size_y + corner.y as usize) * size_z + corner.z as usize]
+    }
+
+    // the 8 voxels sharing the corner
+    fn get_around(corner: IVec3) -> impl Iterator<Item = IVec3>
+    {
+        (0..8).map(move |i| corner - Self::get_corner_offset(i))
+    }
+
+    fn get_corner_offset(i: usize) -> IVec3
+    {
+        IVec3::new((i & 1) as i32, (i >> 1 & 1) as i32, (i >> 2 & 1) as i32)
+    }
+
+    fn is_opaque(voxel: Voxel) -> bool
+    {
+        voxel.is_filled() && !voxel.is_transparent()
+    }
+}
+
+impl ChunkMesher for SurfaceNetsMesher
+{
+    fn generate_mesh(voxels: VoxelFetcher, mesh: &mut Mesh<VoxelVertex>, trans_faces: &mut Vec<Face>)
+    {
+        let grid = DensityGrid::new(&voxels);
+
+        // the transparent voxels are drawn as cubes
+        for x in 0..CHUNK_SIZE_X as i32
+        {
+            for y in 0..CHUNK_SIZE_Y as i32
+            {
+                for z in 0..CHUNK_SIZE_Z as i32
+                {
+                    let pos = IVec3::new(x, y, z);
+                    let voxel = grid.get_voxel(pos);
+                    if voxel.is_filled() && voxel.is_transparent()
+                    {
+                        CullingMesher::mesh_voxel(&voxels, pos, mesh, trans_faces);
+                    }
+                }
+            }
+        }
+
+        SurfaceNetsMesher::mesh_surface(&grid, mesh);
+    }
+}
+
+impl SurfaceNetsMesher
+{
+    // the voxels with a vertex, up to the first voxels of the neighbors on X and Z
+    const CELLS_SIZE: [usize; 3] = [CHUNK_SIZE_X + 1, CHUNK_SIZE_Y, CHUNK_SIZE_Z + 1];
+
+    fn mesh_surface(grid: &DensityGrid, mesh: &mut Mesh<VoxelVertex>)
+    {
+        let [size_x, size_y, size_z] = Self::CELLS_SIZE;
+        let cell_index = |pos: IVec3| (pos.x as usize * size_y + pos.y as usize) * size_z + pos.z as usize;
+
+        // Step 1: a vertex in each voxel crossed by the surface
+        let mut cell_vertices = vec![u32::MAX; size_x * size_y * size_z];
+        for x in 0..size_x as i32
+        {
+            for y in 0..size_y as i32
+            {
+                for z in 0..size_z as i32
+                {
+                    let pos = IVec3::new(x, y, z);
+                    if let Some(vertex) = Self::get_cell_vertex(grid, pos)
+                    {
+                        cell_vertices[cell_index(pos)] = mesh.add_vertex(vertex) as u32;
+                    }
+                }
+            }
+        }
+
+        // Step 2: a quad around each edge crossed by the surface, the edges of the chunk are shared with the neighbors
+        // each edge belongs to the chunk of its voxel, its quad joins the voxels before it on the other axes
+        for dir in 0usize..3
+        {
+            let n_dir = (dir+1) % 3;
+            let nn_dir = (dir+2) % 3;
+
+            let mut start = IVec3::ONE;
+            let mut end = IVec3::new(CHUNK_SIZE_X as i32 + 1, CHUNK_SIZE_Y as i32, CHUNK_SIZE_Z as i32 + 1); // excluded
+            start[dir] = 0;
+            end[dir] = CHUNK_SIZE[dir] as i32;
+
+            for x in start.x..end.x
+            {
+                for y in start.y..end.y
+                {
+                    for z in start.z..end.z
+                    {
+                        let corner = IVec3::new(x, y, z);
+                        let mut next = corner;
+                        next[dir] += 1;
+
+                        let inside = grid.get_density(corner) > ISO_LEVEL;
+                        if inside == (grid.get_density(next) > ISO_LEVEL)
+                        {
+                            continue;
+                        }
+
+                        let mut n_offset = IVec3::ZERO;
+                        n_offset[n_dir] = 1;
+                        let mut nn_offset = IVec3::ZERO;
+                        nn_offset[nn_dir] = 1;
+
+                        // clockwise seen from the outside, the surface faces +dir when the corner is inside
+                        let mut cells = [corner - n_offset - nn_offset, corner - n_offset, corner, corner - nn_offset]
+                            .map(|cell| cell_vertices[cell_index(cell)] as usize);
+                        if !inside
+                        {
+                            cells.reverse();
+                        }
+
+                        mesh.add_triangle_indices(cells[0], cells[1], cells[2]);
+                        mesh.add_triangle_indices(cells[0], cells[2], cells[3]);
+                    }
+                }
+            }
+        }
+    }
+
+    // the vertex of the voxel at pos, if the surface crosses it
+    fn get_cell_vertex(grid: &DensityGrid, pos: IVec3) -> Option<VoxelVertex>
+    {
+        let densities: [f32; 8] = std::array::from_fn(|i| grid.get_density(pos + DensityGrid::get_corner_offset(i)));
+
+        // mean of the crossings on the 12 edges of the voxel
+        let mut sum = Vec3::ZERO;
+        let mut num_crossings = 0;
+        for (i, density) in densities.iter().enumerate()
+        {
+            for axis in [1, 2, 4].into_iter().filter(|axis| i & axis == 0)
+            {
+                let next_density = densities[i | axis];
+                if (*density > ISO_LEVEL) != (next_density > ISO_LEVEL)
+                {
+                    let t = (ISO_LEVEL - density) / (next_density - density);
+                    sum += DensityGrid::get_corner_offset(i).as_vec3().lerp(DensityGrid::get_corner_offset(i | axis).as_vec3(), t);
+                    num_crossings += 1;
+                }
+            }
+        }
+
+        if num_crossings == 0
+        {
+            return None;
+        }
+
+        let local_pos = sum / num_crossings as f32;
+
+        // the density grows into the terrain, the normal goes against its gradient, taken from the trilinear interpolation
+        let mut gradient = Vec3::ZERO;
+        for (i, density) in densities.iter().enumerate()
+        {
+            let offset = DensityGrid::get_corner_offset(i).as_vec3();
+            let weights = (Vec3::ONE - offset) + (offset * 2.0 - Vec3::ONE) * local_pos; // of the corner, along each axis
+            let signs = offset * 2.0 - Vec3::ONE;
+            gradient += *density * signs * Vec3::new(weights.y * weights.z, weights.x * weights.z, weights.x * weights.y);
+        }
+        let normal = (-gradient).try_normalize().unwrap_or(Vec3::Y);
+
+        let (voxel, blend) = Self::get_materials(grid, pos);
+        Some(VoxelVertex::new_smooth(pos.as_vec3() + local_pos, normal, voxel, blend))
+    }
+
+    // the two most common opaque voxel types around the voxel, and the weight of the second, in the order of their values
+    // so that the neighboring vertices blend the same pair
+    fn get_materials(grid: &DensityGrid, pos: IVec3) -> (Voxel, (Voxel, f32))
+    {
+        let mut counts = [0u32; NUM_MATERIALS];
+        for x in -1..=1
+        {
+            for y in -1..=1
+            {
+                for z in -1..=1
+                {
+                    let voxel = grid.get_voxel(pos + IVec3::new(x, y, z));
+                    if DensityGrid::is_opaque(voxel)
+                    {
+                        counts[voxel.voxel_type as usize] += 1;
+                    }
+                }
+            }
+        }
+
+        let mut materials: Vec<usize> = (0..NUM_MATERIALS).filter(|material| counts[*material] > 0).collect();
+        materials.sort_by(|a, b| counts[*b].cmp(&counts[*a]));
+        materials.truncate(2);
+        materials.sort();
+
+        match materials[..]
+        {
+            [first, second] =>
+            {
+                let weight = counts[second] as f32 / (counts[first] + counts[second]) as f32;
+                (Voxel::new(VoxelType::ALL[first]), (Voxel::new(VoxelType::ALL[second]), weight))
+            },
+            [first] => (Voxel::new(VoxelType::ALL[first]), (Voxel::new(VoxelType::ALL[first]), 0.0)),
+            _ => (Voxel::default(), (Voxel::default(), 0.0)), // the opaque voxels are further than the corners
+        }
+    }
+}
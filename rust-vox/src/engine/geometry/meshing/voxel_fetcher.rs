@@ -1,29 +1,31 @@
+use std::sync::Arc;
+
 use glam::{IVec3, IVec2};
 
-use crate::{engine::{chunk_manager::{ChunkManageUnit, ChunkManager}, geometry::voxel::{Voxel}, chunk::{NEIGHBOR_OFFSET}}, generational_vec::{GenerationIndex, GenerationalArena, ReadLock}};
+use crate::{engine::{chunk_manager::{ChunkManageUnit, ChunkManager, ChunkArena}, geometry::voxel::{Voxel}, chunk::{Chunk, MOORE_NEIGHBORHOOD_OFFSET}}, generational_vec::{GenerationIndex, ReadLock}};
 
 pub struct FetcherFactory
 {
-    indices: [GenerationIndex; 5],
-    arena: &'static GenerationalArena<ChunkManageUnit>,
+    indices: [GenerationIndex; 9], // the center chunk then its Moore neighbors
+    arena: Arc<ChunkArena>, // keeps the arena alive while the fetcher is used by a worker
 }
 
 impl FetcherFactory
 {
-    pub fn new(indices: [GenerationIndex; 5], arena: &'static GenerationalArena<ChunkManageUnit>) -> Self
+    pub fn new(indices: [GenerationIndex; 9], arena: Arc<ChunkArena>) -> Self
     {
         Self { indices, arena}
     }
 
-    pub fn get_fetcher(self) -> Option<VoxelFetcher<'static>>
+    pub fn get_fetcher(&self) -> Option<VoxelFetcher<'_>>
     {
-        let mut locks = Vec::with_capacity(5);
+        let mut locks = Vec::with_capacity(9);
         for index in self.indices.iter()
         {
             match self.arena.get(*index)
             {
-                Ok(lock) => locks.push(lock),
-                Err(_) => return None, // FIXME: this might cause problems later!
+                Ok(lock) if lock.chunk.is_some() => locks.push(lock),
+                _ => return None, // locked, or the voxels are not generated yet
             }
         }
         
@@ -46,6 +48,17 @@ impl<'a> VoxelFetcher<'a>
         self.locks[0].chunk.as_ref().unwrap().pos_world_space().as_ivec3()
     }
 
+    pub fn get_center_chunk(&self) -> &Chunk
+    {
+        self.locks[0].chunk.as_ref().unwrap()
+    }
+
+    /// The neighbor of the center chunk on a side, in the order of NEIGHBOR_OFFSET
+    pub fn get_neighbor_chunk(&self, side: usize) -> &Chunk
+    {
+        self.locks[side * 2 + 1].chunk.as_ref().unwrap()
+    }
+
     pub fn get_voxel(&self, world_pos: IVec3) -> Option<Voxel>
     {
         // in what chunk is the voxel ?
@@ -64,7 +77,7 @@ impl<'a> VoxelFetcher<'a>
         {
             let mut found = false;
             // TODO: PERF ? Refactor
-            for (index, neighbor) in NEIGHBOR_OFFSET.into_iter().enumerate()
+            for (index, neighbor) in MOORE_NEIGHBORHOOD_OFFSET.into_iter().enumerate()
             {
                 if neighbor == offset
                 {
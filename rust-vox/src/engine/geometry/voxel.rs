@@ -9,6 +9,12 @@ pub enum VoxelType
     Air = 4, // Keeps it last always, since it has no correspnding texture index
 }
 
+impl VoxelType
+{
+    /// In the order of their values
+    pub const ALL: [VoxelType; 5] = [VoxelType::Dirt, VoxelType::Sand, VoxelType::Water, VoxelType::Glass, VoxelType::Air];
+}
+
 // manual definition of block attributes for now
 const VOXEL_ATTRIBUTES : [VoxelAttribs;5] =[VoxelAttribs::new(true, false, true), // dirt
                                             VoxelAttribs::new(true, false, true), // sand
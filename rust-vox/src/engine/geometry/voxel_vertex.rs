@@ -1,41 +1,195 @@
-use glam::{Vec3};
+use glam::{Vec2, Vec3, IVec3};
 use crate::engine::renderer::opengl_abstractions::vertex_array::VertexLayout;
 
 use super::{opengl_vertex::OpenglVertex, voxel::Voxel, meshing::chunk_mesher::NormalDirection};
 
+// bit offset and width of each field of the vertex
+const POS_X: (u32, u32) = (0, 5);
+const POS_Y: (u32, u32) = (5, 7);
+const POS_Z: (u32, u32) = (12, 5);
+const TEXTURE_U: (u32, u32) = (17, 7);
+const TEXTURE_V: (u32, u32) = (24, 7);
+const SMOOTH: (u32, u32) = (31, 1);
+
+// the vertices of the smooth surfaces have no texture UV, their position is precise to a sixteenth of a voxel
+const FRACTION_X: (u32, u32) = (17, 4);
+const FRACTION_Y: (u32, u32) = (21, 4);
+const FRACTION_Z: (u32, u32) = (25, 4);
+const FRACTION_STEPS: f32 = 16.0;
+
+const NORMAL: (u32, u32) = (0, 3);
+const TEXTURE_INDEX: (u32, u32) = (3, 4);
+const AMBIENT_OCCLUSION: (u32, u32) = (7, 2);
+const LIGHT: (u32, u32) = (9, 4);
+
+// the smooth surfaces have any normal, octahedral encoded, and blend their texture with a second one
+const BLEND: (u32, u32) = (0, 3); // weight of the blend texture, in place of the normal index
+const NORMAL_U: (u32, u32) = (13, 7);
+const NORMAL_V: (u32, u32) = (20, 7);
+const BLEND_TEXTURE_INDEX: (u32, u32) = (27, 4);
+
+pub const NO_OCCLUSION: u32 = 3; // the corner is not darkened
+pub const FULL_LIGHT: u32 = 15;
+
 // Voxel Vertex
-/// Holds all the data which constitute a *vertex*
+/// Holds all the data which constitute a *vertex*, packed in 8 bytes
+///
+/// The position is in the chunk, the origin of the chunk is given to the shader for each draw
 #[repr(C,packed)]
-#[derive(Clone,Copy,Debug)]
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
 pub struct VoxelVertex
 {
-    position: Vec3, // X, Y, Z
-    texture_u: u8, // U
-    texture_v: u8, // V
-    texture_index: u8, // what texture to display
-    normal_index : u8, // byte index into a normal LUT in the shader, 6 possible normal vectors
+    position_uv: u32, // X, Y, Z in the chunk, U, V in voxels along the quad
+    attributes: u32, // normal index into a LUT in the shader, texture index, ambient occlusion, light
 }
 
 impl VoxelVertex
 {
+    /// position is in the chunk, from 0 to the size of the chunk
     pub fn new( position: Vec3 , normal :NormalDirection , texture_uv: (u8,u8), voxel: Voxel ) -> Self
     {
-        Self { position , texture_u: texture_uv.0, texture_v: texture_uv.1, normal_index: normal as u8, texture_index: voxel.voxel_type as u8 }
+        let position = position.as_uvec3();
+        let position_uv = pack(POS_X, position.x) | pack(POS_Y, position.y) | pack(POS_Z, position.z)
+            | pack(TEXTURE_U, texture_uv.0 as u32) | pack(TEXTURE_V, texture_uv.1 as u32);
+        let attributes = pack(NORMAL, normal as u32) | pack(TEXTURE_INDEX, voxel.voxel_type as u32)
+            | pack(AMBIENT_OCCLUSION, NO_OCCLUSION) | pack(LIGHT, FULL_LIGHT);
+
+        Self{position_uv, attributes}
+    }
+
+    /// A vertex of a smooth surface, textured from its position in the world
+    ///
+    /// blend is the second voxel type of the surface and its weight, from 0 to 1
+    pub fn new_smooth(position: Vec3, normal: Vec3, voxel: Voxel, blend: (Voxel, f32)) -> Self
+    {
+        let steps = (position * FRACTION_STEPS).round().as_uvec3();
+        let position = steps / FRACTION_STEPS as u32;
+        let fraction = steps % FRACTION_STEPS as u32;
+        let position_uv = pack(POS_X, position.x) | pack(POS_Y, position.y) | pack(POS_Z, position.z)
+            | pack(FRACTION_X, fraction.x) | pack(FRACTION_Y, fraction.y) | pack(FRACTION_Z, fraction.z) | pack(SMOOTH, 1);
+
+        let (normal_u, normal_v) = encode_octahedral(normal);
+        let max_blend = (1 << BLEND.1) - 1;
+        let attributes = pack(BLEND, (blend.1 * max_blend as f32).round() as u32) | pack(TEXTURE_INDEX, voxel.voxel_type as u32)
+            | pack(AMBIENT_OCCLUSION, NO_OCCLUSION) | pack(LIGHT, FULL_LIGHT)
+            | pack(NORMAL_U, normal_u) | pack(NORMAL_V, normal_v) | pack(BLEND_TEXTURE_INDEX, blend.0.voxel_type as u32);
+
+        Self{position_uv, attributes}
+    }
+
+    /// The same vertex, moved by offset in the chunk
+    pub fn translated(&self, offset: IVec3) -> Self
+    {
+        let position = (self.get_position() + offset).as_uvec3();
+        let fields = self.position_uv >> TEXTURE_U.0 << TEXTURE_U.0; // the bits after the position
+        let position_uv = fields | pack(POS_X, position.x) | pack(POS_Y, position.y) | pack(POS_Z, position.z);
+
+        Self{position_uv, attributes: self.attributes}
+    }
+
+    pub fn is_smooth(&self) -> bool
+    {
+        unpack(SMOOTH, self.position_uv) == 1
+    }
+
+    /// The position in the chunk, with the fraction of the vertices of the smooth surfaces
+    pub fn get_precise_position(&self) -> Vec3
+    {
+        let fraction = if self.is_smooth()
+        {
+            Vec3::new(unpack(FRACTION_X, self.position_uv) as f32, unpack(FRACTION_Y, self.position_uv) as f32, unpack(FRACTION_Z, self.position_uv) as f32) / FRACTION_STEPS
+        }
+        else
+        {
+            Vec3::ZERO
+        };
+
+        self.get_position().as_vec3() + fraction
+    }
+
+    pub fn get_normal(&self) -> Vec3
+    {
+        if self.is_smooth()
+        {
+            decode_octahedral(unpack(NORMAL_U, self.attributes), unpack(NORMAL_V, self.attributes))
+        }
+        else
+        {
+            let normal = VOXEL_NORMALS[self.get_normal_index() as usize];
+            Vec3::new(normal.0 as f32, normal.1 as f32, normal.2 as f32)
+        }
+    }
+
+    pub fn get_position(&self) -> IVec3
+    {
+        IVec3::new(unpack(POS_X, self.position_uv) as i32, unpack(POS_Y, self.position_uv) as i32, unpack(POS_Z, self.position_uv) as i32)
+    }
+
+    pub fn get_texture_uv(&self) -> (u8, u8)
+    {
+        (unpack(TEXTURE_U, self.position_uv) as u8, unpack(TEXTURE_V, self.position_uv) as u8)
+    }
+
+    pub fn get_normal_index(&self) -> u32
+    {
+        unpack(NORMAL, self.attributes)
+    }
+
+    pub fn get_texture_index(&self) -> u32
+    {
+        unpack(TEXTURE_INDEX, self.attributes)
     }
 }
 
+// the normals of the NormalDirection, like the table in the shader
+const VOXEL_NORMALS: [(i32, i32, i32); 6] = [(1, 0, 0), (0, 1, 0), (0, 0, 1), (-1, 0, 0), (0, -1, 0), (0, 0, -1)];
+
+// the normal is projected onto an octahedron, unfolded onto a square
+fn encode_octahedral(normal: Vec3) -> (u32, u32)
+{
+    let normal = normal / normal.abs().dot(Vec3::ONE);
+    let mut uv = Vec2::new(normal.x, normal.y);
+    if normal.z < 0.0
+    {
+        uv = (Vec2::ONE - Vec2::new(uv.y, uv.x).abs()) * uv.signum();
+    }
+
+    let max = ((1 << NORMAL_U.1) - 1) as f32;
+    let uv = ((uv * 0.5 + 0.5) * max).round();
+    (uv.x as u32, uv.y as u32)
+}
+
+fn decode_octahedral(u: u32, v: u32) -> Vec3
+{
+    let max = ((1 << NORMAL_U.1) - 1) as f32;
+    let uv = Vec2::new(u as f32, v as f32) / max * 2.0 - 1.0;
+    let mut normal = Vec3::new(uv.x, uv.y, 1.0 - uv.x.abs() - uv.y.abs());
+    let t = (-normal.z).max(0.0);
+    normal.x += if normal.x >= 0.0 { -t } else { t };
+    normal.y += if normal.y >= 0.0 { -t } else { t };
+    normal.normalize()
+}
+
+fn pack((offset, bits): (u32, u32), value: u32) -> u32
+{
+    debug_assert!(value < 1 << bits, "vertex field out of range");
+    (value & ((1 << bits) - 1)) << offset
+}
+
+fn unpack((offset, bits): (u32, u32), value: u32) -> u32
+{
+    (value >> offset) & ((1 << bits) - 1)
+}
+
 impl OpenglVertex for VoxelVertex
 {
     fn get_layout() -> VertexLayout
     {
         let mut vertex_layout = VertexLayout::new();
 
-        vertex_layout.push_f32(3); // vertex(x,y,z)
-        vertex_layout.push_u8(1); // texture U
-        vertex_layout.push_u8(1); // texture V
-        vertex_layout.push_u8(1); // texture index
-        vertex_layout.push_u8(1); // Normal Index
+        vertex_layout.push_u32(1); // position and texture UV
+        vertex_layout.push_u32(1); // normal, texture index, ambient occlusion and light
 
         vertex_layout
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,244 @@
+// Distant horizon, cheap impostors of the terrain past the loaded chunks
+// Only the surface height and the top block of the columns are asked to the terrain generator, every TILE_STEP blocks.
+// The samples make heightfield tiles of TILE_CHUNKS chunks, built by a worker thread around the camera. The renderer
+// draws them behind the chunks and hides them where a chunk is rendered
+
+use std::{collections::HashMap, sync::{Arc, mpsc::{self, Sender, Receiver}}};
+
+use glam::{IVec2, Vec3, Vec3Swizzles};
+
+use crate::jobs::{JobSystem, JobHandle};
+use super::{camera::AABB, chunk::CHUNK_SIZE_X, terrain::TerrainGenerator, geometry::{mesh::Mesh, opengl_vertex::OpenglVertex},
+    renderer::opengl_abstractions::vertex_array::VertexLayout};
+
+pub const TILE_CHUNKS: i32 = 16; // side of a tile, in chunks
+pub const TILE_STEP: i32 = 10; // blocks between two samples, divides the chunk size so the samples line up with the chunks
+pub const TILE_SIZE: i32 = TILE_CHUNKS * CHUNK_SIZE_X as i32; // in blocks
+const TILE_SAMPLES: i32 = TILE_SIZE / TILE_STEP + 1; // along a side, the border samples are shared with the next tile
+const JOB_QUEUE_CAPACITY: usize = 64;
+
+#[repr(C,packed)]
+#[derive(Clone,Copy,Debug)]
+pub struct HorizonVertex
+{
+    position: Vec3,
+    normal: Vec3,
+    voxel_type: u8, // the color is taken from the texture of the top block
+}
+
+impl OpenglVertex for HorizonVertex
+{
+    fn get_layout() -> VertexLayout
+    {
+        let mut vertex_layout = VertexLayout::new();
+
+        vertex_layout.push_f32(3); // vertex(x,y,z)
+        vertex_layout.push_f32(3); // normal(x,y,z)
+        vertex_layout.push_u8(1); // voxel type
+
+        vertex_layout
+    }
+}
+
+pub struct HorizonTile
+{
+    pub pos: IVec2, // in tiles
+    pub mesh: Mesh<HorizonVertex>,
+    pub aabb: AABB,
+}
+
+impl HorizonTile
+{
+    /// Sample the surface of the tile at tile_pos and build its heightfield
+    pub fn new(tile_pos: IVec2, generator: &dyn TerrainGenerator) -> Self
+    {
+        let origin = tile_pos * TILE_SIZE;
+
+        // one more sample on each side for the normals
+        let stride = TILE_SAMPLES + 2;
+        let mut surface = Vec::with_capacity((stride * stride) as usize);
+        for x in -1..=TILE_SAMPLES
+        {
+            for z in -1..=TILE_SAMPLES
+            {
+                surface.push(generator.get_surface(origin.x + x * TILE_STEP, origin.y + z * TILE_STEP));
+            }
+        }
+        let get_surface = |x: i32, z: i32| surface[((x + 1) * stride + z + 1) as usize];
+        let get_height = |x: i32, z: i32| get_surface(x, z).0 as f32;
+
+        let mut mesh = Mesh::default();
+        let (mut min_y, mut max_y) = (f32::MAX, f32::MIN);
+        for x in 0..TILE_SAMPLES
+        {
+            for z in 0..TILE_SAMPLES
+            {
+                let (height, voxel_type) = get_surface(x, z);
+                let position = Vec3::new((origin.x + x * TILE_STEP) as f32, height as f32, (origin.y + z * TILE_STEP) as f32);
+                let normal = Vec3::new(get_height(x - 1, z) - get_height(x + 1, z), 2.0 * TILE_STEP as f32, get_height(x, z - 1) - get_height(x, z + 1)).normalize();
+
+                mesh.add_vertex(HorizonVertex{position, normal, voxel_type: voxel_type as u8});
+                min_y = min_y.min(position.y);
+                max_y = max_y.max(position.y);
+            }
+        }
+
+        for x in 0..TILE_SAMPLES - 1
+        {
+            for z in 0..TILE_SAMPLES - 1
+            {
+                let index = (x * TILE_SAMPLES + z) as usize;
+                let next_x = index + TILE_SAMPLES as usize;
+
+                // clockwise seen from above
+                mesh.add_triangle_indices(index, next_x, next_x + 1);
+                mesh.add_triangle_indices(index, next_x + 1, index + 1);
+            }
+        }
+
+        let aabb = AABB::new(Vec3::new(origin.x as f32, min_y, origin.y as f32), Vec3::new((origin.x + TILE_SIZE) as f32, max_y, (origin.y + TILE_SIZE) as f32));
+        Self{pos: tile_pos, mesh, aabb}
+    }
+}
+
+/// The tiles around the camera, out to a distance
+pub struct Horizon
+{
+    distance: i32, // in chunks from the camera, 0 disables the horizon
+    generator: Option<Arc<dyn TerrainGenerator>>, // the one the tiles were built with
+
+    jobs: JobSystem,
+    tile_sender: Sender<HorizonTile>,
+    finished_tiles: Receiver<HorizonTile>,
+    tile_jobs: HashMap<IVec2, JobHandle>,
+
+    tiles: HashMap<IVec2, HorizonTile>,
+    removed_tiles: Vec<HorizonTile>, // their meshes must be deallocated by the caller
+}
+
+impl Default for Horizon
+{
+    fn default() -> Self
+    {
+        let (tile_sender, finished_tiles) = mpsc::channel();
+
+        Self{distance: 0, generator: None, jobs: JobSystem::new(1, JOB_QUEUE_CAPACITY), tile_sender, finished_tiles, tile_jobs: HashMap::new(),
+            tiles: HashMap::new(), removed_tiles: Vec::new()}
+    }
+}
+
+impl Horizon
+{
+    pub fn new(distance: i32) -> Self
+    {
+        Self{distance, ..Self::default()}
+    }
+
+    pub fn get_distance(&self) -> i32
+    {
+        self.distance
+    }
+
+    pub fn set_distance(&mut self, distance: i32)
+    {
+        self.distance = distance;
+    }
+
+    /// Keep the tiles built, drop those out of range and request the missing ones, the closest first
+    ///
+    /// Every tile is built again if the generator changed
+    pub fn update(&mut self, generator: &Arc<dyn TerrainGenerator>, camera_pos: Vec3)
+    {
+        if !self.generator.as_ref().is_some_and(|current| Arc::ptr_eq(current, generator))
+        {
+            self.clear();
+            self.generator = Some(Arc::clone(generator));
+        }
+
+        // the results of the cancelled jobs have no entry anymore
+        for tile in self.finished_tiles.try_iter()
+        {
+            if self.tile_jobs.remove(&tile.pos).is_some()
+            {
+                self.tiles.insert(tile.pos, tile);
+            }
+        }
+
+        let center = (camera_pos.xz() / TILE_SIZE as f32).floor().as_ivec2();
+        let radius = (self.distance + TILE_CHUNKS - 1) / TILE_CHUNKS;
+        let enabled = self.distance > 0;
+
+        // a margin of one tile, the tiles at the border are not dropped and built again as the camera moves back and forth
+        let is_kept = |tile_pos: IVec2| enabled && (tile_pos - center).abs().max_element() <= radius + 1;
+        let removed: Vec<IVec2> = self.tiles.keys().copied().filter(|tile_pos| !is_kept(*tile_pos)).collect();
+        self.removed_tiles.extend(removed.iter().filter_map(|tile_pos| self.tiles.remove(tile_pos)));
+        self.tile_jobs.retain(|tile_pos, job|
+        {
+            if !is_kept(*tile_pos)
+            {
+                job.cancel();
+            }
+            is_kept(*tile_pos)
+        });
+
+        if !enabled
+        {
+            return;
+        }
+
+        let mut missing: Vec<IVec2> = (-radius..=radius).flat_map(|x| (-radius..=radius).map(move |z| center + IVec2::new(x, z)))
+            .filter(|tile_pos| !self.tiles.contains_key(tile_pos) && !self.tile_jobs.contains_key(tile_pos)).collect();
+        missing.sort_by_key(|tile_pos| (*tile_pos - center).dot(*tile_pos - center));
+
+        for tile_pos in missing
+        {
+            let generator = Arc::clone(generator);
+            let priority = (tile_pos - center).dot(tile_pos - center) as f32;
+
+            // the rest is requested on the next updates when the queue is full
+            match self.jobs.submit(priority, tile_pos, &self.tile_sender, move || Some(HorizonTile::new(tile_pos, generator.as_ref())))
+            {
+                Some(job) => { self.tile_jobs.insert(tile_pos, job); },
+                None => break,
+            }
+        }
+    }
+
+    /// Drop every tile, ex: the world changed
+    pub fn clear(&mut self)
+    {
+        for job in self.tile_jobs.values()
+        {
+            job.cancel();
+        }
+        self.tile_jobs.clear();
+        self.removed_tiles.extend(self.tiles.drain().map(|(_, tile)| tile));
+    }
+
+    pub fn get_tiles(&self) -> impl Iterator<Item = &HorizonTile>
+    {
+        self.tiles.values()
+    }
+
+    pub fn get_tiles_mut(&mut self) -> impl Iterator<Item = &mut HorizonTile>
+    {
+        self.tiles.values_mut()
+    }
+
+    /// The tiles dropped since the last call
+    pub fn take_removed_tiles(&mut self) -> Vec<HorizonTile>
+    {
+        std::mem::take(&mut self.removed_tiles)
+    }
+
+    pub fn get_num_tiles(&self) -> usize
+    {
+        self.tiles.len()
+    }
+
+    /// Every tile requested has been built
+    pub fn is_loaded(&self) -> bool
+    {
+        self.tile_jobs.is_empty()
+    }
+}
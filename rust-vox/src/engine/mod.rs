@@ -7,4 +7,7 @@ pub mod chunk_manager;
 pub mod sky;
 pub mod geometry;
 pub mod chunk;
-pub mod ray_cast;
\ No newline at end of file
+pub mod ray_cast;
+pub mod frame_budget;
+pub mod visibility;
+pub mod horizon;
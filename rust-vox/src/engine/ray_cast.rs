@@ -4,11 +4,11 @@
 // by John Amanatides and Andrew Woo, 1987
 
 use glam::{Vec3, IVec3};
-use crate::engine::geometry::voxel::Voxel;
+use crate::engine::{geometry::{voxel::Voxel, voxel_vertex::VoxelVertex}, renderer::allocators::MeshAllocator};
 use super::chunk_manager::ChunkManager;
 
 // uses get_closest_voxel
-pub fn cast_ray(position: Vec3, direction: Vec3, chunk_manager: &ChunkManager) -> Option<(IVec3,IVec3)>
+pub fn cast_ray<A: MeshAllocator<VoxelVertex>>(position: Vec3, direction: Vec3, chunk_manager: &ChunkManager<A>) -> Option<(IVec3,IVec3)>
 {
     let mut found = false;
     let mut used_position = IVec3::ZERO;
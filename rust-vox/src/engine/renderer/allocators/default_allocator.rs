@@ -5,6 +5,8 @@
 use std::{collections::HashMap};
 use crate::engine::{geometry::{mesh::Mesh, opengl_vertex::OpenglVertex}, renderer::opengl_abstractions::{vertex_buffer::VertexBuffer, index_buffer::IndexBuffer, vertex_array::VertexArray}};
 
+use super::MeshAllocator;
+
 pub struct DefaultAllocator<T>
 {
     // allocations
@@ -19,7 +21,29 @@ impl<T> DefaultAllocator<T>
         Self{allocations: HashMap::new()}
     }
 
-    pub fn alloc(&mut self, mesh: &mut Mesh<T>)
+    pub fn get_vao(&self, allocation: &AllocToken) -> &VertexArray<T>
+    {
+        self.allocations.get(&allocation.index).unwrap()
+    }
+
+    pub fn render(&self)
+    {
+        for (_, vao) in self.allocations.iter()
+        {
+            vao.bind();
+            unsafe
+            {
+                gl::DrawElements(gl::TRIANGLES, vao.ebo.count as _  , gl::UNSIGNED_INT, 0 as _ );
+            }
+            vao.unbind();
+        }
+    }
+}
+
+impl<T> MeshAllocator<T> for DefaultAllocator<T>
+    where T: OpenglVertex
+{
+    fn alloc(&mut self, mesh: &mut Mesh<T>)
     {
         // create the vertex buffer
         let vertex_buffer = VertexBuffer::new(&mesh.vertices);
@@ -33,30 +57,12 @@ impl<T> DefaultAllocator<T>
         mesh.alloc_token = Some(token);
     }
 
-    pub fn dealloc(&mut self, allocation: AllocToken)
+    fn dealloc(&mut self, allocation: AllocToken)
     {
         // Safety: it is not possible to get an invalid index in the AllocToken
         let _vao = self.allocations.remove(&allocation.index).unwrap();
         // drop takes care of deletion
     }
-
-    pub fn get_vao(&self, allocation: &AllocToken) -> &VertexArray<T>
-    {
-        self.allocations.get(&allocation.index).unwrap()
-    }
-
-    pub fn render(&self)
-    {
-        for (_, vao) in self.allocations.iter()
-        {
-            vao.bind();
-            unsafe
-            {
-                gl::DrawElements(gl::TRIANGLES, vao.ebo.count as _  , gl::UNSIGNED_INT, 0 as _ );
-            }
-            vao.unbind();
-        }
-    }
 }
 
 pub struct AllocToken
@@ -0,0 +1,116 @@
+// Free list over a range of elements, used to sub-allocate the buffers of the vertex pool
+// The free ranges are kept sorted and merged with their neighbors, allocations are first fit
+
+/// Contiguous free elements
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct FreeRange
+{
+    start: u32,
+    len: u32,
+}
+
+pub struct FreeList
+{
+    capacity: u32,
+    free: Vec<FreeRange>, // sorted by start, never adjacent
+    num_free: u32,
+}
+
+impl FreeList
+{
+    pub fn new(capacity: u32) -> Self
+    {
+        let free = if capacity > 0 { vec![FreeRange{start: 0, len: capacity}] } else { Vec::new() };
+        Self{capacity, free, num_free: capacity}
+    }
+
+    /// Reserve len elements, returns the first one
+    ///
+    /// Returns None if there is no free range large enough, an empty allocation always succeeds
+    pub fn alloc(&mut self, len: u32) -> Option<u32>
+    {
+        if len == 0
+        {
+            return Some(0);
+        }
+
+        let i = self.free.iter().position(|range| range.len >= len)?;
+        let range = &mut self.free[i];
+        let start = range.start;
+
+        range.start += len;
+        range.len -= len;
+        if range.len == 0
+        {
+            self.free.remove(i);
+        }
+
+        self.num_free -= len;
+        Some(start)
+    }
+
+    /// Give back a range returned by alloc()
+    pub fn free(&mut self, start: u32, len: u32)
+    {
+        if len == 0
+        {
+            return;
+        }
+
+        let i = self.free.partition_point(|range| range.start < start);
+        debug_assert!(i == 0 || self.free[i-1].start + self.free[i-1].len <= start, "double free");
+        debug_assert!(i == self.free.len() || start + len <= self.free[i].start, "double free");
+
+        self.free.insert(i, FreeRange{start, len});
+        self.num_free += len;
+
+        // merge with the next range, then with the previous one
+        if i + 1 < self.free.len() && start + len == self.free[i+1].start
+        {
+            self.free[i].len += self.free[i+1].len;
+            self.free.remove(i+1);
+        }
+        if i > 0 && self.free[i-1].start + self.free[i-1].len == start
+        {
+            self.free[i-1].len += self.free[i].len;
+            self.free.remove(i);
+        }
+    }
+
+    /// Add free elements at the end
+    pub fn grow(&mut self, new_capacity: u32)
+    {
+        assert!(new_capacity >= self.capacity);
+
+        let added = new_capacity - self.capacity;
+        let start = self.capacity;
+        self.capacity = new_capacity;
+        self.free(start, added);
+    }
+
+    pub fn get_capacity(&self) -> u32
+    {
+        self.capacity
+    }
+
+    pub fn get_num_used(&self) -> u32
+    {
+        self.capacity - self.num_free
+    }
+
+    pub fn get_largest_free(&self) -> u32
+    {
+        self.free.iter().map(|range| range.len).max().unwrap_or(0)
+    }
+
+    /// 0 when the free elements are contiguous, close to 1 when they are scattered in small ranges
+    pub fn get_fragmentation(&self) -> f32
+    {
+        if self.num_free == 0
+        {
+            return 0.0;
+        }
+
+        1.0 - self.get_largest_free() as f32 / self.num_free as f32
+    }
+}
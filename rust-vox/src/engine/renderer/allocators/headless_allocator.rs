@@ -0,0 +1,67 @@
+// Allocator for running without an OpenGL context
+// Nothing is uploaded, the allocations are only recorded, used by the headless world and the tests
+
+use std::collections::HashMap;
+
+use crate::engine::geometry::mesh::Mesh;
+
+use super::{MeshAllocator, default_allocator::AllocToken};
+
+/// What would have been uploaded for one mesh
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HeadlessAllocation
+{
+    pub num_vertices: usize,
+    pub num_indices: usize,
+}
+
+#[derive(Default)]
+pub struct HeadlessAllocator
+{
+    next_index: u32,
+    allocations: HashMap<u32, HeadlessAllocation>,
+
+    // totals since creation
+    pub num_allocs: usize,
+    pub num_deallocs: usize,
+}
+
+impl HeadlessAllocator
+{
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    pub fn get_allocation(&self, index: u32) -> Option<&HeadlessAllocation>
+    {
+        self.allocations.get(&index)
+    }
+
+    /// Number of allocations currently alive
+    pub fn get_num_allocations(&self) -> usize
+    {
+        self.allocations.len()
+    }
+}
+
+impl<T> MeshAllocator<T> for HeadlessAllocator
+{
+    fn alloc(&mut self, mesh: &mut Mesh<T>)
+    {
+        // indices are never reused, a re-allocated mesh always gets a new one
+        let index = self.next_index;
+        self.next_index += 1;
+
+        self.allocations.insert(index, HeadlessAllocation{num_vertices: mesh.vertices.len(), num_indices: mesh.indices.len()});
+        self.num_allocs += 1;
+
+        mesh.alloc_token = Some(AllocToken::new(index));
+    }
+
+    fn dealloc(&mut self, allocation: AllocToken)
+    {
+        self.allocations.remove(&allocation.index).expect("dealloc of an unknown allocation");
+        self.num_deallocs += 1;
+    }
+}
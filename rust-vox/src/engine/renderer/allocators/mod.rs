@@ -1,2 +1,26 @@
+use crate::engine::geometry::mesh::Mesh;
+
+use self::default_allocator::AllocToken;
+
 pub mod default_allocator;
-pub mod vertex_pool_allocator;
\ No newline at end of file
+pub mod vertex_pool_allocator;
+pub mod headless_allocator;
+pub mod free_list;
+
+/// Where the chunk meshes end up, the chunk manager only allocates and deallocates through this
+pub trait MeshAllocator<T>
+{
+    /// Upload the mesh, the mesh receives the token of its allocation
+    fn alloc(&mut self, mesh: &mut Mesh<T>);
+
+    /// Same as alloc(), the first num_transparent indices of the mesh are drawn apart from the opaque ones
+    fn alloc_with_transparent(&mut self, mesh: &mut Mesh<T>, _num_transparent: usize)
+    {
+        self.alloc(mesh);
+    }
+
+    fn dealloc(&mut self, allocation: AllocToken);
+
+    /// Called once per frame by the chunk manager, for the work spread over several frames, ex: defragmentation
+    fn update(&mut self) {}
+}
@@ -1,205 +1,593 @@
 // Manages the Opengl allocations related to chunks
+// Every mesh is sub-allocated from one persistent VBO/EBO pair, the whole pool is drawn with glMultiDrawElementsIndirect
+// The draw commands are written each frame into a persistent ring buffer, a fence guards every section of the ring
+// The buffers grow when they are full, waiting for the copy of their data, and the allocations are moved towards their start a few at a time to fight fragmentation
 
-use core::panic;
-use std::{collections::VecDeque, ffi::c_void, mem::{self}, cell::Cell};
+use std::{collections::{VecDeque, HashMap}, ffi::c_void, mem, cell::Cell, ptr};
 
-use gl::types::__GLsync;
+use gl::types::{GLsync, GLenum};
 
 use crate::engine::{geometry::{mesh::Mesh, opengl_vertex::OpenglVertex}, renderer::opengl_abstractions::{vertex_array::VertexArray, index_buffer::IndexBuffer, vertex_buffer::VertexBuffer}};
 
-use super::default_allocator::AllocToken;
-pub struct VertexPoolAllocator<T>
+use super::{default_allocator::AllocToken, MeshAllocator, free_list::FreeList};
+
+// the GPU can be this many frames behind the CPU before writing the draw commands blocks
+const RING_SECTIONS: usize = 3;
+
+// defragmentation
+const DEFRAG_THRESHOLD: f32 = 0.25; // fragmentation from which the allocations are moved
+const DEFRAG_MOVES_PER_FRAME: usize = 4;
+
+// where the data of a mesh is in the pool, in elements
+#[derive(Clone, Copy)]
+struct PoolAllocation
+{
+    vertex_start: u32,
+    num_vertices: u32,
+    index_start: u32,
+    num_indices: u32,
+    num_transparent: u32, // the first indices are the transparent ones
+}
+
+/// Occupancy of the pool, shown in the profiling panel
+#[derive(Clone, Copy, Default, Debug)]
+pub struct PoolStats
+{
+    pub num_allocations: usize,
+    pub vertex_bytes_used: usize,
+    pub vertex_bytes_capacity: usize,
+    pub vertex_fragmentation: f32, // see FreeList::get_fragmentation()
+    pub index_bytes_used: usize,
+    pub index_bytes_capacity: usize,
+    pub index_fragmentation: f32,
+    pub num_moves: usize, // allocations moved by the defragmentation since the creation of the pool
+    pub num_growths: usize,
+}
+
+// persistently mapped buffer, replaced by a larger one when it grows
+struct PersistentBuffer
 {
-    max_num_buckets: usize, // maximum number of buckets the pool can hold
+    id: u32,
+    start: *mut c_void,
+    size: usize, // bytes
+}
+
+impl PersistentBuffer
+{
+    const FLAGS: GLenum = gl::MAP_WRITE_BIT | gl::MAP_PERSISTENT_BIT | gl::MAP_COHERENT_BIT;
 
-    // Inside each bucket
-    max_num_indices: usize, // maximum number of indices a bucket can hold
-    max_num_vertices: usize, // maximum number of vertices a bucket can hold
+    fn new(target: GLenum, size: usize) -> Self
+    {
+        let mut id = 0;
+        let start;
 
-    vbo_bucket_size: usize, // max size of a single bucket in the vbo
-    ebo_bucket_size: usize, // max size of a single bucket in the ebo
+        unsafe
+        {
+            gl::GenBuffers(1, &mut id);
+            gl::BindBuffer(target, id);
+            gl::BufferStorage(target, size as isize, std::ptr::null::<c_void>(), Self::FLAGS);
+
+            start = gl::MapBufferRange(target, 0, size as isize, Self::FLAGS);
+            gl::BindBuffer(target, 0);
+        }
+
+        if start.is_null()
+        {
+            panic!("could not map a vertex pool buffer of {} bytes", size);
+        }
 
-    free_pool: VecDeque<u32>, // stores the index of the vertex pool bucket that is available
+        Self{id, start, size}
+    }
 
-    draw_calls: Vec<Daic>,
-    indices: Vec<u32>,
+    /// Replace the buffer by a larger one holding the same data, the id of the old buffer is returned to be deleted by its owner
+    ///
+    /// The copy is done by the GPU after the commands already issued, the draws in flight still read the old buffer.
+    /// Blocks until the copy is done, the next writes through the mapping would be overwritten by it otherwise
+    fn grow(&mut self, target: GLenum, new_size: usize) -> u32
+    {
+        let new = Self::new(target, new_size);
 
-    allocation_index: Vec<i32>, // holds the index of the Daic at the allocation numbers given by the Vec's index
-    vbo_start: *mut c_void, // start of the persistent VBO
-    ebo_start: *mut c_void, // start of persistent EBO
+        unsafe
+        {
+            gl::BindBuffer(gl::COPY_READ_BUFFER, self.id);
+            gl::BindBuffer(gl::COPY_WRITE_BUFFER, new.id);
+            gl::CopyBufferSubData(gl::COPY_READ_BUFFER, gl::COPY_WRITE_BUFFER, 0, 0, self.size as isize);
+            gl::BindBuffer(gl::COPY_READ_BUFFER, 0);
+            gl::BindBuffer(gl::COPY_WRITE_BUFFER, 0);
+
+            // any free range of the old data can be reserved right after, ex: merged with the new tail
+            let fence = gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0);
+            if gl::ClientWaitSync(fence, gl::SYNC_FLUSH_COMMANDS_BIT, u64::MAX) == gl::WAIT_FAILED
+            {
+                panic!("Some error occured while waiting for the copy of a vertex pool buffer");
+            }
+            gl::DeleteSync(fence);
+        }
 
-    // TODO: refactor these later
-    vao: VertexArray<T>,
-    draw_ind_buffer: u32, // indirect draw command buffer object
+        mem::replace(self, new).id
+    }
 
-    // OpenGL Sync object
-    sync_obj: *const __GLsync,
+    /// Copy a range of the buffer to another place of the same buffer, the ranges must not overlap
+    fn copy_within(&self, src: usize, dst: usize, size: usize)
+    {
+        unsafe
+        {
+            gl::BindBuffer(gl::COPY_READ_BUFFER, self.id);
+            gl::CopyBufferSubData(gl::COPY_READ_BUFFER, gl::COPY_READ_BUFFER, src as isize, dst as isize, size as isize);
+            gl::BindBuffer(gl::COPY_READ_BUFFER, 0);
+        }
+    }
+}
 
-    // state
-    dirty: Cell<bool>, // commands have been changed and beed to be reuploaded
+pub struct VertexPoolAllocator<T>
+{
+    vertices: FreeList, // in vertices
+    indices: FreeList, // in indices
+    // ranges freed in the given frame, the frames before it may still be drawing them
+    retired: VecDeque<(PoolAllocation, u64)>,
+
+    allocations: HashMap<u32, PoolAllocation>, // indexed by the allocation tokens
+    next_token: u32,
+
+    vbo: PersistentBuffer,
+    ebo: PersistentBuffer,
+    vao: VertexArray<T>, // owns the vbo and ebo
+
+    // draw commands, one ring section per frame
+    commands: PersistentBuffer,
+    section_len: usize, // in commands
+    section_fences: Cell<[GLsync; RING_SECTIONS]>,
+    frame: Cell<u64>, // frame whose commands are being written
+    completed_frames: Cell<u64>, // every frame before this one is done on the GPU
+    num_prepared: Cell<usize>, // allocations whose commands were written for the current frame
+
+    stats: PoolStats,
 }
 
 impl<T> VertexPoolAllocator<T>
     where T: OpenglVertex
 {
-    /// Construct a new Vertex Pool
-    pub fn new(max_num_buckets: usize, max_num_indices: usize, max_num_vertices: usize) -> Self
+    /// Construct a new Vertex Pool, the capacities are in elements and grow when needed
+    pub fn new(num_vertices: usize, num_indices: usize) -> Self
+    {
+        let vbo = PersistentBuffer::new(gl::ARRAY_BUFFER, num_vertices * mem::size_of::<T>());
+        let ebo = PersistentBuffer::new(gl::ELEMENT_ARRAY_BUFFER, num_indices * mem::size_of::<u32>());
+
+        // enough for an opaque and a transparent command per chunk, grows with the number of allocations
+        // a multiple of 64 commands keeps the sections aligned to bind them as storage buffers
+        let section_len = 1024;
+        let commands = PersistentBuffer::new(gl::DRAW_INDIRECT_BUFFER, RING_SECTIONS * section_len * mem::size_of::<Daic>());
+
+        println!("vertex pool allocator allocated ~{} MBs of GPU memory", (vbo.size + ebo.size)/1000000);
+
+        // create the VAO
+        let vao = VertexArray::new(VertexBuffer::from_id(vbo.id),&T::get_layout(), IndexBuffer::from_id(ebo.id));
+
+        Self{vertices: FreeList::new(num_vertices as u32), indices: FreeList::new(num_indices as u32), retired: VecDeque::new(),
+                allocations: HashMap::new(), next_token: 0, vbo, ebo, vao, commands, section_len,
+                section_fences: Cell::new([ptr::null(); RING_SECTIONS]), frame: Cell::new(0), completed_frames: Cell::new(0),
+                num_prepared: Cell::new(0), stats: PoolStats::default()}
+    }
+
+    /// Write the draw commands of the frame, the allocations are drawn in the given order
+    ///
+    /// The opaque command of the i-th allocation is at i and its transparent command at n + i, the ranges that are empty
+    /// or unknown get commands drawing nothing, which lets the culling match the commands with the chunks. Both commands have
+    /// i as their base instance, the shaders use it to find the origin of the chunk. Returns the commands
+    ///
+    /// Must be called once per frame before the draws, and followed by end_frame()
+    pub fn prepare_frame<I>(&self, allocations: I) -> Vec<Daic>
+        where I: Iterator<Item = u32>
     {
-        let mut free_pool = VecDeque::new();
-        let flags = gl::MAP_WRITE_BIT | gl::MAP_PERSISTENT_BIT | gl::MAP_COHERENT_BIT ;//| gl::DYNAMIC_STORAGE_BIT;
+        let frame = self.frame.get();
+        let section = frame as usize % RING_SECTIONS;
 
-        let mut pers_vbo = 0;
-        let mut ebo = 0;
-        let mut draw_ind_buffer = 0;
+        // wait for the GPU to be done with the commands written RING_SECTIONS frames ago
+        let mut fences = self.section_fences.get();
+        if !fences[section].is_null()
+        {
+            unsafe
+            {
+                if gl::ClientWaitSync(fences[section], gl::SYNC_FLUSH_COMMANDS_BIT, u64::MAX) == gl::WAIT_FAILED
+                {
+                    panic!("Some error occured while waiting for Buffer to Sync");
+                }
+                gl::DeleteSync(fences[section]);
+            }
+            fences[section] = ptr::null();
+            self.section_fences.set(fences);
+        }
+        self.completed_frames.set(frame.saturating_sub(RING_SECTIONS as u64 - 1));
 
-        let vbo_start: *mut c_void;
-        let ebo_start: *mut c_void;
+        // the commands are built from the current place of the allocations, moving an allocation patches its commands
+        let mut opaque = Vec::new();
+        let mut transparent = Vec::new();
+        for (draw, token) in allocations.enumerate()
+        {
+            let allocation = self.allocations.get(&token).copied().unwrap_or(PoolAllocation{vertex_start: 0, num_vertices: 0, index_start: 0, num_indices: 0, num_transparent: 0});
+            let num_opaque = allocation.num_indices - allocation.num_transparent;
 
-        let vbo_bucket_size = max_num_vertices * mem::size_of::<T>(); // max size of a single vbo bucket
-        let ebo_bucket_size = max_num_indices * mem::size_of::<u32>(); // max size of a single ebo bucket
+            opaque.push(Daic::new(num_opaque, 1, allocation.index_start + allocation.num_transparent, allocation.vertex_start).with_base_instance(draw as u32));
+            transparent.push(Daic::new(allocation.num_transparent, 1, allocation.index_start, allocation.vertex_start).with_base_instance(draw as u32));
+        }
 
-        println!("vertex pool allocator will allocate ~{} MBs of GPU memory", (max_num_buckets * (vbo_bucket_size + ebo_bucket_size))/1000000);
+        let num_prepared = opaque.len();
+        opaque.append(&mut transparent);
+        let commands = opaque;
 
+        // Safety: an allocation is drawn at most once, the section has room for two commands per allocation
         unsafe
         {
-            // Setup Persistent VBO for Vertex Data
-            gl::GenBuffers(1, &mut pers_vbo);
-            gl::BindBuffer(gl::ARRAY_BUFFER, pers_vbo);
-            gl::BufferStorage(gl::ARRAY_BUFFER, (max_num_buckets * vbo_bucket_size) as isize, std::ptr::null::<c_void>(), flags);
+            let section_start = (self.commands.start as *mut Daic).add(section * self.section_len);
+            section_start.copy_from_nonoverlapping(commands.as_ptr(), commands.len());
+        }
+
+        self.num_prepared.set(num_prepared);
+        commands
+    }
+
+    /// Fence the commands of the frame, the next frame writes into the next section of the ring
+    pub fn end_frame(&self)
+    {
+        let frame = self.frame.get();
+
+        let mut fences = self.section_fences.get();
+        fences[frame as usize % RING_SECTIONS] = unsafe { gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0) };
+        self.section_fences.set(fences);
+
+        self.frame.set(frame + 1);
+    }
+
+    /// Draw the opaque range of every allocation prepared for the frame, in one call
+    pub fn draw_opaque(&self)
+    {
+        let (buffer, offset, num) = self.get_frame_commands();
+        self.draw_commands(buffer, offset, num);
+    }
+
+    /// Draw the transparent range of every allocation prepared for the frame, in one call
+    pub fn draw_transparent(&self)
+    {
+        let (buffer, offset, num) = self.get_frame_commands();
+        self.draw_commands(buffer, offset + num * mem::size_of::<Daic>(), num);
+    }
 
-            vbo_start = gl::MapBufferRange(gl::ARRAY_BUFFER, 0, (max_num_buckets * vbo_bucket_size) as isize,flags);
+    /// Ids of the vertex and index buffers, they change when the pool grows
+    pub fn get_buffers(&self) -> (u32, u32)
+    {
+        (self.vbo.id, self.ebo.id)
+    }
 
-            // Setup persistent EBO
-            gl::GenBuffers(1, &mut ebo);
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
-            gl::BufferStorage(gl::ELEMENT_ARRAY_BUFFER, (max_num_buckets * max_num_indices * mem::size_of::<u32>()) as isize, std::ptr::null::<c_void>(), flags);
-            
-            ebo_start = gl::MapBufferRange(gl::ELEMENT_ARRAY_BUFFER, 0, (max_num_buckets * max_num_indices * mem::size_of::<u32>()) as isize,flags);
+    /// Buffer, byte offset and number of allocations of the commands prepared for the frame
+    pub fn get_frame_commands(&self) -> (u32, usize, usize)
+    {
+        let section = self.frame.get() as usize % RING_SECTIONS;
+        (self.commands.id, section * self.section_len * mem::size_of::<Daic>(), self.num_prepared.get())
+    }
 
-            println!("vbo start pointer is {:?}", vbo_start);
-            println!("ebo start pointer is {:?}", ebo_start);
+    /// Draw count commands of the pool's geometry, read from buffer at the byte offset
+    pub fn draw_commands(&self, buffer: u32, offset: usize, count: usize)
+    {
+        if count == 0
+        {
+            return;
         }
 
-        // init the buckets
-        for i in 0..max_num_buckets
+        // use indirect draw mode
+        self.vao.bind();
+        unsafe
         {
-            free_pool.push_back(i as u32);
+            gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, buffer);
+            gl::MultiDrawElementsIndirect(gl::TRIANGLES, gl::UNSIGNED_INT, offset as *const c_void, count as i32, mem::size_of::<Daic>() as i32);
+            gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, 0);
         }
+        self.vao.unbind();
+    }
 
-        // create the VAO
-        let vao = VertexArray::new(VertexBuffer::from_id(pers_vbo),&T::get_layout(), IndexBuffer::from_id(ebo));
+    pub fn get_stats(&self) -> PoolStats
+    {
+        self.stats
+    }
 
-        vao.bind(); // add draw_ind_buffer to the current vao
-        unsafe
+    /// Give the retired ranges no frame in flight can draw anymore back to the free lists
+    fn reclaim_ranges(&mut self)
+    {
+        // a range retired in frame f was last read by the commands of frame f-1
+        while let Some((allocation, frame)) = self.retired.front().copied()
         {
-            gl::GenBuffers(1, &mut draw_ind_buffer);
-            gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, draw_ind_buffer);
+            if frame > self.completed_frames.get()
+            {
+                break;
+            }
+
+            self.retired.pop_front();
+            self.vertices.free(allocation.vertex_start, allocation.num_vertices);
+            self.indices.free(allocation.index_start, allocation.num_indices);
         }
-        vao.unbind();
-        
-        Self{max_num_buckets, max_num_indices, max_num_vertices, vao, draw_ind_buffer, free_pool,draw_calls: Vec::new(), indices: Vec::new(),
-                allocation_index: vec![-1;max_num_buckets], vbo_start, ebo_start, vbo_bucket_size, ebo_bucket_size, sync_obj: unsafe {gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0)}, dirty:Cell::new(false)}
     }
 
-    /// Add the mesh to the pool
-    pub fn alloc(&mut self, mesh: &mut Mesh<T>) -> Option<AllocToken>
+    fn retire(&mut self, allocation: PoolAllocation)
+    {
+        self.retired.push_back((allocation, self.frame.get()));
+    }
+
+    // the copy reading a moved-from range is only fenced at the end of the current frame
+    fn retire_moved(&mut self, allocation: PoolAllocation)
+    {
+        self.retired.push_back((allocation, self.frame.get() + 1));
+    }
+
+    /// Reserve the ranges of a mesh, the buffers grow until they fit
+    fn reserve(&mut self, num_vertices: u32, num_indices: u32) -> (u32, u32)
     {
-        if self.free_pool.is_empty() || mesh.indices.len() > self.max_num_indices || mesh.vertices.len() > self.max_num_vertices
+        let vertex_start = loop
         {
-            println!("None returned");
-            return None;
+            match self.vertices.alloc(num_vertices)
+            {
+                Some(start) => break start,
+                None => self.grow_vertices(num_vertices),
+            }
+        };
+
+        let index_start = loop
+        {
+            match self.indices.alloc(num_indices)
+            {
+                Some(start) => break start,
+                None => self.grow_indices(num_indices),
+            }
+        };
+
+        (vertex_start, index_start)
+    }
+
+    // double the capacity, or more if the allocation does not fit in that
+    fn get_grown_capacity(list: &FreeList, needed: u32) -> u32
+    {
+        (list.get_capacity() * 2).max(list.get_num_used() + needed)
+    }
+
+    fn grow_vertices(&mut self, needed: u32)
+    {
+        let capacity = Self::get_grown_capacity(&self.vertices, needed);
+        let old_id = self.vbo.grow(gl::ARRAY_BUFFER, capacity as usize * mem::size_of::<T>());
+        self.vertices.grow(capacity);
+        self.replace_vao(old_id);
+    }
+
+    fn grow_indices(&mut self, needed: u32)
+    {
+        let capacity = Self::get_grown_capacity(&self.indices, needed);
+        let old_id = self.ebo.grow(gl::ELEMENT_ARRAY_BUFFER, capacity as usize * mem::size_of::<u32>());
+        self.indices.grow(capacity);
+        self.replace_vao(old_id);
+    }
+
+    // point the VAO to the current buffers, after one of them was replaced by a larger one
+    fn replace_vao(&mut self, old_buffer: u32)
+    {
+        let vao = VertexArray::new(VertexBuffer::from_id(self.vbo.id), &T::get_layout(), IndexBuffer::from_id(self.ebo.id));
+        let old_vao = mem::replace(&mut self.vao, vao);
+
+        // dropping the old VAO would delete the buffer that was kept, only delete what was replaced
+        unsafe
+        {
+            gl::DeleteVertexArrays(1, &old_vao.get_id());
+            gl::DeleteBuffers(1, &old_buffer);
         }
+        mem::forget(old_vao);
 
-        // allocate a bucket and return a reference to it
+        self.stats.num_growths += 1;
+    }
+
+    fn grow_commands(&mut self)
+    {
+        let section_len = self.section_len * 2;
+        let old = mem::replace(&mut self.commands, PersistentBuffer::new(gl::DRAW_INDIRECT_BUFFER, RING_SECTIONS * section_len * mem::size_of::<Daic>()));
+        self.section_len = section_len;
 
-        let index = self.free_pool.pop_front().unwrap(); // Guaranteed to not panic since we already checked that free_pool is not empty
-        
+        // the fences are kept, they still tell which frames are done
         unsafe
         {
-            let wait = gl::ClientWaitSync(self.sync_obj, gl::SYNC_FLUSH_COMMANDS_BIT, 0); // wait for previous operation on buffer to complete
+            gl::DeleteBuffers(1, &old.id);
+        }
+    }
+
+    /// Move the allocation at the end of the vertex buffer and the one at the end of the index buffer to the first free range that fits
+    ///
+    /// Returns false if nothing could be moved closer to the start
+    fn defragment_step(&mut self) -> bool
+    {
+        let mut moved = false;
+
+        let last_vertices = self.allocations.iter().filter(|(_, alloc)| alloc.num_vertices > 0).max_by_key(|(_, alloc)| alloc.vertex_start).map(|(token, _)| *token);
+        if let Some(token) = last_vertices
+        {
+            let allocation = self.allocations[&token];
+            if let Some(start) = self.vertices.alloc(allocation.num_vertices)
+            {
+                if start < allocation.vertex_start
+                {
+                    let size = mem::size_of::<T>();
+                    self.vbo.copy_within(allocation.vertex_start as usize * size, start as usize * size, allocation.num_vertices as usize * size);
+                    self.retire_moved(PoolAllocation{num_indices: 0, ..allocation});
+                    self.allocations.get_mut(&token).unwrap().vertex_start = start;
+                    moved = true;
+                }
+                else
+                {
+                    self.vertices.free(start, allocation.num_vertices);
+                }
+            }
+        }
 
-            if wait == gl::WAIT_FAILED
+        let last_indices = self.allocations.iter().filter(|(_, alloc)| alloc.num_indices > 0).max_by_key(|(_, alloc)| alloc.index_start).map(|(token, _)| *token);
+        if let Some(token) = last_indices
+        {
+            let allocation = self.allocations[&token];
+            if let Some(start) = self.indices.alloc(allocation.num_indices)
             {
-                panic!("Some error occured while waiting for Buffer to Sync");
+                if start < allocation.index_start
+                {
+                    let size = mem::size_of::<u32>();
+                    self.ebo.copy_within(allocation.index_start as usize * size, start as usize * size, allocation.num_indices as usize * size);
+                    self.retire_moved(PoolAllocation{num_vertices: 0, ..allocation});
+                    self.allocations.get_mut(&token).unwrap().index_start = start;
+                    moved = true;
+                }
+                else
+                {
+                    self.indices.free(start, allocation.num_indices);
+                }
             }
+        }
+
+        if moved
+        {
+            self.stats.num_moves += 1;
+        }
+        moved
+    }
+
+    fn update_stats(&mut self)
+    {
+        self.stats.num_allocations = self.allocations.len();
+        self.stats.vertex_bytes_used = self.vertices.get_num_used() as usize * mem::size_of::<T>();
+        self.stats.vertex_bytes_capacity = self.vbo.size;
+        self.stats.vertex_fragmentation = self.vertices.get_fragmentation();
+        self.stats.index_bytes_used = self.indices.get_num_used() as usize * mem::size_of::<u32>();
+        self.stats.index_bytes_capacity = self.ebo.size;
+        self.stats.index_fragmentation = self.indices.get_fragmentation();
+    }
+}
 
-            let vertex_start = self.vbo_start.add(index as usize * self.vbo_bucket_size);
-            let index_start = self.ebo_start.add(index as usize * self.ebo_bucket_size);
+impl<T> MeshAllocator<T> for VertexPoolAllocator<T>
+    where T: OpenglVertex
+{
+    fn alloc(&mut self, mesh: &mut Mesh<T>)
+    {
+        self.alloc_with_transparent(mesh, 0);
+    }
+
+    /// Copy the mesh into free ranges of the pool, the pool grows if there are none large enough
+    fn alloc_with_transparent(&mut self, mesh: &mut Mesh<T>, num_transparent: usize)
+    {
+        self.reclaim_ranges();
 
+        let (num_vertices, num_indices) = (mesh.vertices.len() as u32, mesh.indices.len() as u32);
+        let (vertex_start, index_start) = self.reserve(num_vertices, num_indices);
+
+        // Safety: the ranges are free and not used by any frame in flight, the data fits in them
+        unsafe
+        {
             // place the vertex data
-            vertex_start.copy_from(mesh.vertices.as_ptr() as _, mesh.get_vertices_size_bytes());
+            self.vbo.start.add(vertex_start as usize * mem::size_of::<T>()).copy_from_nonoverlapping(mesh.vertices.as_ptr() as _, mesh.get_vertices_size_bytes());
             // place the index data
-            index_start.copy_from(mesh.indices.as_ptr() as _, mesh.get_indices_size_bytes());
-            self.sync_obj = gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0);
+            self.ebo.start.add(index_start as usize * mem::size_of::<u32>()).copy_from_nonoverlapping(mesh.indices.as_ptr() as _, mesh.get_indices_size_bytes());
         }
 
-        self.draw_calls.push(Daic::new(mesh.indices.len() as u32, 1, index * self.max_num_indices as u32, index * self.max_num_vertices as u32));
-        self.dirty.set(true);
+        let token = self.next_token;
+        self.next_token = self.next_token.wrapping_add(1);
+        self.allocations.insert(token, PoolAllocation{vertex_start, num_vertices, index_start, num_indices, num_transparent: num_transparent as u32});
+
+        // an opaque and a transparent command per allocation must fit in a section of the ring
+        if self.allocations.len() * 2 > self.section_len
+        {
+            self.grow_commands();
+        }
 
-        Some(AllocToken::new(index)) // TODO: document why we can return index, very important !!!!
+        mesh.alloc_token = Some(AllocToken::new(token));
     }
 
-    /// Remove the allocation from the pool
-    pub fn dealloc(&mut self, allocation: AllocToken) 
+    /// Remove the allocation from the pool, its ranges are reused once the frames in flight are done with them
+    fn dealloc(&mut self, allocation: AllocToken)
     {
-        let index = allocation.index as usize;
-        // remove the corresponding draw call, and get the return the bucket to the pool
-        let last = self.draw_calls.len()-1;
-        self.draw_calls.swap(index, last); // swap and delete last so we don't delete from the middle of a Vec,
-        // possibly causing an expensive move to keep the vec continous
-        self.draw_calls.remove(last);
-        self.dirty.set(true);
-
-        // update allocation index array
-        self.allocation_index[last] = index as i32;
-        self.allocation_index[index] = -1;
+        let allocation = self.allocations.remove(&allocation.index).expect("dealloc of an unknown allocation");
+        self.retire(allocation);
     }
 
-    pub fn render(&self)
+    /// Move a few allocations towards the start of the buffers while the pool is fragmented
+    fn update(&mut self)
     {
-        if self.dirty.get() // commands are dirty ?
+        self.reclaim_ranges();
+
+        for _ in 0..DEFRAG_MOVES_PER_FRAME
         {
-            self.upload_draw_commands();
-            self.dirty.set(false);
+            if self.vertices.get_fragmentation() < DEFRAG_THRESHOLD && self.indices.get_fragmentation() < DEFRAG_THRESHOLD
+            {
+                break;
+            }
+
+            if !self.defragment_step()
+            {
+                break;
+            }
         }
-        
-         // use indirect draw mode
-         self.vao.bind();
-         unsafe
-         {
-            gl::MultiDrawElementsIndirect(gl::TRIANGLES, gl::UNSIGNED_INT, std::ptr::null::<c_void>(), self.draw_calls.len() as i32, mem::size_of::<Daic>() as i32);
-         }
+
+        self.update_stats();
     }
+}
 
-    fn upload_draw_commands(&self)
+impl<T> Drop for VertexPoolAllocator<T>
+{
+    fn drop(&mut self)
     {
-        unsafe
+        for fence in self.section_fences.get()
         {
-            gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, self.draw_ind_buffer);
-            gl::BufferData(gl::DRAW_INDIRECT_BUFFER, (self.draw_calls.len() * mem::size_of::<Daic>()) as isize, self.draw_calls.as_ptr() as _, gl::DYNAMIC_DRAW);
+            if !fence.is_null()
+            {
+                unsafe { gl::DeleteSync(fence); }
+            }
         }
-    }
 
+        // the vbo and ebo are deleted with the VAO
+        unsafe { gl::DeleteBuffers(1, &self.commands.id); }
+    }
 }
 
+/// DrawElementsIndirectCommand as read by Opengl
 #[repr(C,packed)]
+#[derive(Clone, Copy)]
 pub struct Daic
 {
-    // fields read by Opengl
     num_indices: u32,
     num_instances: u32,
     start_index: u32,
     start_vertex: u32,
     base_inst: u32,
-
-    // Additional custom fields we include
-    index: i32, // points to an entry in the allocation index vector
 }
 
 impl Daic
 {
     pub fn new(num_indices: u32, num_instances: u32, start_index: u32, start_vertex: u32) -> Self
     {
-        Self{num_indices, num_instances, start_index, start_vertex, base_inst: 0, index: 0}
+        Self{num_indices, num_instances, start_index, start_vertex, base_inst: 0}
+    }
+
+    /// Read in the shaders as gl_BaseInstance
+    pub fn with_base_instance(self, base_inst: u32) -> Self
+    {
+        Self{base_inst, ..self}
+    }
+
+    pub fn get_base_instance(&self) -> u32
+    {
+        self.base_inst
+    }
+
+    pub fn get_num_indices(&self) -> u32
+    {
+        self.num_indices
     }
-}
\ No newline at end of file
+
+    pub fn get_num_instances(&self) -> u32
+    {
+        self.num_instances
+    }
+
+    /// Same command, drawing nothing
+    pub fn hidden(&self) -> Self
+    {
+        Self{num_instances: 0, ..*self}
+    }
+}
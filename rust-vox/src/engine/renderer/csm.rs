@@ -1,7 +1,7 @@
 // contains the implementation of Cascaded Shadow Maps
 
 use std::{ffi::c_void, mem::size_of};
-use glam::{Mat4, Vec3, Vec4, Vec4Swizzles};
+use glam::{IVec3, Mat4, Vec3, Vec4, Vec4Swizzles};
 use crate::engine::camera::{Camera};
 
 pub struct Csm
@@ -9,6 +9,7 @@ pub struct Csm
     cascades: Vec<f32>,
     cascade_bounds: Vec<(Vec3,f32)>,
     prev_view_trans: Vec<Mat4>,
+    origin: IVec3, // render origin of the camera, the light space transforms are relative to it
 
     // depth texture paramters
     width: i32,
@@ -46,9 +47,23 @@ impl Csm
         }
 
         // define cascades
-        let cascades = vec![near_plane,far_plane/50.0,far_plane/25.0,far_plane/10.0,far_plane/2.0,far_plane];
+        let cascades = Self::get_cascade_splits(near_plane, far_plane);
         let prev_view_trans = vec![Mat4::IDENTITY;cascades.len()-1];
         let light_space_matrices = vec![Mat4::IDENTITY;cascades.len()-1];
+        let cascade_bounds = Self::get_cascade_bounds(&cascades, eye);
+
+        let depth_texture_array = Self::create_depth_texture_array(width, height, cascades.len()-1);
+
+        Self{ cascades, prev_view_trans, origin: eye.get_render_origin(), depth_texture_array, matrices_ubo, light_space_matrices, cascade_bounds, width, height }
+    }
+
+    fn get_cascade_splits(near_plane: f32, far_plane: f32) -> Vec<f32>
+    {
+        vec![near_plane,far_plane/50.0,far_plane/25.0,far_plane/10.0,far_plane/2.0,far_plane]
+    }
+
+    fn get_cascade_bounds(cascades: &[f32], eye: &Camera) -> Vec<(Vec3,f32)>
+    {
         let mut cascade_bounds = vec![(Vec3::ZERO,0.0);cascades.len()-1];
 
         let mut i = 0;
@@ -57,8 +72,13 @@ impl Csm
             cascade_bounds[i] = Self::precalculate_cascade_center(cascades[i],cascades[i+1], eye.fov_y, eye.aspect_ratio);
             i += 1;
         }
-    
-        // allocate a 3d texture for the depth textures
+
+        cascade_bounds
+    }
+
+    /// Allocate a 3d texture for the depth textures, one layer per cascade
+    fn create_depth_texture_array(width: i32, height: i32, layers: usize) -> u32
+    {
         let mut depth_texture_array = 0;
 
         unsafe
@@ -66,7 +86,7 @@ impl Csm
             gl::GenTextures(1, &mut depth_texture_array);
             gl::BindTexture(gl::TEXTURE_2D_ARRAY, depth_texture_array);
             gl::TexImage3D(gl::TEXTURE_2D_ARRAY, 0, gl::DEPTH_COMPONENT32F as _ , width, height,
-                (cascades.len()-1).try_into().unwrap(), 0 , gl::DEPTH_COMPONENT, gl::FLOAT , std::ptr::null::<c_void>() );
+                layers.try_into().unwrap(), 0 , gl::DEPTH_COMPONENT, gl::FLOAT , std::ptr::null::<c_void>() );
 
             gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MIN_FILTER,gl::NEAREST as _ );
             gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MAG_FILTER,gl::NEAREST as _ );
@@ -77,11 +97,42 @@ impl Csm
             gl::BindTexture(gl::TEXTURE_2D_ARRAY, 0); // unbind
         }
 
-        Self{ cascades, prev_view_trans, depth_texture_array, matrices_ubo, light_space_matrices, cascade_bounds, width, height }
+        depth_texture_array
+    }
+
+    /// Reallocate the depth textures with the new resolution
+    ///
+    /// The texture id changes, it has to be attached again wherever it was used
+    pub fn set_resolution(&mut self, width: i32, height: i32)
+    {
+        unsafe
+        {
+            gl::DeleteTextures(1, &self.depth_texture_array);
+        }
+
+        self.depth_texture_array = Self::create_depth_texture_array(width, height, self.cascades.len()-1);
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Recalculate the cascades after the camera's projection changed (near/far planes, fov, aspect ratio)
+    pub fn update_cascades(&mut self, eye: &Camera)
+    {
+        self.cascades = Self::get_cascade_splits(eye.near_plane, eye.far_plane);
+        self.cascade_bounds = Self::get_cascade_bounds(&self.cascades, eye);
     }
 
     pub fn update(&mut self , eye: &Camera, light_direction: Vec3)
     {
+        // keep snapping the cascades on the same texels when the camera enters another chunk
+        let origin = eye.get_render_origin();
+        if origin != self.origin
+        {
+            let shift = Mat4::from_translation((origin - self.origin).as_vec3());
+            self.prev_view_trans.iter_mut().for_each(|prev_view_trans| *prev_view_trans *= shift);
+            self.origin = origin;
+        }
+
         // calculate the updated light-space matrices for each cascasde
         self.get_cascaded_lightspace_matrices(eye,light_direction);
         unsafe
@@ -137,8 +188,8 @@ impl Csm
         let center = bound_sphere.0;
         let radius = bound_sphere.1;
 
-        // transform the center from camera's view space -> world space
-        let mut center_world = eye.get_look_at().inverse() * Vec4::new(center.x, center.y, center.z,1.0);
+        // transform the center from camera's view space -> world space, relative to the render origin
+        let mut center_world = eye.get_relative_look_at().inverse() * Vec4::new(center.x, center.y, center.z,1.0);
         center_world /= center_world.w; // transform to cartesian coordinates
 
         // snap the current center to texel offsets from the last center
@@ -203,6 +254,7 @@ impl Csm
 
     pub fn get_depth_texture_id (&self) -> u32 { self.depth_texture_array }
     pub fn get_cascade_levels(&self) -> &Vec<f32> { &self.cascades }
+    pub fn get_light_space_matrices(&self) -> &[Mat4] { &self.light_space_matrices }
 
     pub fn get_height(&self) -> i32 { self.height }
     pub fn get_width(&self) -> i32 { self.width }
@@ -0,0 +1,343 @@
+// Frustum culling of the chunk draw commands, for every view (the camera, then each shadow cascade)
+// On the GPU the AABBs of the chunks are uploaded next to the commands of the vertex pool, a compute shader tests them against
+// the planes of the view and writes its commands before the multi-draws, the counts are read back a few frames later
+// On the CPU the commands of the view are built the same way and uploaded, the counts are those of the current frame
+// The chunks hidden by the cave culling can be left out of a view, ex: the camera, the shadow cascades keep them as casters
+
+use std::{ffi::c_void, mem::size_of};
+use glam::{Mat4, Vec3, Vec4, Vec4Swizzles};
+
+use crate::{assets::asset_path, engine::{camera::AABB, geometry::voxel_vertex::VoxelVertex}};
+
+use super::{opengl_abstractions::shader::Shader, allocators::vertex_pool_allocator::{VertexPoolAllocator, Daic}};
+
+// the counters are read back this many frames later, the vertex pool has waited for that frame to be done
+const READBACK_FRAMES: usize = 3;
+const WORKGROUP_SIZE: usize = 64; // local_size_x of the compute shader
+
+/// Planes of the frustum of a view-projection matrix, pointing inside, in the order left, right, bottom, top, near, far
+///
+/// Without the near plane the geometry behind the view still passes, ex: shadow casters between the light and a cascade
+pub fn get_frustum_planes(view_proj: &Mat4, cull_near: bool) -> [Vec4; 6]
+{
+    let (row0, row1, row2, row3) = (view_proj.row(0), view_proj.row(1), view_proj.row(2), view_proj.row(3));
+    let near = if cull_near { row3 + row2 } else { Vec4::new(0.0, 0.0, 0.0, 1.0) };
+
+    [row3 + row0, row3 - row0, row3 + row1, row3 - row1, near, row3 - row2]
+}
+
+/// Same test as the compute shader
+pub fn is_box_visible(planes: &[Vec4; 6], aabb: &AABB) -> bool
+{
+    planes.iter().all(|plane|
+    {
+        let normal = plane.xyz();
+        let corner = normal.cmpgt(Vec3::ZERO);
+        let corner = Vec3::select(corner, aabb.max, aabb.min);
+        normal.dot(corner) + plane.w >= 0.0
+    })
+}
+
+/// Commands of a view, as written by the compute shader: the visible opaque commands compacted, then the transparent commands
+/// in their order with the culled ones drawing nothing
+///
+/// commands holds the opaque command of every chunk, then their transparent command. The chunks flagged in occluded are
+/// culled too, if given. Returns the number of visible chunks
+pub fn cull_commands(planes: &[Vec4; 6], aabbs: &[AABB], occluded: Option<&[bool]>, commands: &[Daic], culled: &mut Vec<Daic>) -> usize
+{
+    let num_chunks = aabbs.len();
+    debug_assert_eq!(commands.len(), 2 * num_chunks);
+
+    let visible: Vec<bool> = aabbs.iter().enumerate()
+        .map(|(i, aabb)| !occluded.is_some_and(|occluded| occluded[i]) && is_box_visible(planes, aabb)).collect();
+
+    culled.clear();
+    culled.extend(commands[..num_chunks].iter().zip(&visible).filter(|(command, visible)| **visible && command.get_num_indices() > 0).map(|(command, _)| *command));
+    culled.resize(num_chunks, Daic::new(0, 0, 0, 0));
+    culled.extend(commands[num_chunks..].iter().zip(&visible).map(|(command, visible)| if *visible { *command } else { command.hidden() }));
+
+    visible.iter().filter(|visible| **visible).count()
+}
+
+// output of the culling for one view
+struct CulledView
+{
+    commands: u32, // opaque commands compacted, then the transparent commands
+    counters: [u32; READBACK_FRAMES], // opaque commands and visible chunks, one buffer per frame in flight
+    num_visible: usize, // read back
+}
+
+pub struct ChunkCuller
+{
+    gpu: bool, // cull with the compute shader, else on the CPU
+    shader: Shader,
+    bounds: u32, // AABBs of the chunks of the frame
+
+    views: Vec<CulledView>,
+    capacity: usize, // chunks the buffers can hold
+    num_chunks: usize, // of the current frame
+    num_chunks_sent: [usize; READBACK_FRAMES], // of the frames using each counter buffer
+    num_chunks_read: usize, // of the frame whose counters were read back
+    frame: usize,
+
+    // CPU culling
+    aabbs: Vec<AABB>,
+    occluded: Vec<bool>,
+    commands: Vec<Daic>, // of the vertex pool for the frame
+    culled: Vec<Daic>,
+}
+
+impl Default for ChunkCuller
+{
+    fn default() -> Self
+    {
+        let shader = Shader::new_from_cs(&asset_path("shaders/cull.comp")).expect("Shader Error");
+
+        let mut bounds = 0;
+        unsafe
+        {
+            gl::GenBuffers(1, &mut bounds);
+        }
+
+        Self{gpu: true, shader, bounds, views: Vec::new(), capacity: 0, num_chunks: 0, num_chunks_sent: [0; READBACK_FRAMES], num_chunks_read: 0, frame: 0,
+            aabbs: Vec::new(), occluded: Vec::new(), commands: Vec::new(), culled: Vec::new()}
+    }
+}
+
+impl ChunkCuller
+{
+    pub fn set_gpu(&mut self, gpu: bool)
+    {
+        self.gpu = gpu;
+    }
+
+    /// Take the AABBs of the chunks, whether they are hidden by the cave culling, and the commands returned by the vertex pool's
+    /// prepare_frame(), in the same order
+    ///
+    /// Must be called after the vertex pool's prepare_frame(), followed by cull() for each view and end_frame()
+    pub fn prepare_frame(&mut self, aabbs: Vec<AABB>, occluded: Vec<bool>, commands: Vec<Daic>, num_views: usize)
+    {
+        let slot = self.frame % READBACK_FRAMES;
+
+        // the buffers of the views are sized for the largest frame so far
+        if aabbs.len() > self.capacity || num_views != self.views.len()
+        {
+            self.capacity = self.capacity.max(aabbs.len()).max(WORKGROUP_SIZE).next_power_of_two();
+            self.create_views(num_views);
+            self.num_chunks_sent = [0; READBACK_FRAMES];
+        }
+
+        self.num_chunks = aabbs.len();
+
+        if !self.gpu
+        {
+            // the views not culled this frame draw nothing
+            for view in self.views.iter_mut()
+            {
+                view.num_visible = 0;
+            }
+            self.num_chunks_read = self.num_chunks;
+            self.num_chunks_sent = [0; READBACK_FRAMES]; // the counters are not written
+
+            self.aabbs = aabbs;
+            self.occluded = occluded;
+            self.commands = commands;
+            return;
+        }
+
+        // read what was culled READBACK_FRAMES frames ago, before the counters are cleared
+        for view in self.views.iter_mut()
+        {
+            let mut counters = [0u32; 2];
+            unsafe
+            {
+                gl::BindBuffer(gl::COPY_READ_BUFFER, view.counters[slot]);
+                gl::GetBufferSubData(gl::COPY_READ_BUFFER, 0, size_of::<[u32; 2]>() as isize, counters.as_mut_ptr() as *mut c_void);
+                gl::BindBuffer(gl::COPY_READ_BUFFER, 0);
+            }
+            view.num_visible = counters[1] as usize;
+        }
+        self.num_chunks_read = self.num_chunks_sent[slot];
+        self.num_chunks_sent[slot] = aabbs.len();
+
+        // the w of the min corner is 0 for the occluded chunks
+        let bounds: Vec<Vec4> = aabbs.iter().zip(occluded).flat_map(|(aabb, occluded)| [aabb.min.extend(if occluded { 0.0 } else { 1.0 }), aabb.max.extend(1.0)]).collect();
+        unsafe
+        {
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, self.bounds);
+            gl::BufferData(gl::SHADER_STORAGE_BUFFER, (bounds.len() * size_of::<Vec4>()) as isize, bounds.as_ptr() as *const c_void, gl::STREAM_DRAW);
+
+            // the commands that are not written by the shader must draw nothing
+            for view in self.views.iter()
+            {
+                for buffer in [view.commands, view.counters[slot]]
+                {
+                    gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, buffer);
+                    gl::ClearBufferData(gl::SHADER_STORAGE_BUFFER, gl::R32UI, gl::RED_INTEGER, gl::UNSIGNED_INT, std::ptr::null());
+                }
+            }
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+        }
+    }
+
+    /// Cull the commands of the frame for the view, the result is drawn with draw_opaque() and draw_transparent()
+    ///
+    /// With occlusion the chunks hidden by the cave culling are culled too
+    pub fn cull(&mut self, view: usize, view_proj: &Mat4, cull_near: bool, occlusion: bool, allocator: &VertexPoolAllocator<VoxelVertex>)
+    {
+        if self.num_chunks == 0
+        {
+            return;
+        }
+
+        let (commands, offset, num) = allocator.get_frame_commands();
+        debug_assert_eq!(num, self.num_chunks);
+
+        self.cull_buffer(view, &get_frustum_planes(view_proj, cull_near), occlusion, commands, offset);
+    }
+
+    /// Cull the commands of the frame for the view, the compute shader reads them from the buffer at the byte offset
+    pub fn cull_buffer(&mut self, view: usize, planes: &[Vec4; 6], occlusion: bool, commands: u32, offset: usize)
+    {
+        let num = self.num_chunks;
+        if num == 0
+        {
+            return;
+        }
+
+        if !self.gpu
+        {
+            self.views[view].num_visible = cull_commands(planes, &self.aabbs, occlusion.then_some(&self.occluded[..]), &self.commands, &mut self.culled);
+            unsafe
+            {
+                gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, self.views[view].commands);
+                gl::BufferSubData(gl::DRAW_INDIRECT_BUFFER, 0, (self.culled.len() * size_of::<Daic>()) as isize, self.culled.as_ptr() as *const c_void);
+                gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, 0);
+            }
+            return;
+        }
+
+        let slot = self.frame % READBACK_FRAMES;
+
+        self.shader.bind();
+        self.shader.set_uniform_4fv("planes", planes).expect("error setting the frustum planes");
+        self.shader.set_uniform1ui("num_chunks", num as u32).expect("error setting the number of chunks");
+        self.shader.set_uniform1i("occlusion", i32::from(occlusion)).expect("error setting the occlusion");
+
+        unsafe
+        {
+            gl::BindBufferRange(gl::SHADER_STORAGE_BUFFER, 0, commands, offset as isize, (2 * num * size_of::<Daic>()) as isize);
+            gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, 1, self.bounds);
+            gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, 2, self.views[view].commands);
+            gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, 3, self.views[view].counters[slot]);
+
+            gl::DispatchCompute(num.div_ceil(WORKGROUP_SIZE) as u32, 1, 1);
+
+            // the draws read the commands written by the shader
+            gl::MemoryBarrier(gl::COMMAND_BARRIER_BIT | gl::SHADER_STORAGE_BARRIER_BIT);
+        }
+        Shader::unbind();
+    }
+
+    /// The commands written by the last culling of the view and its number of visible chunks, waits for the GPU
+    ///
+    /// Used to check the compute shader against cull_commands()
+    pub fn read_back(&self, view: usize) -> (Vec<Daic>, usize)
+    {
+        let view = &self.views[view];
+        let mut commands = vec![Daic::new(0, 0, 0, 0); 2 * self.num_chunks];
+        let mut counters = [0u32; 2];
+
+        unsafe
+        {
+            gl::MemoryBarrier(gl::BUFFER_UPDATE_BARRIER_BIT);
+            gl::BindBuffer(gl::COPY_READ_BUFFER, view.commands);
+            gl::GetBufferSubData(gl::COPY_READ_BUFFER, 0, (commands.len() * size_of::<Daic>()) as isize, commands.as_mut_ptr() as *mut c_void);
+            gl::BindBuffer(gl::COPY_READ_BUFFER, view.counters[self.frame % READBACK_FRAMES]);
+            gl::GetBufferSubData(gl::COPY_READ_BUFFER, 0, size_of::<[u32; 2]>() as isize, counters.as_mut_ptr() as *mut c_void);
+            gl::BindBuffer(gl::COPY_READ_BUFFER, 0);
+        }
+
+        (commands, counters[1] as usize)
+    }
+
+    /// The visible opaque commands of the view are compacted, the rest are empty
+    pub fn draw_opaque(&self, view: usize, allocator: &VertexPoolAllocator<VoxelVertex>)
+    {
+        allocator.draw_commands(self.views[view].commands, 0, self.num_chunks);
+    }
+
+    /// In the order given to prepare_frame(), the culled ones draw nothing
+    pub fn draw_transparent(&self, view: usize, allocator: &VertexPoolAllocator<VoxelVertex>)
+    {
+        allocator.draw_commands(self.views[view].commands, self.num_chunks * size_of::<Daic>(), self.num_chunks);
+    }
+
+    pub fn end_frame(&mut self)
+    {
+        self.frame += 1;
+    }
+
+    /// Chunks culled for the view, from a frame READBACK_FRAMES frames ago on the GPU, from the current one on the CPU
+    pub fn get_num_culled(&self, view: usize) -> usize
+    {
+        self.views.get(view).map_or(0, |view| self.num_chunks_read.saturating_sub(view.num_visible))
+    }
+
+    /// Chunks drawn for the view, from the same frame as get_num_culled()
+    pub fn get_num_drawn(&self, view: usize) -> usize
+    {
+        self.views.get(view).map_or(0, |view| view.num_visible.min(self.num_chunks_read))
+    }
+
+    fn create_views(&mut self, num_views: usize)
+    {
+        self.delete_views();
+
+        for _ in 0..num_views
+        {
+            let mut commands = 0;
+            let mut counters = [0u32; READBACK_FRAMES];
+            unsafe
+            {
+                gl::GenBuffers(1, &mut commands);
+                gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, commands);
+                gl::BufferData(gl::SHADER_STORAGE_BUFFER, (2 * self.capacity * size_of::<Daic>()) as isize, std::ptr::null(), gl::DYNAMIC_COPY);
+
+                gl::GenBuffers(READBACK_FRAMES as i32, counters.as_mut_ptr());
+                for counter in counters
+                {
+                    gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, counter);
+                    gl::BufferData(gl::SHADER_STORAGE_BUFFER, size_of::<[u32; 2]>() as isize, [0u32; 2].as_ptr() as *const c_void, gl::DYNAMIC_READ);
+                }
+                gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+            }
+
+            self.views.push(CulledView{commands, counters, num_visible: 0});
+        }
+    }
+
+    fn delete_views(&mut self)
+    {
+        for view in self.views.drain(..)
+        {
+            unsafe
+            {
+                gl::DeleteBuffers(1, &view.commands);
+                gl::DeleteBuffers(READBACK_FRAMES as i32, view.counters.as_ptr());
+            }
+        }
+    }
+}
+
+impl Drop for ChunkCuller
+{
+    fn drop(&mut self)
+    {
+        self.delete_views();
+        unsafe
+        {
+            gl::DeleteBuffers(1, &self.bounds);
+        }
+    }
+}
@@ -0,0 +1,161 @@
+// Draws the tiles of the distant horizon, before the chunks
+// The tiles use a projection with a far plane past the horizon, the depth buffer is cleared after them so the chunks are
+// drawn on top. A mask of the rendered chunks around the camera hides the tiles where the real chunk is drawn, a tile is
+// replaced chunk by chunk as they stream in
+
+use glam::{IVec2, Mat4, Vec3, Vec3Swizzles};
+
+use crate::{assets::asset_path, engine::{chunk::CHUNK_SIZE_X, chunk_manager::MAX_LOADED_DISTANCE, horizon::{Horizon, HorizonVertex}, world::World}};
+
+use super::{Renderer, opengl_abstractions::shader::Shader, allocators::{default_allocator::DefaultAllocator, MeshAllocator}, culling::{get_frustum_planes, is_box_visible}};
+
+const MASK_SIZE: i32 = 2 * MAX_LOADED_DISTANCE + 1; // in chunks, centered on the camera
+const NEAR_PLANE: f32 = 1.0; // the tiles close to the camera are hidden by the chunks
+
+pub struct HorizonRenderer
+{
+    horizon: Horizon,
+    allocator: DefaultAllocator<HorizonVertex>,
+    shader: Shader,
+
+    mask_texture: u32,
+    mask: Vec<u8>, // 1 where a chunk is rendered
+
+    num_drawn: usize,
+}
+
+impl Default for HorizonRenderer
+{
+    fn default() -> Self
+    {
+        let shader = Shader::new_from_vs_fs(&asset_path("shaders/horizon.vert"), &asset_path("shaders/horizon.frag")).expect("Shader Error");
+
+        let mut mask_texture = 0;
+        unsafe
+        {
+            gl::GenTextures(1, &mut mask_texture);
+            gl::ActiveTexture(gl::TEXTURE6);
+            gl::BindTexture(gl::TEXTURE_2D, mask_texture);
+            gl::TexStorage2D(gl::TEXTURE_2D, 1, gl::R8UI, MASK_SIZE, MASK_SIZE);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as _);
+            gl::ActiveTexture(gl::TEXTURE0);
+        }
+
+        Self{horizon: Horizon::default(), allocator: DefaultAllocator::new(), shader, mask_texture, mask: vec![0; (MASK_SIZE * MASK_SIZE) as usize], num_drawn: 0}
+    }
+}
+
+impl HorizonRenderer
+{
+    /// In chunks from the camera, 0 disables the horizon
+    pub fn set_distance(&mut self, distance: i32)
+    {
+        self.horizon.set_distance(distance);
+    }
+
+    /// Upload the new tiles and draw them, then clear the depth buffer
+    ///
+    /// rendered_chunks are the chunks that hide the tiles
+    pub fn render(&mut self, world: &World, rendered_chunks: &[IVec2], view: &Mat4, light_dir: Option<Vec3>, fog_density: f32)
+    {
+        let camera = &world.camera;
+        self.horizon.update(world.chunk_manager.get_generator(), camera.get_position());
+
+        for mut tile in self.horizon.take_removed_tiles()
+        {
+            if let Some(token) = tile.mesh.release_token()
+            {
+                self.allocator.dealloc(token);
+            }
+        }
+        for tile in self.horizon.get_tiles_mut().filter(|tile| !tile.mesh.is_alloc())
+        {
+            self.allocator.alloc(&mut tile.mesh);
+        }
+
+        self.num_drawn = 0;
+        let horizon_end = (self.horizon.get_distance() * CHUNK_SIZE_X as i32) as f32;
+        if horizon_end == 0.0
+        {
+            return;
+        }
+
+        // the corners of the horizon are further than its end
+        let projection = Mat4::perspective_rh_gl(camera.fov_y, camera.aspect_ratio, NEAR_PLANE, horizon_end * 1.5);
+        let planes = get_frustum_planes(&(projection * *view), true);
+
+        // the tiles are drawn relative to the camera's chunk, like the chunks
+        let origin = camera.get_render_origin();
+        let camera_chunk = origin.xz() / CHUNK_SIZE_X as i32;
+        let mask_origin = camera_chunk - IVec2::splat(MAX_LOADED_DISTANCE);
+        self.mask.fill(0);
+        for texel in rendered_chunks.iter().map(|chunk_pos| *chunk_pos - mask_origin)
+        {
+            if texel.cmpge(IVec2::ZERO).all() && texel.cmplt(IVec2::splat(MASK_SIZE)).all()
+            {
+                self.mask[(texel.y * MASK_SIZE + texel.x) as usize] = 1;
+            }
+        }
+
+        unsafe
+        {
+            gl::ActiveTexture(gl::TEXTURE6);
+            gl::BindTexture(gl::TEXTURE_2D, self.mask_texture);
+
+            // the rows of the mask are not aligned on 4 bytes
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexSubImage2D(gl::TEXTURE_2D, 0, 0, 0, MASK_SIZE, MASK_SIZE, gl::RED_INTEGER, gl::UNSIGNED_BYTE, self.mask.as_ptr().cast());
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            gl::ActiveTexture(gl::TEXTURE0);
+        }
+
+        self.shader.bind();
+        self.shader.set_uniform_matrix4fv("projection", &projection).expect("error setting the horizon projection");
+        self.shader.set_uniform1i("voxel_textures", 0).expect("error binding texture altlas");
+        self.shader.set_uniform1i("chunk_mask", 6).expect("error setting the chunk mask");
+        self.shader.set_uniform3i("origin", origin).expect("error setting the render origin");
+        self.shader.set_uniform2i("mask_origin", mask_origin - camera_chunk).expect("error setting the chunk mask origin");
+        self.shader.set_uniform1i("chunk_size", CHUNK_SIZE_X as i32).expect("error setting the chunk size");
+        self.shader.set_uniform3fv("light_dir", &light_dir.unwrap_or(Vec3::ZERO)).expect("error setting the light direction uniform");
+        self.shader.set_uniform_1f("far", camera.far_plane).expect("error setting the far plane");
+        self.shader.set_uniform_1f("fog_density", fog_density).expect("error setting the fog density");
+        self.shader.set_uniform_1f("horizon_end", horizon_end).expect("error setting the horizon distance");
+
+        unsafe
+        {
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        }
+
+        for tile in self.horizon.get_tiles().filter(|tile| tile.mesh.is_alloc() && is_box_visible(&planes, &tile.aabb.translated(-origin.as_vec3())))
+        {
+            Renderer::draw_mesh(&self.allocator, &tile.mesh);
+            self.num_drawn += 1;
+        }
+
+        unsafe
+        {
+            gl::Disable(gl::BLEND);
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+        }
+        Shader::unbind();
+    }
+
+    /// Tiles built and tiles drawn in the last frame
+    pub fn get_num_tiles(&self) -> (usize, usize)
+    {
+        (self.horizon.get_num_tiles(), self.num_drawn)
+    }
+}
+
+impl Drop for HorizonRenderer
+{
+    fn drop(&mut self)
+    {
+        unsafe
+        {
+            gl::DeleteTextures(1, &self.mask_texture);
+        }
+    }
+}
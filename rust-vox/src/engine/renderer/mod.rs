@@ -1,20 +1,26 @@
-use std::{ffi::{c_void, CStr}, mem::{size_of, self}, rc::Rc, cell::{RefCell}};
+use std::{ffi::{c_void, CStr}, mem::size_of, rc::Rc, cell::{RefCell}};
 use gl::types;
-use glam::{Vec3, Mat3, Mat4, IVec2};
+use glam::{Vec3, Mat3, Mat4, IVec2, IVec3, IVec4};
 use image::EncodableLayout;
 use sdl2::{VideoSubsystem};
-use crate::DebugData;
+use crate::{DebugData, settings::Settings, assets::asset_path};
 
-use self::{opengl_abstractions::{shader::Shader}, csm::Csm, allocators::default_allocator::DefaultAllocator};
-use super::{world::{World}, geometry::{mesh::Mesh, opengl_vertex::OpenglVertex, chunk_mesh}, sky::{sky_state::Sky, sky_renderer::SkyRenderer}};
+use self::{opengl_abstractions::{shader::Shader}, csm::Csm, allocators::default_allocator::DefaultAllocator, culling::{ChunkCuller, get_frustum_planes}, occlusion::OcclusionQueries, horizon::HorizonRenderer};
+use super::{camera::{Camera, AABB, BoundingBox}, world::{World}, chunk::{CHUNK_SIZE_X, CHUNK_SIZE_Z}, geometry::{mesh::Mesh, opengl_vertex::OpenglVertex, chunk_mesh}, sky::{sky_state::Sky, sky_renderer::SkyRenderer}};
 
 pub mod opengl_abstractions;
 pub mod csm;
 pub mod allocators;
+pub mod culling;
+pub mod occlusion;
+pub mod horizon;
+
+const CHUNK_ORIGINS_BINDING: u32 = 4; // shader storage binding, the culling uses the ones before
 
 pub struct Renderer
 {
     trans_ubo: u32,
+    chunk_origins: u32, // shader storage buffer, the origin of the chunk of each draw
     csm: Csm,
     shadow_fb: u32,
     default_shader : Shader,
@@ -22,6 +28,14 @@ pub struct Renderer
     sun_direction: Vec3,
     pub sky: Sky,
     sky_rend : SkyRenderer,
+    culler: ChunkCuller,
+
+    viewport_size: (i32,i32),
+    fog_density: f32,
+    cave_culling: bool, // leave out of the main pass the chunks hidden behind the terrain
+    occlusion: OcclusionQueries,
+    occlusion_queries: bool, // leave out of the main pass the chunks whose box was hidden in the depth buffer
+    horizon: HorizonRenderer,
 
     // debug info
     debug_data: Rc<RefCell<DebugData>>,
@@ -33,7 +47,7 @@ pub struct Renderer
 
 impl Renderer
 {
-    pub fn new(video_subsystem: &VideoSubsystem, world: &World, debug_info: &Rc<RefCell<DebugData>>) -> Self
+    pub fn new(video_subsystem: &VideoSubsystem, world: &World, settings: &Settings, debug_info: &Rc<RefCell<DebugData>>) -> Self
     {
         // Setup
         // load up every opengl function, is this good ?
@@ -71,15 +85,18 @@ impl Renderer
             gl::BindBufferBase(gl::UNIFORM_BUFFER,0,trans_ubo);
             gl::BindBuffer(gl::UNIFORM_BUFFER,0);
 
+            let mut chunk_origins = 0;
+            gl::GenBuffers(1, &mut chunk_origins);
+
             // TODO: this does not belong here
             // Load the Voxel Textures
 
             let tex_width = 64;
             let tex_height = 64;
-            let dirt = image::open("rust-vox/textures/dirt.png").unwrap().flipv().into_rgba8();
-            let sand = image::open("rust-vox/textures/sand.png").unwrap().flipv().into_rgba8();
-            let water = image::open("rust-vox/textures/water.png").unwrap().flipv().into_rgba8();
-            let glass = image::open("rust-vox/textures/glass.png").unwrap().flipv().into_rgba8();
+            let dirt = image::open(asset_path("textures/dirt.png")).unwrap().flipv().into_rgba8();
+            let sand = image::open(asset_path("textures/sand.png")).unwrap().flipv().into_rgba8();
+            let water = image::open(asset_path("textures/water.png")).unwrap().flipv().into_rgba8();
+            let glass = image::open(asset_path("textures/glass.png")).unwrap().flipv().into_rgba8();
 
             let layer_count = 4; // only dirt and grass for now
             let mut texture_array = 0;
@@ -102,23 +119,48 @@ impl Renderer
             gl::BindTexture(gl::TEXTURE_2D_ARRAY,0); // unbind
 
             // load the program
-            let default_shader = Shader::new_from_vs_fs("rust-vox/shaders/default.vert",
-             "rust-vox/shaders/default.frag" ).expect("Shader Error");
+            let default_shader = Shader::new_from_vs_fs(&asset_path("shaders/default.vert"),
+             &asset_path("shaders/default.frag") ).expect("Shader Error");
             
             gl::Enable(gl::DEPTH_TEST);
             gl::Enable(gl::CULL_FACE);
             gl::FrontFace(gl::CW);
 
-            let shadow_shader =  Shader::new_from_vs_gs_fs("rust-vox/shaders/shadow.vert",
-            "rust-vox/shaders/shadow.geom", "rust-vox/shaders/shadow.frag" ).expect("Shader Error");
+            let shadow_shader =  Shader::new_from_vs_gs_fs(&asset_path("shaders/shadow.vert"),
+            &asset_path("shaders/shadow.geom"), &asset_path("shaders/shadow.frag") ).expect("Shader Error");
 
             // generate a framebuffer for the shadow map
             let mut shadow_fb = 0;
             gl::GenFramebuffers(1, &mut shadow_fb);
 
             // initialise the Shadows
-            let csm = Csm::new(2048,2048, &world.camera);
+            let csm = Csm::new(settings.shadow_map_resolution, settings.shadow_map_resolution, &world.camera);
+            Self::attach_shadow_map(shadow_fb, &csm);
+
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, texture_array);
+
+            let sky_rend = SkyRenderer::default();
+            
+            let viewport_size = (settings.window_width as i32, settings.window_height as i32);
+
+            let mut culler = ChunkCuller::default();
+            culler.set_gpu(settings.gpu_culling);
 
+            let mut horizon = HorizonRenderer::default();
+            horizon.set_distance(settings.horizon_distance);
+
+            Self { trans_ubo, chunk_origins, default_shader , shadow_shader , shadow_fb , csm, sun_direction: Vec3::ZERO, sky_rend,sky:Sky::default(), culler, debug_data: debug_info.clone(),
+                        timer_index, timers, viewport_size, fog_density: settings.fog_density, cave_culling: settings.cave_culling,
+                        occlusion: OcclusionQueries::default(), occlusion_queries: settings.occlusion_queries, horizon}
+        }
+    }
+
+    /// Attach the CSM depth textures to the shadow framebuffer, and bind them to texture unit 1 for the default shader
+    fn attach_shadow_map(shadow_fb: u32, csm: &Csm)
+    {
+        unsafe
+        {
             gl::BindFramebuffer(gl::FRAMEBUFFER, shadow_fb);
             gl::FramebufferTexture(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, csm.get_depth_texture_id(), 0);
             // need to explicitely mention that we will render no color on this framebuffer
@@ -129,15 +171,38 @@ impl Renderer
 
             gl::ActiveTexture(gl::TEXTURE1);
             gl::BindTexture(gl::TEXTURE_2D_ARRAY, csm.get_depth_texture_id());
-
             gl::ActiveTexture(gl::TEXTURE0);
-            gl::BindTexture(gl::TEXTURE_2D_ARRAY, texture_array);
+        }
+    }
 
-            let sky_rend = SkyRenderer::default();
-            
-            Self { trans_ubo, default_shader , shadow_shader , shadow_fb , csm, sun_direction: Vec3::ZERO, sky_rend,sky:Sky::default(), debug_data: debug_info.clone(),
-                        timer_index, timers}
+    /// Apply the settings that concern rendering, must be called after the camera has been updated
+    pub fn apply_settings(&mut self, old: &Settings, new: &Settings, camera: &Camera)
+    {
+        if old.shadow_map_resolution != new.shadow_map_resolution
+        {
+            self.csm.set_resolution(new.shadow_map_resolution, new.shadow_map_resolution);
+            Self::attach_shadow_map(self.shadow_fb, &self.csm);
         }
+
+        // the cascades depend on the projection
+        self.csm.update_cascades(camera);
+
+        self.fog_density = new.fog_density;
+        self.culler.set_gpu(new.gpu_culling);
+        self.cave_culling = new.cave_culling;
+        if new.occlusion_queries != self.occlusion_queries
+        {
+            self.occlusion.reset();
+        }
+        self.occlusion_queries = new.occlusion_queries;
+        self.horizon.set_distance(new.horizon_distance);
+    }
+
+    /// Called when the drawable size of the window changed, after the camera's aspect ratio has been updated
+    pub fn resize(&mut self, width: i32, height: i32, camera: &Camera)
+    {
+        self.viewport_size = (width, height);
+        self.csm.update_cascades(camera);
     }
 
     pub fn draw_world(&mut self, world: &World)
@@ -148,8 +213,10 @@ impl Renderer
             gl::BeginQuery(gl::TIME_ELAPSED, self.timers[self.timer_index]);
         }
 
+        // the world is rendered relative to the chunk of the camera, the positions stay small far from the world origin
+        let origin = world.camera.get_render_origin();
         let perspective = world.camera.get_persp_trans();
-        let view = world.camera.get_look_at();
+        let view = world.camera.get_relative_look_at();
         let view_no_trans = Mat4::from_mat3(Mat3::from_mat4(view));
 
         let transforms: [Mat4;3] = [perspective,view,view_no_trans];
@@ -161,10 +228,62 @@ impl Renderer
             gl::BindBuffer(gl::UNIFORM_BUFFER, 0); // unbind
         }
 
+        // the chunks the camera cannot see through the terrain, and those hidden in the last occlusion queries
+        let reachable = self.cave_culling.then(|| world.chunk_manager.get_reachable_chunks(world.camera.get_position()));
+        if self.occlusion_queries
+        {
+            self.occlusion.collect_results();
+        }
+
+        // the draw commands of the chunks, shared by every pass of the frame
+        let chunks: Vec<(u32, IVec2, AABB)> = world.chunk_manager.get_rendered_chunks().filter_map(|unit|
+        {
+            let chunk_mesh = unit.chunk_mesh.as_ref()?;
+            let token = chunk_mesh.mesh.alloc_token.as_ref()?.index;
+            let chunk = unit.chunk.as_ref()?;
+            Some((token, chunk.pos_chunk_space(), chunk_mesh.get_aabb(chunk.get_aabb()).translated(-origin.as_vec3())))
+        }).collect();
+        let aabbs: Vec<AABB> = chunks.iter().map(|chunk| chunk.2).collect();
+
+        // the vertices are in their chunk, the shaders find its origin with the base instance of the draw
+        let origins: Vec<IVec4> = chunks.iter().map(|chunk| (IVec3::new(chunk.1.x * CHUNK_SIZE_X as i32, 0, chunk.1.y * CHUNK_SIZE_Z as i32) - origin).extend(0)).collect();
+        unsafe
+        {
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, self.chunk_origins);
+            gl::BufferData(gl::SHADER_STORAGE_BUFFER, (origins.len() * size_of::<IVec4>()) as isize, origins.as_ptr() as *const c_void, gl::STREAM_DRAW);
+            gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, CHUNK_ORIGINS_BINDING, self.chunk_origins);
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+        }
+
+        let cave_occluded: Vec<bool> = chunks.iter().map(|chunk| reachable.as_ref().is_some_and(|reachable| !reachable.contains(&chunk.1))).collect();
+        let query_occluded: Vec<bool> = chunks.iter().map(|chunk| self.occlusion_queries && self.occlusion.is_occluded(chunk.1)).collect();
+        let num_cave_occluded = cave_occluded.iter().filter(|occluded| **occluded).count();
+        let num_query_occluded = cave_occluded.iter().zip(&query_occluded).filter(|(cave, query)| !**cave && **query).count();
+        let occluded = cave_occluded.iter().zip(&query_occluded).map(|(cave, query)| *cave || *query).collect();
+
+        let allocator = &world.chunk_manager.allocator;
+        let commands = allocator.prepare_frame(chunks.iter().map(|chunk| chunk.0));
+
         self.sky.update();
         self.sun_direction = self.sky.get_sun_direction();
         
         let sun_present = self.sky.is_sun_present();
+        if sun_present
+        {
+            self.csm.update(&world.camera,self.sun_direction);
+        }
+
+        // cull the commands for the camera, then for each shadow cascade
+        let light_space_matrices = self.csm.get_light_space_matrices();
+        self.culler.prepare_frame(aabbs, occluded, commands, 1 + light_space_matrices.len());
+        self.culler.cull(0, &(perspective * view), true, true, allocator);
+        if sun_present
+        {
+            for (cascade, matrix) in light_space_matrices.iter().enumerate()
+            {
+                self.culler.cull(1 + cascade, matrix, false, false, allocator);
+            }
+        }
 
         if sun_present // do not render shadows is the sun is not present
         {
@@ -178,7 +297,7 @@ impl Renderer
         {            
             // PASS 2: render the scene normally
             gl::BindFramebuffer(gl::FRAMEBUFFER, 0); // bind default framebuffer
-            gl::Viewport(0, 0, 1700 ,900);
+            gl::Viewport(0, 0, self.viewport_size.0, self.viewport_size.1);
             gl::ClearColor(0.25,0.5,0.88,1.0);
             gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
             gl::Enable(gl::CULL_FACE);
@@ -187,6 +306,10 @@ impl Renderer
             // render the sky
             self.sky_rend.render(&self.sky);
 
+            // the distant terrain, behind the chunks
+            let rendered: Vec<IVec2> = chunks.iter().map(|chunk| chunk.1).collect();
+            self.horizon.render(world, &rendered, &view, sun_present.then_some(self.sun_direction), self.fog_density);
+
             self.default_shader.bind();
 
             self.default_shader.set_uniform1i("render_csm", i32::from(sun_present)).expect("error setting the sun present uniform");
@@ -200,10 +323,35 @@ impl Renderer
             self.default_shader.set_uniform1i("cascade_count", cascades.len() as i32 ).expect("error setting the cascade count");
             self.default_shader.set_uniform_1fv("cascades", cascades).expect("error setting the cascades");
 
-            debug_data.culled_chunks = Self::draw_geometry(world, &mut self.default_shader);
+            self.default_shader.set_uniform_1f("near", world.camera.near_plane).expect("error setting the near plane");
+            self.default_shader.set_uniform_1f("far", world.camera.far_plane).expect("error setting the far plane");
+            self.default_shader.set_uniform_1f("fog_density", self.fog_density).expect("error setting the fog density");
+
+            // the depth of the opaque geometry is tested by the occlusion queries, before the transparent geometry
+            self.culler.draw_opaque(0, allocator);
+            if self.occlusion_queries
+            {
+                let boxes: Vec<(IVec2, AABB)> = chunks.iter().map(|chunk| (chunk.1, chunk.2)).collect();
+                self.occlusion.query(&boxes, &get_frustum_planes(&(perspective * view), true), world.camera.get_relative_position());
+                self.default_shader.bind();
+            }
+            Self::draw_transparent_geometry(world, &self.culler, 0);
             Shader::unbind();
         }
 
+        debug_data.culled_chunks = self.culler.get_num_culled(0);
+        debug_data.drawn_chunks = self.culler.get_num_drawn(0);
+        debug_data.occluded_chunks = num_cave_occluded;
+        debug_data.query_occluded_chunks = num_query_occluded;
+        (debug_data.horizon_tiles, debug_data.drawn_horizon_tiles) = self.horizon.get_num_tiles();
+        debug_data.cascade_chunks.clear();
+        if sun_present
+        {
+            debug_data.cascade_chunks.extend((0..self.csm.get_light_space_matrices().len()).map(|cascade| (self.culler.get_num_drawn(1 + cascade), self.culler.get_num_culled(1 + cascade))));
+        }
+        allocator.end_frame();
+        self.culler.end_frame();
+
         let mut timer: u64 = 0;
         unsafe
         {
@@ -215,59 +363,41 @@ impl Renderer
         }
 
         // update debug data
+        debug_data.pool_stats = world.chunk_manager.allocator.get_stats();
         debug_data.draw_world_time = timer as f64 / 1000000.0; // in ms
     }
 
-    fn draw_geometry(world: &World, shader: &mut Shader) -> usize
+    /// Draw the chunks left by the culling of the view
+    fn draw_geometry(world: &World, culler: &ChunkCuller, view: usize)
     {
-        // draw each chunk's mesh
-        let i = 0;
-
-        // first draw all opaque meshes
-        for chunk_mesh in world.chunk_manager.get_rendered_chunks()
-        {
-            let chunk_mesh = chunk_mesh.chunk_mesh.as_ref().unwrap();
-            let vao = world.chunk_manager.allocator.get_vao(chunk_mesh.mesh.alloc_token.as_ref().expect("mesh has no alloc token"));
-            
-            unsafe
-            {
-                vao.bind();
-
-                gl::DrawElements(gl::TRIANGLES, chunk_mesh.get_num_opaque_indices() as _ , gl::UNSIGNED_INT, (chunk_mesh.get_num_trans_indices() * mem::size_of::<u32>()) as _ );
+        // first draw all opaque meshes, then the transparent ones
+        culler.draw_opaque(view, &world.chunk_manager.allocator);
+        Self::draw_transparent_geometry(world, culler, view);
+    }
 
-                vao.unbind();
-            }
-        }
+    /// Draw the transparent chunks left by the culling of the view, the commands are ordered from back to front
+    fn draw_transparent_geometry(world: &World, culler: &ChunkCuller, view: usize)
+    {
+        let allocator = &world.chunk_manager.allocator;
 
-        // then draw all transparent meshes
-        for chunk_mesh in world.chunk_manager.get_rendered_chunks()
+        unsafe
         {
-            let chunk_mesh = chunk_mesh.chunk_mesh.as_ref().unwrap();
-            let vao = world.chunk_manager.allocator.get_vao(chunk_mesh.mesh.alloc_token.as_ref().unwrap());
-            
-            unsafe
-            {
-                vao.bind();
-                gl::Enable(gl::BLEND);
-                gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA); 
-
-                gl::DrawElements(gl::TRIANGLES, chunk_mesh.get_num_trans_indices() as _  , gl::UNSIGNED_INT, 0 as _ );
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA); 
+        }
 
-                gl::Disable(gl::BLEND);
+        culler.draw_transparent(view, allocator);
 
-                vao.unbind();
-            }  
+        unsafe
+        {
+            gl::Disable(gl::BLEND);
         }
-
-        i
     }
 
     /// Depth Only Render Pass
     /// Implements Cascaded Shadow Maps (CSM)
     fn render_shadow(&mut self, world: &World)
     {
-        self.csm.update(&world.camera,self.sun_direction);
-
         self.shadow_shader.bind();
 
         unsafe
@@ -278,7 +408,12 @@ impl Renderer
             gl::Disable(gl::CULL_FACE);
         }
 
-        Self::draw_geometry(world,&mut self.shadow_shader);
+        // one draw per cascade, each with the commands culled against its light frustum
+        for cascade in 0..self.csm.get_light_space_matrices().len()
+        {
+            self.shadow_shader.set_uniform1i("cascade", cascade as i32).expect("error setting the cascade");
+            Self::draw_geometry(world, &self.culler, 1 + cascade);
+        }
 
         unsafe
         {
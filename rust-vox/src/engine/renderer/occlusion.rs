@@ -0,0 +1,213 @@
+// Hardware occlusion culling of the chunks
+// After the opaque geometry of the main pass, the AABB of every chunk in the frustum is drawn against the depth buffer
+// inside an occlusion query. The results are read a few frames later, when they are available, the chunks whose box
+// was hidden are left out of the main pass until a new result says otherwise
+
+use std::{collections::HashSet, ffi::c_void, mem::size_of};
+use glam::{IVec2, Vec3, Vec4};
+
+use crate::{assets::asset_path, engine::camera::AABB};
+
+use super::{opengl_abstractions::shader::Shader, culling::is_box_visible};
+
+// the queries of a frame are read this many frames later, same as the timers of the renderer
+const QUERY_FRAMES: usize = 3;
+// the boxes are grown so the chunk's own geometry never hides them
+const PROXY_MARGIN: f32 = 0.5;
+
+const CUBE_CORNERS: [[f32; 3]; 8] = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0],
+                                     [0.0, 0.0, 1.0], [1.0, 0.0, 1.0], [1.0, 1.0, 1.0], [0.0, 1.0, 1.0]];
+const CUBE_INDICES: [u8; 36] = [0, 1, 2, 2, 3, 0, // -z
+                                4, 6, 5, 6, 4, 7, // +z
+                                0, 3, 7, 7, 4, 0, // -x
+                                1, 5, 6, 6, 2, 1, // +x
+                                0, 4, 5, 5, 1, 0, // -y
+                                3, 2, 6, 6, 7, 3]; // +y
+
+pub struct OcclusionQueries
+{
+    shader: Shader,
+    vao: u32, // unit cube
+    vbo: u32,
+    ebo: u32,
+
+    frames: [Vec<(IVec2, u32)>; QUERY_FRAMES], // queries issued each frame and their chunk
+    free_queries: Vec<u32>,
+    frame: usize,
+    waiting: bool, // the results of the slot of the frame are not available, no query is issued
+
+    occluded: HashSet<IVec2>, // chunks whose box was hidden in the last result
+}
+
+impl Default for OcclusionQueries
+{
+    fn default() -> Self
+    {
+        let shader = Shader::new_from_vs_fs(&asset_path("shaders/occlusion.vert"), &asset_path("shaders/occlusion.frag")).expect("Shader Error");
+
+        let (mut vao, mut vbo, mut ebo) = (0, 0, 0);
+        unsafe
+        {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::BufferData(gl::ARRAY_BUFFER, size_of::<[[f32; 3]; 8]>() as isize, CUBE_CORNERS.as_ptr() as *const c_void, gl::STATIC_DRAW);
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, size_of::<[f32; 3]>() as i32, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+
+            gl::GenBuffers(1, &mut ebo);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
+            gl::BufferData(gl::ELEMENT_ARRAY_BUFFER, CUBE_INDICES.len() as isize, CUBE_INDICES.as_ptr() as *const c_void, gl::STATIC_DRAW);
+
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+
+        Self{shader, vao, vbo, ebo, frames: Default::default(), free_queries: Vec::new(), frame: 0, waiting: false, occluded: HashSet::new()}
+    }
+}
+
+impl OcclusionQueries
+{
+    /// Read the results of the queries issued QUERY_FRAMES frames ago, never waits for the GPU
+    ///
+    /// Must be called once per frame before is_occluded() and query()
+    pub fn collect_results(&mut self)
+    {
+        let queries = &mut self.frames[self.frame % QUERY_FRAMES];
+
+        // the queries of a frame end in order, the last one tells if all are available
+        let mut available = 1;
+        if let Some((_, query)) = queries.last()
+        {
+            unsafe { gl::GetQueryObjectuiv(*query, gl::QUERY_RESULT_AVAILABLE, &mut available); }
+        }
+
+        self.waiting = available == 0;
+        if self.waiting
+        {
+            return;
+        }
+
+        for (chunk_pos, query) in queries.drain(..)
+        {
+            let mut samples_passed = 0;
+            unsafe { gl::GetQueryObjectuiv(query, gl::QUERY_RESULT, &mut samples_passed); }
+
+            if samples_passed == 0
+            {
+                self.occluded.insert(chunk_pos);
+            }
+            else
+            {
+                self.occluded.remove(&chunk_pos);
+            }
+            self.free_queries.push(query);
+        }
+    }
+
+    /// If the box of the chunk was hidden in the last result
+    pub fn is_occluded(&self, chunk_pos: IVec2) -> bool
+    {
+        self.occluded.contains(&chunk_pos)
+    }
+
+    /// Test the boxes of the chunks against the depth buffer, must be called after the opaque geometry of the main pass
+    ///
+    /// The chunks outside of the frustum or around the camera are visible right away, their query would say nothing
+    pub fn query(&mut self, chunks: &[(IVec2, AABB)], planes: &[Vec4; 6], camera_pos: Vec3)
+    {
+        // forget the chunks that are not rendered anymore
+        let rendered: HashSet<IVec2> = chunks.iter().map(|(chunk_pos, _)| *chunk_pos).collect();
+        self.occluded.retain(|chunk_pos| rendered.contains(chunk_pos));
+
+        if self.waiting
+        {
+            return;
+        }
+
+        self.shader.bind();
+        unsafe
+        {
+            gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+            gl::DepthMask(gl::FALSE);
+            gl::Disable(gl::CULL_FACE);
+            gl::BindVertexArray(self.vao);
+        }
+
+        let slot = self.frame % QUERY_FRAMES;
+        for (chunk_pos, aabb) in chunks
+        {
+            let proxy = AABB::new(aabb.min - PROXY_MARGIN, aabb.max + PROXY_MARGIN);
+            let contains_camera = camera_pos.cmpge(proxy.min).all() && camera_pos.cmple(proxy.max).all();
+
+            if contains_camera || !is_box_visible(planes, &proxy)
+            {
+                self.occluded.remove(chunk_pos);
+                continue;
+            }
+
+            let query = self.free_queries.pop().unwrap_or_else(||
+            {
+                let mut query = 0;
+                unsafe { gl::GenQueries(1, &mut query); }
+                query
+            });
+
+            self.shader.set_uniform3fv("box_min", &proxy.min).expect("error setting the box of the chunk");
+            self.shader.set_uniform3fv("box_max", &proxy.max).expect("error setting the box of the chunk");
+            unsafe
+            {
+                gl::BeginQuery(gl::ANY_SAMPLES_PASSED_CONSERVATIVE, query);
+                gl::DrawElements(gl::TRIANGLES, CUBE_INDICES.len() as i32, gl::UNSIGNED_BYTE, std::ptr::null());
+                gl::EndQuery(gl::ANY_SAMPLES_PASSED_CONSERVATIVE);
+            }
+            self.frames[slot].push((*chunk_pos, query));
+        }
+
+        unsafe
+        {
+            gl::BindVertexArray(0);
+            gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+            gl::DepthMask(gl::TRUE);
+            gl::Enable(gl::CULL_FACE);
+        }
+        Shader::unbind();
+
+        self.frame += 1;
+    }
+
+    /// Forget the hidden chunks and the queries in flight, their results are stale once the queries were turned off
+    pub fn reset(&mut self)
+    {
+        for queries in self.frames.iter_mut()
+        {
+            self.free_queries.extend(queries.drain(..).map(|(_, query)| query));
+        }
+        self.occluded.clear();
+        self.waiting = false;
+    }
+
+    /// Number of chunks hidden in the last result
+    pub fn get_num_occluded(&self) -> usize
+    {
+        self.occluded.len()
+    }
+}
+
+impl Drop for OcclusionQueries
+{
+    fn drop(&mut self)
+    {
+        let queries: Vec<u32> = self.frames.iter().flatten().map(|(_, query)| *query).chain(self.free_queries.iter().copied()).collect();
+        unsafe
+        {
+            gl::DeleteQueries(queries.len() as i32, queries.as_ptr());
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteBuffers(1, &self.ebo);
+        }
+    }
+}
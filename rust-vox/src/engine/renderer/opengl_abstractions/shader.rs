@@ -1,5 +1,5 @@
 use std::{fs, io::Error, ffi::{CString}, collections::HashMap};
-use glam::{Vec4, Mat4, Vec3};
+use glam::{Vec4, Mat4, Vec3, IVec2, IVec3};
 
 pub struct Shader
 {
@@ -10,6 +10,7 @@ pub struct Shader
     pub vertex_src : String,
     pub fragment_src : String,
     pub geometry_src: Option<String>,
+    pub compute_src: Option<String>, // compute programs have no other stage
 
     // uniform locations
     locations: HashMap<String,i32>,
@@ -81,7 +82,27 @@ impl Shader
             Shader::link_program(program_id).expect("Error linking Program: ");
         }
         //TODO: error propagation is not done correctly
-        Ok(Self{program_id, fragment_src, vertex_src, geometry_src, locations: HashMap::new(), strings: HashMap::new()})
+        Ok(Self{program_id, fragment_src, vertex_src, geometry_src, compute_src: None, locations: HashMap::new(), strings: HashMap::new()})
+    }
+
+    /// Compile + Link a compute shader
+    pub fn new_from_cs(compute_filepath: &str) -> Result<Self,Error>
+    {
+        let compute_src = fs::read_to_string(compute_filepath)?;
+        let program_id: u32;
+
+        unsafe
+        {
+            let compute_shader = gl::CreateShader(gl::COMPUTE_SHADER);
+            gl::ShaderSource(compute_shader, 1, &(compute_src.as_bytes().as_ptr().cast()), &(compute_src.len().try_into().unwrap()));
+            Shader::compile_shader(compute_shader).expect("Error compiling Compute Shader: ");
+
+            program_id = gl::CreateProgram();
+            gl::AttachShader(program_id, compute_shader);
+            Shader::link_program(program_id).expect("Error linking Program: ");
+        }
+
+        Ok(Self{program_id, fragment_src: String::new(), vertex_src: String::new(), geometry_src: None, compute_src: Some(compute_src), locations: HashMap::new(), strings: HashMap::new()})
     }
 
     /// Cleaner Wrapper Around OpenGL's CompileShader(gluint) function
@@ -181,6 +202,42 @@ impl Shader
         }
     }
 
+    pub fn set_uniform2i(&mut self , name: &str , value: IVec2) -> Result<bool,String>
+    {
+        let location = self.get_uniform_location(name);
+
+        if location == -1
+        {
+            Err(String::from("an error occured"))
+        }
+        else
+        {
+            unsafe
+            {
+                gl::Uniform2i( location , value.x , value.y );
+            }
+            Ok(true)
+        }
+    }
+
+    pub fn set_uniform3i(&mut self , name: &str , value: IVec3) -> Result<bool,String>
+    {
+        let location = self.get_uniform_location(name);
+
+        if location == -1
+        {
+            Err(String::from("an error occured"))
+        }
+        else
+        {
+            unsafe
+            {
+                gl::Uniform3i( location , value.x , value.y , value.z );
+            }
+            Ok(true)
+        }
+    }
+
     pub fn _set_uniform1iv(&mut self , name: &str , value: i32) -> Result<bool,String>
     {
         let location = self.get_uniform_location(name);
@@ -199,6 +256,42 @@ impl Shader
         }
     }
 
+    pub fn set_uniform1ui(&mut self , name: &str , value: u32) -> Result<bool,String>
+    {
+        let location = self.get_uniform_location(name);
+
+        if location == -1
+        {
+            Err(String::from("an error occured"))
+        }
+        else
+        {
+            unsafe
+            {
+                gl::Uniform1ui( location , value );
+            }
+            Ok(true)
+        }
+    }
+
+    pub fn set_uniform_4fv(&mut self, name: &str, value: &[Vec4]) -> Result<bool,String>
+    {
+        let location = self.get_uniform_location(name);
+
+        if location == -1
+        {
+            Err(String::from("an error occured"))
+        }
+        else
+        {
+            unsafe
+            {
+                gl::Uniform4fv( location , value.len() as i32, value.as_ptr().cast() );
+            }
+            Ok(true)
+        }
+    }
+
     pub fn set_uniform_1f(&mut self, name: &str, value: f32) -> Result<bool,String>
     {
         let location = self.get_uniform_location(name);
@@ -127,7 +127,7 @@ impl VertexLayout
         self.push_element(element);
     }
 
-    pub fn _push_unsigned(&mut self, count: usize)
+    pub fn push_u32(&mut self, count: usize)
     {
         let element = VertexLayoutElement { element_type: gl::UNSIGNED_INT, count , normalized: gl::FALSE , size_bytes: size_of::<u32>() * count, integral: true};
         self.push_element(element);
@@ -253,7 +253,8 @@ pub mod sky_renderer
     use std::{mem};
 
     use glam::{Vec3, Vec2, Mat4};
-    use crate::engine::{geometry::{opengl_vertex::OpenglVertex, mesh::Mesh}, renderer::{opengl_abstractions::{vertex_array::{VertexLayout, VertexArray}, shader::Shader}, Renderer, allocators::{vertex_pool_allocator::Daic, default_allocator::DefaultAllocator}}};
+    use crate::engine::{geometry::{opengl_vertex::OpenglVertex, mesh::Mesh}, renderer::{opengl_abstractions::{vertex_array::{VertexLayout, VertexArray}, shader::Shader}, Renderer, allocators::{vertex_pool_allocator::Daic, default_allocator::DefaultAllocator, MeshAllocator}}};
+    use crate::assets::asset_path;
     use super::sky_state::Sky;
 
     struct SkyBoxVertex
@@ -336,7 +337,7 @@ pub mod sky_renderer
             let mut sky_box_allocator = DefaultAllocator::new();
 
             // Initialise everything needed to render the sky + objects
-            let celestial_shader = Shader::new_from_vs_fs("rust-vox/shaders/celestial.vert", "rust-vox/shaders/celestial.frag").expect("Shader Error");
+            let celestial_shader = Shader::new_from_vs_fs(&asset_path("shaders/celestial.vert"), &asset_path("shaders/celestial.frag")).expect("Shader Error");
             // create sky plane
             let mut sky_quad = Mesh::default();
             //TODO: refactor needed, we should be able to customize the Attributes for according to each Shader
@@ -353,7 +354,7 @@ pub mod sky_renderer
     
             let mut cloud_texture = 0;        
             // load texture atlas
-            let img = image::open("rust-vox/textures/clouds.png").unwrap().flipv();
+            let img = image::open(asset_path("textures/clouds.png")).unwrap().flipv();
             let width = img.width();
             let height = img.height();
             let data = img.as_bytes();
@@ -407,8 +408,8 @@ pub mod sky_renderer
     
             sky_box_allocator.alloc(&mut sky_box);
     
-            let skybox_shader = Shader::new_from_vs_gs_fs("rust-vox/shaders/skybox.vert",
-            "rust-vox/shaders/skybox.geom", "rust-vox/shaders/skybox.frag").expect("Shader Error");
+            let skybox_shader = Shader::new_from_vs_gs_fs(&asset_path("shaders/skybox.vert"),
+            &asset_path("shaders/skybox.geom"), &asset_path("shaders/skybox.frag")).expect("Shader Error");
     
             // generate the sun
             // the sun is just a textured quad
@@ -432,7 +433,7 @@ pub mod sky_renderer
     
             let mut sun_texture = 0;        
             // load texture atlas
-            let img = image::open("rust-vox/textures/sun.png").unwrap().flipv();
+            let img = image::open(asset_path("textures/sun.png")).unwrap().flipv();
             let width = img.width();
             let height = img.height();
             let data = img.as_bytes();
@@ -467,7 +468,7 @@ pub mod sky_renderer
     
             let mut moon_texture = 0;        
             // load texture atlas
-            let img = image::open("rust-vox/textures/moon.png").unwrap().flipv();
+            let img = image::open(asset_path("textures/moon.png")).unwrap().flipv();
             let width = img.width();
             let height = img.height();
             let data = img.as_bytes();
@@ -1,13 +1,52 @@
 use noise::{Perlin, NoiseFn};
 
-use crate::engine::geometry::voxel::{Voxel, VoxelType};
+use crate::engine::{chunk::CHUNK_SIZE_Y, geometry::voxel::{Voxel, VoxelType}};
+
+pub const DEFAULT_SEED: u32 = 2345345;
+
+/// Names accepted by create_generator()
+pub const GENERATOR_NAMES: [&str; 2] = ["perlin", "flat"];
+
+/// Create the terrain generator with the given name, None if there is no generator with this name
+pub fn create_generator(name: &str, seed: u32) -> Option<Box<dyn TerrainGenerator>>
+{
+    match name
+    {
+        "perlin" => Some(Box::new(PerlinGenerator::new(seed))),
+        "flat" => Some(Box::new(FlatGenerator::default())),
+        _ => None,
+    }
+}
 
 // unsafe impl Sync for TerrainGenerator{}
-pub trait TerrainGenerator : Sync
+pub trait TerrainGenerator : Sync + Send
 {
     /// Determine the type of block that will reside at the specified x,y,z in the world \
     /// The x,y,z coordinates must be in world coordinates
     fn generate( &self, voxel: &mut Voxel , x:i32, y:i32, z:i32);
+
+    /// Height of the top of the highest non air block at x,z and its type, used for the distant horizon \
+    /// Generators with a cheaper way to get it should override the default, which generates the whole column
+    fn get_surface(&self, x: i32, z: i32) -> (i32, VoxelType)
+    {
+        scan_surface(self, x, z)
+    }
+}
+
+/// Surface of the column at x,z found by generating its voxels from the top
+pub fn scan_surface<T: TerrainGenerator + ?Sized>(generator: &T, x: i32, z: i32) -> (i32, VoxelType)
+{
+    let mut voxel = Voxel::new(VoxelType::Air);
+    for y in (0..CHUNK_SIZE_Y as i32).rev()
+    {
+        generator.generate(&mut voxel, x, y, z);
+        if voxel.voxel_type != VoxelType::Air
+        {
+            return (y + 1, voxel.voxel_type);
+        }
+    }
+
+    (0, VoxelType::Air)
 }
 
 pub struct PerlinGenerator
@@ -16,20 +55,18 @@ pub struct PerlinGenerator
     layer1: Perlin,
 }
 
-impl Default for PerlinGenerator
+impl PerlinGenerator
 {
-    fn default() -> Self
+    pub fn new(seed: u32) -> Self
     {
         //TODO: use PlaneMapBuilder instead
-        let layer0 = Perlin::new(2345345);
-        let layer1 = Perlin::new(2345345);
+        let layer0 = Perlin::new(seed);
+        let layer1 = Perlin::new(seed);
         Self{layer0, layer1}
     }
-}
 
-impl TerrainGenerator for PerlinGenerator
-{
-    fn generate( &self, voxel: &mut Voxel,  x:i32, y:i32, z:i32)
+    // the blocks under this height are filled
+    fn get_max_height(&self, x: i32, z: i32) -> u32
     {
         const MIN_HEIGHT: u32 = 10; // 10 blocks
 
@@ -37,7 +74,23 @@ impl TerrainGenerator for PerlinGenerator
         let weight1 = self.layer1.get([x as f64 / 10.0, z as f64 / 10.0]);
 
         let weight = 0.7;
-        let max_height = (((weigth0 * weight + weight1 * (1.0 - weight)) + 1.0) * 15.0) as u32 + MIN_HEIGHT ;
+        (((weigth0 * weight + weight1 * (1.0 - weight)) + 1.0) * 15.0) as u32 + MIN_HEIGHT
+    }
+}
+
+impl Default for PerlinGenerator
+{
+    fn default() -> Self
+    {
+        Self::new(DEFAULT_SEED)
+    }
+}
+
+impl TerrainGenerator for PerlinGenerator
+{
+    fn generate( &self, voxel: &mut Voxel,  x:i32, y:i32, z:i32)
+    {
+        let max_height = self.get_max_height(x, z);
 
         let y = y as u32 ;
 
@@ -77,4 +130,52 @@ impl TerrainGenerator for PerlinGenerator
         // }
 
     }
-}
\ No newline at end of file
+
+    fn get_surface(&self, x: i32, z: i32) -> (i32, VoxelType)
+    {
+        // the water fills the holes up to the 20th block
+        match self.get_max_height(x, z)
+        {
+            0..=19 => (20, VoxelType::Water),
+            max_height => (max_height as i32, VoxelType::Dirt),
+        }
+    }
+}
+
+/// Flat ground at a fixed height, useful for benchmarks and debugging
+pub struct FlatGenerator
+{
+    height: i32,
+}
+
+impl Default for FlatGenerator
+{
+    fn default() -> Self
+    {
+        Self{height: 20}
+    }
+}
+
+impl TerrainGenerator for FlatGenerator
+{
+    fn generate( &self, voxel: &mut Voxel, _x:i32, y:i32, _z:i32)
+    {
+        if y >= self.height
+        {
+            voxel.set_type(VoxelType::Air);
+        }
+        else if y >= self.height - 3
+        {
+            voxel.set_type(VoxelType::Dirt);
+        }
+        else
+        {
+            voxel.set_type(VoxelType::Sand);
+        }
+    }
+
+    fn get_surface(&self, _x: i32, _z: i32) -> (i32, VoxelType)
+    {
+        (self.height, VoxelType::Dirt)
+    }
+}
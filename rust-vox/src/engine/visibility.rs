@@ -0,0 +1,216 @@
+// Cave culling, the chunks hidden behind the terrain are not drawn
+// The chunks are split in cubic sections, when a chunk is meshed we record which faces of each section can see each other
+// through the non opaque voxels. At render time a breadth first search starts from the section of the camera and only
+// crosses the sections from a face to a face connected to it, never going back towards the camera
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use glam::{IVec2, IVec3, Vec3Swizzles};
+
+use super::chunk::{Chunk, CHUNK_SIZE_X, CHUNK_SIZE_Y, CHUNK_SIZE_Z};
+
+pub const SECTION_SIZE_Y: usize = CHUNK_SIZE_X; // the sections are cubes
+pub const NUM_SECTIONS: usize = CHUNK_SIZE_Y.div_ceil(SECTION_SIZE_Y);
+
+/// Face of a section, the opposite faces are next to each other
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Face
+{
+    NegX,
+    PosX,
+    NegY,
+    PosY,
+    NegZ,
+    PosZ,
+}
+
+impl Face
+{
+    pub const ALL: [Face; 6] = [Face::NegX, Face::PosX, Face::NegY, Face::PosY, Face::NegZ, Face::PosZ];
+
+    /// Direction of the face, in sections
+    pub fn get_offset(self) -> IVec3
+    {
+        match self
+        {
+            Face::NegX => IVec3::NEG_X,
+            Face::PosX => IVec3::X,
+            Face::NegY => IVec3::NEG_Y,
+            Face::PosY => IVec3::Y,
+            Face::NegZ => IVec3::NEG_Z,
+            Face::PosZ => IVec3::Z,
+        }
+    }
+
+    pub fn opposite(self) -> Self
+    {
+        Self::ALL[self as usize ^ 1]
+    }
+}
+
+/// Which faces of a section can see each other, one bit per pair of faces
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct FaceConnections(u64);
+
+impl FaceConnections
+{
+    /// Every face sees every other one, ex: a section of air
+    pub const ALL: Self = Self((1 << 36) - 1);
+
+    pub fn connect(&mut self, a: Face, b: Face)
+    {
+        self.0 |= 1 << (a as usize * 6 + b as usize);
+        self.0 |= 1 << (b as usize * 6 + a as usize);
+    }
+
+    pub fn is_connected(&self, a: Face, b: Face) -> bool
+    {
+        self.0 & (1 << (a as usize * 6 + b as usize)) != 0
+    }
+}
+
+/// Face connections of the sections of a chunk, from the bottom one
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ChunkVisibility
+{
+    sections: [FaceConnections; NUM_SECTIONS],
+}
+
+impl ChunkVisibility
+{
+    pub fn new(chunk: &Chunk) -> Self
+    {
+        let mut sections = [FaceConnections::default(); NUM_SECTIONS];
+        for (section, connections) in sections.iter_mut().enumerate()
+        {
+            *connections = Self::get_section_connections(chunk, section);
+        }
+
+        Self{sections}
+    }
+
+    /// Nothing is hidden by the chunk, used while its voxels are not known
+    pub fn open() -> Self
+    {
+        Self{sections: [FaceConnections::ALL; NUM_SECTIONS]}
+    }
+
+    pub fn get_section(&self, section: usize) -> FaceConnections
+    {
+        self.sections[section]
+    }
+
+    // flood fill every group of non opaque voxels of the section, the faces touched by a group can see each other
+    fn get_section_connections(chunk: &Chunk, section: usize) -> FaceConnections
+    {
+        let y_start = section * SECTION_SIZE_Y;
+        let size = IVec3::new(CHUNK_SIZE_X as i32, (SECTION_SIZE_Y.min(CHUNK_SIZE_Y - y_start)) as i32, CHUNK_SIZE_Z as i32);
+
+        let index = |pos: IVec3| ((pos.x * size.y + pos.y) * size.z + pos.z) as usize;
+        let is_open = |pos: IVec3| chunk.voxels[pos.x as usize][y_start + pos.y as usize][pos.z as usize].is_transparent();
+
+        let mut visited = vec![false; (size.x * size.y * size.z) as usize];
+        let mut stack = Vec::new();
+        let mut connections = FaceConnections::default();
+
+        for x in 0..size.x
+        {
+            for y in 0..size.y
+            {
+                for z in 0..size.z
+                {
+                    let start = IVec3::new(x, y, z);
+                    if visited[index(start)] || !is_open(start)
+                    {
+                        continue;
+                    }
+
+                    visited[index(start)] = true;
+                    stack.push(start);
+
+                    let mut touched = [false; 6];
+                    while let Some(pos) = stack.pop()
+                    {
+                        for face in Face::ALL
+                        {
+                            let next = pos + face.get_offset();
+                            if next.cmplt(IVec3::ZERO).any() || next.cmpge(size).any()
+                            {
+                                touched[face as usize] = true;
+                            }
+                            else if !visited[index(next)] && is_open(next)
+                            {
+                                visited[index(next)] = true;
+                                stack.push(next);
+                            }
+                        }
+                    }
+
+                    for a in Face::ALL.into_iter().filter(|face| touched[*face as usize])
+                    {
+                        for b in Face::ALL.into_iter().filter(|face| touched[*face as usize])
+                        {
+                            connections.connect(a, b);
+                        }
+                    }
+                }
+            }
+        }
+
+        connections
+    }
+}
+
+/// Section of the chunk at chunk_pos holding the height y, clamped to the sections of the chunk
+pub fn get_section_pos(chunk_pos: IVec2, y: i32) -> IVec3
+{
+    let section = y.div_euclid(SECTION_SIZE_Y as i32).clamp(0, NUM_SECTIONS as i32 - 1);
+    IVec3::new(chunk_pos.x, section, chunk_pos.y)
+}
+
+/// Chunks with a section reached from the section of the camera, at most max_distance chunks away from it
+///
+/// get_visibility returns None for the chunks that cannot be crossed, ex: not loaded
+pub fn get_reachable_chunks<F>(camera_section: IVec3, max_distance: i32, get_visibility: F) -> HashSet<IVec2>
+    where F: Fn(IVec2) -> Option<ChunkVisibility>
+{
+    let mut chunks = HashMap::new(); // the visibility of each chunk is fetched once
+    let mut visited = HashSet::from([camera_section]);
+    let mut reachable = HashSet::new();
+
+    // each section keeps the face it was entered by and the directions taken to get to it
+    let mut queue = VecDeque::from([(camera_section, None::<Face>, 0u8)]);
+    while let Some((section, entered, directions)) = queue.pop_front()
+    {
+        let chunk_pos = section.xz();
+        reachable.insert(chunk_pos);
+
+        let connections = chunks.entry(chunk_pos).or_insert_with(|| get_visibility(chunk_pos))
+            .map_or(FaceConnections::ALL, |visibility| visibility.get_section(section.y as usize));
+
+        for face in Face::ALL
+        {
+            // never go back towards the camera, the camera sees through every face of its own section
+            if directions & (1 << face.opposite() as usize) != 0 || entered.is_some_and(|entered| !connections.is_connected(entered, face))
+            {
+                continue;
+            }
+
+            let next = section + face.get_offset();
+            if next.y < 0 || next.y >= NUM_SECTIONS as i32 || (next.xz() - camera_section.xz()).abs().max_element() > max_distance || visited.contains(&next)
+            {
+                continue;
+            }
+
+            if chunks.entry(next.xz()).or_insert_with(|| get_visibility(next.xz())).is_none()
+            {
+                continue;
+            }
+
+            visited.insert(next);
+            queue.push_back((next, Some(face.opposite()), directions | 1 << face as usize));
+        }
+    }
+
+    reachable
+}
@@ -1,29 +1,106 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, rc::Rc, fs, io, path::Path, thread, time::{Duration, Instant}};
 
-use crate::ui::DebugData;
+use serde::{Serialize, Deserialize};
 
-use super::{camera::Camera, chunk_manager::ChunkManager, ray_cast::cast_ray};
+use crate::{ui::DebugData, settings::Settings};
 
-pub struct World
+use super::{camera::Camera, chunk_manager::ChunkManager, ray_cast::cast_ray, terrain::{TerrainGenerator, DEFAULT_SEED}, geometry::voxel_vertex::VoxelVertex,
+    renderer::allocators::{MeshAllocator, vertex_pool_allocator::VertexPoolAllocator, headless_allocator::HeadlessAllocator}};
+
+const WORLD_INFO_FILE: &str = "world.toml";
+
+/// What is needed to generate a world again, stored in the world's directory
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct WorldInfo
+{
+    pub seed: u32,
+    pub generator: String, // one of terrain::GENERATOR_NAMES
+}
+
+impl Default for WorldInfo
+{
+    fn default() -> Self
+    {
+        Self{seed: DEFAULT_SEED, generator: "perlin".to_string()}
+    }
+}
+
+impl WorldInfo
+{
+    pub fn load(world_dir: &Path) -> io::Result<Self>
+    {
+        let content = fs::read_to_string(world_dir.join(WORLD_INFO_FILE))?;
+        toml::from_str(&content).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))
+    }
+
+    /// Write the info into the world's directory, the directory is created if needed
+    pub fn save(&self, world_dir: &Path) -> io::Result<()>
+    {
+        fs::create_dir_all(world_dir)?;
+        let content = toml::to_string_pretty(self).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+        fs::write(world_dir.join(WORLD_INFO_FILE), content)
+    }
+}
+
+pub struct World<A = VertexPoolAllocator<VoxelVertex>>
 {
     pub camera : Camera,
-    pub chunk_manager: ChunkManager,
+    pub chunk_manager: ChunkManager<A>,
 }
 
 impl World
 {
-    pub fn new(eye: Camera, debug_data: &Rc<RefCell<DebugData>>) -> Self
+    pub fn new(eye: Camera, settings: &Settings, generator: Box<dyn TerrainGenerator>, debug_data: &Rc<RefCell<DebugData>>) -> Self
     {
         // init the chunk manager
-        let chunk_manager = ChunkManager::new(2, debug_data);
+        let chunk_manager = ChunkManager::new(settings, generator, debug_data);
 
         Self{camera: eye,chunk_manager}
     }
+}
+
+impl World<HeadlessAllocator>
+{
+    /// A world that needs neither a window nor an OpenGL context, the meshes are built but never uploaded
+    pub fn new_headless(eye: Camera, settings: &Settings, generator: Box<dyn TerrainGenerator>) -> Self
+    {
+        let debug_data = Rc::new(RefCell::new(DebugData::default()));
+        let chunk_manager = ChunkManager::with_allocator(settings, generator, HeadlessAllocator::new(), &debug_data);
+
+        Self{camera: eye,chunk_manager}
+    }
+}
+
+impl<A> World<A>
+    where A: MeshAllocator<VoxelVertex>
+{
+    /// Update until every visible chunk is loaded, returns false if it took longer than timeout
+    ///
+    /// For use without a frame loop, ex: headless
+    pub fn update_until_loaded(&mut self, timeout: Duration) -> bool
+    {
+        let start = Instant::now();
+
+        self.update();
+        while !self.chunk_manager.is_fully_loaded()
+        {
+            if start.elapsed() > timeout
+            {
+                return false;
+            }
+
+            // let the workers make progress
+            thread::sleep(Duration::from_millis(1));
+            self.update();
+        }
+
+        true
+    }
 
     pub fn update(&mut self)
     {
         // update the chunks if needed
-        self.chunk_manager.update(self.camera.get_position());
+        self.chunk_manager.update(&self.camera);
     }
 
     pub fn place(&mut self)
@@ -42,6 +119,42 @@ impl World
         }
     }
 
+    /// Apply the settings that concern the world, the render distances and the camera's projection
+    pub fn apply_settings(&mut self, old: &Settings, new: &Settings)
+    {
+        if (old.no_update_distance, old.visible_distance, old.loaded_distance) != (new.no_update_distance, new.visible_distance, new.loaded_distance)
+        {
+            self.chunk_manager.set_render_distances(new.no_update_distance, new.visible_distance, new.loaded_distance);
+        }
+
+        if old.lod_distances != new.lod_distances
+        {
+            self.chunk_manager.set_lod_distances(new.lod_distances);
+        }
+
+        if old.mesher != new.mesher
+        {
+            self.chunk_manager.set_mesher(new.mesher);
+        }
+
+        if old.thread_count != new.thread_count
+        {
+            self.chunk_manager.set_thread_count(new.thread_count);
+        }
+
+        self.chunk_manager.set_target_frame_time(new.target_frame_time);
+
+        self.camera.fov_y = new.fov_y.to_radians();
+        self.camera.far_plane = new.far_plane;
+        self.camera.rebuild_frustum();
+    }
+
+    /// Generate the world again with another terrain generator
+    pub fn set_generator(&mut self, generator: Box<dyn TerrainGenerator>)
+    {
+        self.chunk_manager.set_generator(generator);
+    }
+
     pub fn rebuild(&mut self)
     {
         self.chunk_manager.rebuild_chunk_meshes();
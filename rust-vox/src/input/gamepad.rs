@@ -0,0 +1,199 @@
+// game controller support
+// sticks drive the camera directly, buttons and triggers are mapped into the same actions as the keyboard
+
+use std::collections::{HashMap, HashSet};
+
+use glam::Vec2;
+use sdl2::{GameControllerSubsystem, controller::{GameController, Axis}, event::Event};
+
+use super::Binding;
+
+const AXIS_MAX: f32 = i16::MAX as f32;
+
+pub struct GamepadConfig
+{
+    pub deadzone: f32, // fraction of the stick range that is ignored, filters out stick drift
+    pub response_exponent: f32, // 1 is linear, > 1 gives finer control near the center
+    pub trigger_threshold: f32, // fraction of the trigger range at which it counts as pressed
+    pub move_speed: f32, // world units per second at full stick deflection
+    pub look_speed: f32, // degrees per second at full stick deflection
+    pub invert_look_y: bool,
+}
+
+impl Default for GamepadConfig
+{
+    fn default() -> Self
+    {
+        Self{deadzone: 0.15, response_exponent: 2.0, trigger_threshold: 0.5, move_speed: 20.0, look_speed: 120.0, invert_look_y: false}
+    }
+}
+
+/// Applies a radial deadzone then the response curve to a stick position
+///
+/// Components of stick are expected in [-1,1], the returned vector has a length <= 1
+pub fn apply_stick_response(stick: Vec2, deadzone: f32, exponent: f32) -> Vec2
+{
+    let magnitude = stick.length();
+
+    if magnitude <= deadzone
+    {
+        return Vec2::ZERO;
+    }
+
+    // rescale so the output starts at 0 right at the edge of the deadzone instead of jumping
+    let scaled = ((magnitude - deadzone) / (1.0 - deadzone)).min(1.0);
+
+    stick / magnitude * scaled.powf(exponent)
+}
+
+/// State of every connected controller, driven entirely by SDL events
+#[derive(Default)]
+pub struct GamepadState
+{
+    pub config: GamepadConfig,
+    axes: HashMap<u32, [f32; 6]>, // normalized axis values per controller instance id, indexed by sdl2::controller::Axis
+    pressed_triggers: HashSet<(u32, i32)>, // (instance id, axis) of the triggers currently held down
+}
+
+impl GamepadState
+{
+    pub fn new(config: GamepadConfig) -> Self
+    {
+        Self{config, axes: HashMap::new(), pressed_triggers: HashSet::new()}
+    }
+
+    /// Update the state from the event
+    ///
+    /// Returns the trigger that was pressed in this event, if any
+    /// Controller buttons are reported by Binding::from_event() like keys
+    pub fn handle_event(&mut self, event: &Event) -> Option<Binding>
+    {
+        match event
+        {
+            Event::ControllerAxisMotion { which, axis, value, .. } =>
+            {
+                let value = *value as f32 / AXIS_MAX;
+                self.axes.entry(*which).or_insert([0.0; 6])[*axis as usize] = value;
+
+                if *axis != Axis::TriggerLeft && *axis != Axis::TriggerRight
+                {
+                    return None;
+                }
+
+                // triggers are analog, they are considered pressed when crossing the threshold upwards
+                let key = (*which, *axis as i32);
+                if value >= self.config.trigger_threshold
+                {
+                    if self.pressed_triggers.insert(key)
+                    {
+                        return Some(Binding::ControllerTrigger(*axis));
+                    }
+                }
+                else
+                {
+                    self.pressed_triggers.remove(&key);
+                }
+
+                None
+            },
+            Event::ControllerDeviceRemoved { which, .. } =>
+            {
+                self.remove_controller(*which);
+                None
+            },
+            _ => None,
+        }
+    }
+
+    pub fn remove_controller(&mut self, instance_id: u32)
+    {
+        self.axes.remove(&instance_id);
+        self.pressed_triggers.retain(|(id, _)| *id != instance_id);
+    }
+
+    /// Movement requested by the left sticks, x is strafe (+ right), y is forward (+ forward)
+    pub fn get_movement(&self) -> Vec2
+    {
+        // stick up reports a negative Y
+        let stick = self.get_stick(Axis::LeftX, Axis::LeftY);
+        Vec2::new(stick.x, -stick.y)
+    }
+
+    /// Look direction change requested by the right sticks, x is yaw, y is pitch in the same convention as mouse motion
+    pub fn get_look(&self) -> Vec2
+    {
+        let stick = self.get_stick(Axis::RightX, Axis::RightY);
+        if self.config.invert_look_y { Vec2::new(stick.x, -stick.y) } else { stick }
+    }
+
+    // combine the stick of every controller, so any of them can be used
+    fn get_stick(&self, x_axis: Axis, y_axis: Axis) -> Vec2
+    {
+        let combined: Vec2 = self.axes.values().map(|axes|
+        {
+            apply_stick_response(Vec2::new(axes[x_axis as usize], axes[y_axis as usize]), self.config.deadzone, self.config.response_exponent)
+        }).sum();
+
+        combined.clamp_length_max(1.0)
+    }
+}
+
+/// Owns the opened game controllers, handles them being plugged in and out
+pub struct Gamepads
+{
+    subsystem: GameControllerSubsystem,
+    controllers: HashMap<u32, GameController>, // instance id -> controller
+    pub state: GamepadState,
+}
+
+impl Gamepads
+{
+    pub fn new(subsystem: GameControllerSubsystem, config: GamepadConfig) -> Self
+    {
+        // controllers already connected are reported by SDL through ControllerDeviceAdded events as well
+        Self{subsystem, controllers: HashMap::new(), state: GamepadState::new(config)}
+    }
+
+    /// Update the controllers from the event
+    ///
+    /// Returns the trigger that was pressed in this event, if any
+    pub fn handle_event(&mut self, event: &Event) -> Option<Binding>
+    {
+        match event
+        {
+            Event::ControllerDeviceAdded { which, .. } =>
+            {
+                // which is the joystick index here, not the instance id
+                match self.subsystem.open(*which)
+                {
+                    Ok(controller) =>
+                    {
+                        println!("game controller connected: {}", controller.name());
+                        self.controllers.insert(controller.instance_id(), controller);
+                    },
+                    Err(err) => println!("error opening game controller {}: {}", which, err),
+                }
+                None
+            },
+            Event::ControllerDeviceRemoved { which, .. } =>
+            {
+                if let Some(controller) = self.controllers.remove(which)
+                {
+                    println!("game controller disconnected: {}", controller.name());
+                }
+                self.state.handle_event(event)
+            },
+            _ => self.state.handle_event(event),
+        }
+    }
+
+    pub fn get_num_connected(&self) -> usize
+    {
+        self.controllers.len()
+    }
+
+    pub fn get_controller_names(&self) -> Vec<String>
+    {
+        self.controllers.values().map(|controller| controller.name()).collect()
+    }
+}
@@ -0,0 +1,340 @@
+// maps physical inputs (keys, mouse buttons, controller buttons) to named actions
+// the bindings can be loaded from and saved to a TOML config file
+
+use std::{collections::{HashMap, BTreeMap}, fmt::{Display, Formatter}, fmt, fs, io, path::Path};
+
+use serde::{Serialize, Deserialize};
+use sdl2::{event::Event, keyboard::Keycode, mouse::MouseButton, controller::{Button, Axis}};
+
+pub mod gamepad;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Action
+{
+    MoveForward,
+    MoveBackward,
+    StrafeLeft,
+    StrafeRight,
+    PlaceBlock,
+    DestroyBlock,
+    ToggleMouse,
+    ToggleWireframe,
+    ToggleVsync,
+    ToggleFullscreen,
+    ToggleBorderless,
+    Quit,
+}
+
+impl Action
+{
+    /// Every action in the order they are shown to the user
+    pub const ALL: [Action; 12] = [Action::MoveForward, Action::MoveBackward, Action::StrafeLeft, Action::StrafeRight,
+        Action::PlaceBlock, Action::DestroyBlock, Action::ToggleMouse, Action::ToggleWireframe, Action::ToggleVsync,
+        Action::ToggleFullscreen, Action::ToggleBorderless, Action::Quit];
+
+    /// Name used as the key in the config file
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            Action::MoveForward => "MoveForward",
+            Action::MoveBackward => "MoveBackward",
+            Action::StrafeLeft => "StrafeLeft",
+            Action::StrafeRight => "StrafeRight",
+            Action::PlaceBlock => "PlaceBlock",
+            Action::DestroyBlock => "DestroyBlock",
+            Action::ToggleMouse => "ToggleMouse",
+            Action::ToggleWireframe => "ToggleWireframe",
+            Action::ToggleVsync => "ToggleVsync",
+            Action::ToggleFullscreen => "ToggleFullscreen",
+            Action::ToggleBorderless => "ToggleBorderless",
+            Action::Quit => "Quit",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action>
+    {
+        Action::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+impl Display for Action
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result
+    {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A physical input that can trigger an action
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Binding
+{
+    Key(Keycode),
+    Mouse(MouseButton),
+    ControllerButton(Button),
+    ControllerTrigger(Axis), // only TriggerLeft and TriggerRight, reported by GamepadState when crossing the press threshold
+}
+
+impl Binding
+{
+    /// Extract the binding that was pressed in this event, if any
+    pub fn from_event(event: &Event) -> Option<Binding>
+    {
+        match event
+        {
+            Event::KeyDown { keycode: Some(key), .. } => Some(Binding::Key(*key)),
+            Event::MouseButtonDown { mouse_btn, .. } if *mouse_btn != MouseButton::Unknown => Some(Binding::Mouse(*mouse_btn)),
+            Event::ControllerButtonDown { button, .. } => Some(Binding::ControllerButton(*button)),
+            _ => None,
+        }
+    }
+
+    /// Controller bindings and keyboard/mouse bindings of an action are kept side by side
+    pub fn is_controller(&self) -> bool
+    {
+        matches!(self, Binding::ControllerButton(_) | Binding::ControllerTrigger(_))
+    }
+
+    /// Parses the format written by to_config_string(), ex: "Key:W", "Mouse:Left" or "Button:a"
+    pub fn from_config_str(value: &str) -> Option<Binding>
+    {
+        let (kind, name) = value.split_once(':')?;
+
+        match kind
+        {
+            "Key" => Keycode::from_name(name).map(Binding::Key),
+            "Mouse" => Self::mouse_button_from_name(name).map(Binding::Mouse),
+            "Button" => Button::from_string(name).map(Binding::ControllerButton),
+            "Trigger" => Axis::from_string(name).filter(|axis| *axis == Axis::TriggerLeft || *axis == Axis::TriggerRight)
+                .map(Binding::ControllerTrigger),
+            _ => None,
+        }
+    }
+
+    pub fn to_config_string(self) -> String
+    {
+        match self
+        {
+            Binding::Key(key) => format!("Key:{}", key.name()),
+            Binding::Mouse(button) => format!("Mouse:{}", Self::mouse_button_name(button)),
+            Binding::ControllerButton(button) => format!("Button:{}", button.string()),
+            Binding::ControllerTrigger(axis) => format!("Trigger:{}", axis.string()),
+        }
+    }
+
+    fn mouse_button_name(button: MouseButton) -> &'static str
+    {
+        match button
+        {
+            MouseButton::Left => "Left",
+            MouseButton::Middle => "Middle",
+            MouseButton::Right => "Right",
+            MouseButton::X1 => "X1",
+            MouseButton::X2 => "X2",
+            MouseButton::Unknown => "Unknown",
+        }
+    }
+
+    fn mouse_button_from_name(name: &str) -> Option<MouseButton>
+    {
+        match name
+        {
+            "Left" => Some(MouseButton::Left),
+            "Middle" => Some(MouseButton::Middle),
+            "Right" => Some(MouseButton::Right),
+            "X1" => Some(MouseButton::X1),
+            "X2" => Some(MouseButton::X2),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Binding
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result
+    {
+        match self
+        {
+            Binding::Key(key) => write!(f, "{}", key.name()),
+            Binding::Mouse(button) => write!(f, "Mouse {}", Self::mouse_button_name(*button)),
+            Binding::ControllerButton(button) => write!(f, "Pad {}", button.string()),
+            Binding::ControllerTrigger(axis) => write!(f, "Pad {}", axis.string()),
+        }
+    }
+}
+
+// on-disk representation of the bindings
+#[derive(Serialize, Deserialize, Default)]
+struct BindingsFile
+{
+    bindings: BTreeMap<String, Vec<String>>,
+}
+
+pub struct InputMap
+{
+    bindings: HashMap<Action, Vec<Binding>>,
+    pending_rebind: Option<Action>, // the action waiting for the next pressed input to be bound to it
+}
+
+impl Default for InputMap
+{
+    fn default() -> Self
+    {
+        let defaults = [
+            (Action::MoveForward, Binding::Key(Keycode::W)),
+            (Action::MoveForward, Binding::ControllerButton(Button::DPadUp)),
+            (Action::MoveBackward, Binding::Key(Keycode::S)),
+            (Action::MoveBackward, Binding::ControllerButton(Button::DPadDown)),
+            (Action::StrafeLeft, Binding::Key(Keycode::A)),
+            (Action::StrafeLeft, Binding::ControllerButton(Button::DPadLeft)),
+            (Action::StrafeRight, Binding::Key(Keycode::D)),
+            (Action::StrafeRight, Binding::ControllerButton(Button::DPadRight)),
+            (Action::PlaceBlock, Binding::Mouse(MouseButton::Left)),
+            (Action::PlaceBlock, Binding::ControllerTrigger(Axis::TriggerRight)),
+            (Action::DestroyBlock, Binding::Mouse(MouseButton::Right)),
+            (Action::DestroyBlock, Binding::ControllerTrigger(Axis::TriggerLeft)),
+            (Action::ToggleMouse, Binding::Key(Keycode::Num1)),
+            (Action::ToggleWireframe, Binding::Key(Keycode::Num2)),
+            (Action::ToggleVsync, Binding::Key(Keycode::Num3)),
+            (Action::ToggleFullscreen, Binding::Key(Keycode::F11)),
+            (Action::ToggleBorderless, Binding::Key(Keycode::F10)),
+            (Action::Quit, Binding::Key(Keycode::Escape)),
+        ];
+
+        let mut bindings: HashMap<Action, Vec<Binding>> = HashMap::new();
+        for (action, binding) in defaults
+        {
+            bindings.entry(action).or_default().push(binding);
+        }
+
+        Self{bindings, pending_rebind: None}
+    }
+}
+
+impl InputMap
+{
+    /// Load the bindings from the config file at path
+    ///
+    /// Actions missing from the file keep their default bindings
+    pub fn load(path: &Path) -> io::Result<Self>
+    {
+        let content = fs::read_to_string(path)?;
+        let file: BindingsFile = toml::from_str(&content).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+
+        let mut input_map = Self::default();
+
+        for (name, values) in file.bindings.iter()
+        {
+            match Action::from_name(name)
+            {
+                Some(action) =>
+                {
+                    let bindings = values.iter().filter_map(|value| Binding::from_config_str(value)).collect();
+                    input_map.bindings.insert(action, bindings);
+                },
+                None => println!("unknown action \"{}\" in {}, ignored", name, path.display()),
+            }
+        }
+
+        Ok(input_map)
+    }
+
+    /// Same as load(), falls back to the default bindings if the file could not be read
+    pub fn load_or_default(path: &Path) -> Self
+    {
+        match Self::load(path)
+        {
+            Ok(input_map) => input_map,
+            Err(err) =>
+            {
+                if err.kind() != io::ErrorKind::NotFound
+                {
+                    println!("error loading bindings from {}: {}, using defaults", path.display(), err);
+                }
+                Self::default()
+            }
+        }
+    }
+
+    /// Write the bindings to the config file at path
+    pub fn save(&self, path: &Path) -> io::Result<()>
+    {
+        let mut file = BindingsFile::default();
+
+        for action in Action::ALL
+        {
+            let values = self.get_bindings(action).iter().map(|binding| binding.to_config_string()).collect();
+            file.bindings.insert(action.name().to_string(), values);
+        }
+
+        let content = toml::to_string_pretty(&file).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+        fs::write(path, content)
+    }
+
+    pub fn get_bindings(&self, action: Action) -> &[Binding]
+    {
+        self.bindings.get(&action).map(|bindings| bindings.as_slice()).unwrap_or(&[])
+    }
+
+    /// Get the action bound to the input
+    pub fn get_action(&self, binding: Binding) -> Option<Action>
+    {
+        Action::ALL.into_iter().find(|action| self.get_bindings(*action).contains(&binding))
+    }
+
+    /// Get the action triggered by the event, if any
+    pub fn map_event(&self, event: &Event) -> Option<Action>
+    {
+        Binding::from_event(event).and_then(|binding| self.get_action(binding))
+    }
+
+    /// Bind the input to the action, replacing its previous bindings of the same kind
+    /// (a new key replaces the keyboard/mouse binding but keeps the controller binding)
+    ///
+    /// The input is removed from any other action it was bound to
+    pub fn rebind(&mut self, action: Action, binding: Binding)
+    {
+        for bindings in self.bindings.values_mut()
+        {
+            bindings.retain(|bound| *bound != binding);
+        }
+
+        let bindings = self.bindings.entry(action).or_default();
+        bindings.retain(|bound| bound.is_controller() != binding.is_controller());
+        bindings.push(binding);
+    }
+
+    /// The next pressed input will be bound to the action
+    pub fn start_rebind(&mut self, action: Action)
+    {
+        self.pending_rebind = Some(action);
+    }
+
+    pub fn cancel_rebind(&mut self)
+    {
+        self.pending_rebind = None;
+    }
+
+    pub fn get_pending_rebind(&self) -> Option<Action>
+    {
+        self.pending_rebind
+    }
+
+    /// If a rebind is pending, bind the pressed input to it, Escape cancels the rebind instead
+    ///
+    /// Returns true if the input was consumed by the rebind
+    pub fn capture_rebind(&mut self, binding: Binding) -> bool
+    {
+        if let Some(action) = self.pending_rebind.take()
+        {
+            if binding != Binding::Key(Keycode::Escape)
+            {
+                self.rebind(action, binding);
+            }
+            return true;
+        }
+
+        false
+    }
+}
@@ -0,0 +1,246 @@
+// Priority job system used to generate and mesh the chunks
+// The queue is bounded and ordered by priority, the priorities can be recomputed while the jobs wait
+// Every job can be cancelled through its handle, and dropping the system abandons the pending jobs
+
+use std::{thread::{JoinHandle, self}, sync::{Arc, Mutex, Condvar, mpsc::Sender, atomic::{AtomicBool, Ordering}}, collections::BinaryHeap, cmp};
+
+use glam::IVec2;
+
+type Task = Box<dyn FnOnce() + Send + 'static>;
+
+/// Allows to cancel a job, a cancelled job is dropped if it has not started yet and its result is discarded
+#[derive(Clone, Debug)]
+pub struct JobHandle
+{
+    cancelled: Arc<AtomicBool>,
+}
+
+impl JobHandle
+{
+    fn new() -> Self
+    {
+        Self{cancelled: Arc::new(AtomicBool::new(false))}
+    }
+
+    pub fn cancel(&self)
+    {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool
+    {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+struct Job
+{
+    priority: f32, // lower runs first
+    order: u64, // jobs with the same priority run in submission order
+    chunk_pos: IVec2, // what the job works on, used to recompute the priority
+    handle: JobHandle,
+    task: Task,
+}
+
+impl PartialEq for Job
+{
+    fn eq(&self, other: &Self) -> bool
+    {
+        self.cmp(other) == cmp::Ordering::Equal
+    }
+}
+
+impl Eq for Job {}
+
+impl PartialOrd for Job
+{
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering>
+    {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Job
+{
+    // BinaryHeap is a max heap, the job with the lowest priority value must compare as the greatest
+    fn cmp(&self, other: &Self) -> cmp::Ordering
+    {
+        other.priority.total_cmp(&self.priority).then_with(|| other.order.cmp(&self.order))
+    }
+}
+
+struct State
+{
+    queue: BinaryHeap<Job>,
+    capacity: usize,
+    next_order: u64,
+    generation: u64, // workers of an older generation exit, incremented when the thread count changes
+    shutdown: bool,
+}
+
+struct Shared
+{
+    state: Mutex<State>,
+    job_available: Condvar,
+}
+
+pub struct JobSystem
+{
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl JobSystem
+{
+    /// # Panics
+    /// if thread_count or capacity is zero
+    pub fn new(thread_count: usize, capacity: usize) -> Self
+    {
+        assert!(thread_count > 0 && capacity > 0);
+
+        let state = State{queue: BinaryHeap::with_capacity(capacity), capacity, next_order: 0, generation: 0, shutdown: false};
+        let shared = Arc::new(Shared{state: Mutex::new(state), job_available: Condvar::new()});
+
+        let mut system = Self{shared, workers: Vec::new()};
+        system.spawn_workers(thread_count, 0);
+        system
+    }
+
+    /// Queue a job, the value it returns is sent to the completion channel unless the job was cancelled
+    ///
+    /// Returns None if the queue is full, the job should be submitted again later
+    pub fn submit<T, F>(&self, priority: f32, chunk_pos: IVec2, completion: &Sender<T>, job: F) -> Option<JobHandle>
+        where T: Send + 'static, F: FnOnce() -> Option<T> + Send + 'static
+    {
+        let mut state = self.shared.state.lock().unwrap();
+
+        if state.queue.len() >= state.capacity
+        {
+            // make room by dropping the cancelled jobs
+            state.queue.retain(|job| !job.handle.is_cancelled());
+            if state.queue.len() >= state.capacity
+            {
+                return None;
+            }
+        }
+
+        let handle = JobHandle::new();
+        let job_handle = handle.clone();
+        let completion = completion.clone();
+
+        let task: Task = Box::new(move ||
+        {
+            if let Some(result) = job()
+            {
+                if !job_handle.is_cancelled()
+                {
+                    // the receiver is gone if the results are not wanted anymore
+                    let _ = completion.send(result);
+                }
+            }
+        });
+
+        let order = state.next_order;
+        state.next_order += 1;
+        state.queue.push(Job{priority, order, chunk_pos, handle: handle.clone(), task});
+        drop(state);
+
+        self.shared.job_available.notify_one();
+        Some(handle)
+    }
+
+    /// Recompute the priority of every waiting job from the position it works on, the cancelled jobs are dropped
+    pub fn reprioritize<F>(&self, priority: F)
+        where F: Fn(IVec2) -> f32
+    {
+        let mut state = self.shared.state.lock().unwrap();
+
+        let mut jobs = std::mem::take(&mut state.queue).into_vec();
+        jobs.retain(|job| !job.handle.is_cancelled());
+        for job in jobs.iter_mut()
+        {
+            job.priority = priority(job.chunk_pos);
+        }
+
+        state.queue = BinaryHeap::from(jobs);
+    }
+
+    /// Number of jobs waiting to be run, cancelled jobs included until they are dropped
+    pub fn get_num_queued(&self) -> usize
+    {
+        self.shared.state.lock().unwrap().queue.len()
+    }
+
+    /// Replace the workers, the current ones finish the job they are running and exit
+    pub fn set_thread_count(&mut self, thread_count: usize)
+    {
+        assert!(thread_count > 0);
+
+        let generation =
+        {
+            let mut state = self.shared.state.lock().unwrap();
+            state.generation += 1;
+            state.generation
+        };
+        self.shared.job_available.notify_all();
+
+        // the old workers are detached, waiting on them would block the caller
+        self.workers.clear();
+        self.spawn_workers(thread_count, generation);
+    }
+
+    fn spawn_workers(&mut self, thread_count: usize, generation: u64)
+    {
+        for _ in 0..thread_count
+        {
+            let shared = Arc::clone(&self.shared);
+            self.workers.push(thread::spawn(move || Self::work(shared, generation)));
+        }
+    }
+
+    fn work(shared: Arc<Shared>, generation: u64)
+    {
+        loop
+        {
+            let job =
+            {
+                let mut state = shared.state.lock().unwrap();
+                loop
+                {
+                    if state.shutdown || state.generation != generation
+                    {
+                        return;
+                    }
+
+                    match state.queue.pop()
+                    {
+                        Some(job) if job.handle.is_cancelled() => continue,
+                        Some(job) => break job,
+                        None => state = shared.job_available.wait(state).unwrap(),
+                    }
+                }
+            }; // the lock is released before running the job
+
+            (job.task)();
+        }
+    }
+}
+
+impl Drop for JobSystem
+{
+    /// Abandons the waiting jobs, only the running ones are waited for
+    fn drop(&mut self)
+    {
+        {
+            let mut state = self.shared.state.lock().unwrap();
+            state.shutdown = true;
+            state.queue.clear();
+        }
+        self.shared.job_available.notify_all();
+
+        for worker in self.workers.drain(..)
+        {
+            let _ = worker.join();
+        }
+    }
+}
@@ -2,8 +2,12 @@
 extern crate lazy_static;
 
 pub mod engine;
-mod threadpool;
+pub mod assets;
+pub mod benchmark;
+pub mod jobs;
 pub mod generational_vec;
+pub mod input;
+pub mod settings;
 mod ui;
 
 pub use ui::UiRenderer;
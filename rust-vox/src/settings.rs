@@ -0,0 +1,179 @@
+// runtime settings of the engine, loaded from a TOML file at startup and written back on exit
+// every field has a default, so a partial or outdated file still loads
+
+use std::{fs, io, path::Path};
+
+use serde::{Serialize, Deserialize};
+
+use crate::engine::{chunk_manager::MAX_LOADED_DISTANCE, geometry::meshing::chunk_mesher::MesherType};
+
+pub const SHADOW_MAP_RESOLUTIONS: [i32; 4] = [512, 1024, 2048, 4096];
+pub const MAX_HORIZON_DISTANCE: i32 = 512; // in chunks
+
+// bounds of the values, shared by sanitize() and the settings panel
+pub const MAX_VISIBLE_DISTANCE: i32 = MAX_LOADED_DISTANCE - 2; // the loaded zone needs one extra chunk on each side of the visible zone
+pub const FOV_RANGE: (f32, f32) = (20.0, 120.0);
+pub const FAR_PLANE_RANGE: (f32, f32) = (50.0, 5000.0);
+pub const MOUSE_SENSITIVITY_RANGE: (f32, f32) = (0.001, 1.0);
+pub const THREAD_COUNT_RANGE: (usize, usize) = (1, 64);
+pub const TARGET_FRAME_TIME_RANGE: (f32, f32) = (4.0, 100.0); // in ms
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DisplayMode
+{
+    Windowed,
+    Borderless, // fullscreen window at the desktop resolution
+    Exclusive, // fullscreen with a display mode change
+}
+
+impl DisplayMode
+{
+    pub const ALL: [DisplayMode; 3] = [DisplayMode::Windowed, DisplayMode::Borderless, DisplayMode::Exclusive];
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            DisplayMode::Windowed => "Windowed",
+            DisplayMode::Borderless => "Borderless Fullscreen",
+            DisplayMode::Exclusive => "Exclusive Fullscreen",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct Settings
+{
+    // render distances, in chunks
+    pub no_update_distance: i32, // the player can move this far from the last anchor before the chunks are reloaded
+    pub visible_distance: i32,
+    pub loaded_distance: i32, // chunks not visible but kept in memory, must engulf the visible zone
+    pub lod_distances: [i32; 3], // past each distance the chunks are meshed at half the previous resolution
+    pub horizon_distance: i32, // the terrain past the loaded chunks is drawn as a heightmap up to this distance, 0 disables it
+    pub mesher: MesherType, // builds the full resolution meshes of the chunks
+
+    pub window_width: u32, // size of the window when windowed, in screen coordinates
+    pub window_height: u32,
+    pub display_mode: DisplayMode,
+    pub fov_y: f32, // in degrees
+    pub far_plane: f32,
+    pub mouse_sensitivity: f32,
+    pub vsync: bool,
+    pub shadow_map_resolution: i32,
+    pub thread_count: usize, // workers generating and meshing the chunks
+    pub fog_density: f32, // 0 disables the fog
+    pub target_frame_time: f32, // in ms, the chunk uploads are throttled to stay under it
+    pub gpu_culling: bool, // frustum culling of the chunks in a compute shader, else on the CPU
+    pub cave_culling: bool, // the chunks hidden behind the terrain are not drawn
+    pub occlusion_queries: bool, // the chunks hidden in the depth buffer of the previous frames are not drawn
+}
+
+impl Default for Settings
+{
+    fn default() -> Self
+    {
+        Self{no_update_distance: 2, visible_distance: 10, loaded_distance: 18, lod_distances: [4, 8, 16], horizon_distance: 128, mesher: MesherType::default(),
+            window_width: 1700, window_height: 900, display_mode: DisplayMode::Windowed, fov_y: 45.0, far_plane: 500.0,
+            mouse_sensitivity: 0.05, vsync: true, shadow_map_resolution: 2048, thread_count: 2, fog_density: 0.0,
+            target_frame_time: 20.0, gpu_culling: true, cave_culling: true, occlusion_queries: true}
+    }
+}
+
+impl Settings
+{
+    /// Load the settings from the TOML file at path
+    pub fn load(path: &Path) -> io::Result<Self>
+    {
+        let content = fs::read_to_string(path)?;
+        let mut settings: Settings = toml::from_str(&content).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+
+        settings.sanitize();
+
+        Ok(settings)
+    }
+
+    /// Same as load(), falls back to the default settings if the file could not be read
+    pub fn load_or_default(path: &Path) -> Self
+    {
+        match Self::load(path)
+        {
+            Ok(settings) => settings,
+            Err(err) =>
+            {
+                if err.kind() != io::ErrorKind::NotFound
+                {
+                    println!("error loading settings from {}: {}, using defaults", path.display(), err);
+                }
+                Self::default()
+            }
+        }
+    }
+
+    /// Write the settings to the TOML file at path
+    pub fn save(&self, path: &Path) -> io::Result<()>
+    {
+        let content = toml::to_string_pretty(self).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+        fs::write(path, content)
+    }
+
+    /// Undo temporary overrides (ex: from the command line) before saving
+    ///
+    /// The fields that still have their overridden value get back the value from the file,
+    /// the fields changed since (ex: from the UI) are kept
+    pub fn without_overrides(&self, file: &Settings, overridden: &Settings) -> Settings
+    {
+        let (Ok(toml::Value::Table(mut current)), Ok(toml::Value::Table(file)), Ok(toml::Value::Table(overridden))) =
+            (toml::Value::try_from(self), toml::Value::try_from(file), toml::Value::try_from(overridden))
+        else
+        {
+            return self.clone();
+        };
+
+        for (key, value) in overridden.iter()
+        {
+            if file.get(key) != Some(value) && current.get(key) == Some(value)
+            {
+                current.insert(key.clone(), file[key].clone());
+            }
+        }
+
+        toml::Value::Table(current).try_into().unwrap_or_else(|_| self.clone())
+    }
+
+    /// The loaded zone needs one extra chunk on each side of the visible zone, the neighbors are needed for meshing
+    pub fn get_min_loaded_distance(&self) -> i32
+    {
+        self.visible_distance + 2
+    }
+
+    /// Bring every value back in a range the engine can work with
+    pub fn sanitize(&mut self)
+    {
+        self.visible_distance = self.visible_distance.clamp(1, MAX_VISIBLE_DISTANCE);
+        self.no_update_distance = self.no_update_distance.clamp(0, self.visible_distance);
+        self.loaded_distance = self.loaded_distance.clamp(self.get_min_loaded_distance(), MAX_LOADED_DISTANCE);
+
+        self.horizon_distance = self.horizon_distance.clamp(0, MAX_HORIZON_DISTANCE);
+
+        let mut previous = 1;
+        for distance in self.lod_distances.iter_mut()
+        {
+            *distance = (*distance).clamp(previous, MAX_LOADED_DISTANCE);
+            previous = *distance;
+        }
+
+        self.window_width = self.window_width.max(320);
+        self.window_height = self.window_height.max(240);
+        self.fov_y = self.fov_y.clamp(FOV_RANGE.0, FOV_RANGE.1);
+        self.far_plane = self.far_plane.clamp(FAR_PLANE_RANGE.0, FAR_PLANE_RANGE.1);
+        self.mouse_sensitivity = self.mouse_sensitivity.clamp(MOUSE_SENSITIVITY_RANGE.0, MOUSE_SENSITIVITY_RANGE.1);
+        self.thread_count = self.thread_count.clamp(THREAD_COUNT_RANGE.0, THREAD_COUNT_RANGE.1);
+        self.fog_density = self.fog_density.max(0.0);
+        self.target_frame_time = self.target_frame_time.clamp(TARGET_FRAME_TIME_RANGE.0, TARGET_FRAME_TIME_RANGE.1);
+
+        // snap to the closest supported resolution
+        let resolution = self.shadow_map_resolution;
+        self.shadow_map_resolution = *SHADOW_MAP_RESOLUTIONS.iter().min_by_key(|res| (*res - resolution).abs()).unwrap();
+    }
+}
@@ -1,16 +1,14 @@
 use std::{collections::VecDeque, rc::Rc, cell::RefCell};
 
-use glam::{Vec3, Vec2};
+use glam::Vec2;
 use imgui::{Condition, FontSource, Context, FontId, CollapsingHeader, Ui};
 use imgui_opengl_renderer::Renderer;
 use imgui_sdl2_support::SdlPlatform;
 use sdl2::{VideoSubsystem, video::Window, EventPump};
 
-use crate::{engine::{renderer::{opengl_abstractions::{shader::Shader, vertex_array::{VertexLayout}}, allocators::default_allocator::DefaultAllocator, self}, geometry::{mesh::Mesh, opengl_vertex::{self, OpenglVertex}}, chunk_manager::ChunkManager, self}, world::{World, self}};
+use crate::{assets::asset_path, input::{InputMap, Action}, settings::{Settings, DisplayMode, SHADOW_MAP_RESOLUTIONS, MAX_HORIZON_DISTANCE, MAX_VISIBLE_DISTANCE, FOV_RANGE, FAR_PLANE_RANGE, MOUSE_SENSITIVITY_RANGE, THREAD_COUNT_RANGE, TARGET_FRAME_TIME_RANGE}, engine::chunk_manager::MAX_LOADED_DISTANCE, engine::{renderer::{opengl_abstractions::{shader::Shader, vertex_array::{VertexLayout}}, allocators::{default_allocator::DefaultAllocator, vertex_pool_allocator::PoolStats, MeshAllocator}, self}, geometry::{mesh::Mesh, opengl_vertex::{self, OpenglVertex}, meshing::chunk_mesher::MesherType}, chunk_manager::ChunkManager, self}, world::{World, self}};
 
 pub struct DebugData {
-    pub player_pos: Vec3,       // player position in absolute coordinates
-    pub front: Vec3,          // front vector
     calculation_times: VecDeque<f32>, // same as frame_time, but without waiting for the framebuffer swap
     pub frame_time: u128,       // should be 16ms on 60 Hz refresh rate
 
@@ -22,6 +20,27 @@ pub struct DebugData {
 
     pub loaded_chunks: usize,
     pub culled_chunks: usize,
+    pub drawn_chunks: usize,
+    pub occluded_chunks: usize, // hidden by the cave culling, counted in the culled chunks
+    pub query_occluded_chunks: usize, // hidden by the occlusion queries only, counted in the culled chunks
+    pub cascade_chunks: Vec<(usize, usize)>, // drawn and culled chunks of each shadow cascade
+    pub lod_chunks: [usize; 4], // rendered chunks at each level of detail
+    pub horizon_tiles: usize,
+    pub drawn_horizon_tiles: usize,
+
+    // streaming
+    pub upload_budget: f32, // ms per frame for the uploads and re-sorts
+    pub queued_jobs: usize,
+    pub chunks_to_generate: usize, // waiting for room in the job queue
+    pub chunks_to_upload: usize,
+    pub chunks_to_sort: usize,
+
+    // edits of the voxels
+    pub edit_latency: f32, // ms from the last edit to the upload of its mesh
+    pub edit_time: f32, // ms per frame on the main thread, to apply the edits and upload their meshes
+    pub pending_edits: usize, // edited voxels not drawn yet
+
+    pub pool_stats: PoolStats,
 }
 
 impl Default for DebugData
@@ -31,11 +50,12 @@ impl Default for DebugData
         let mut calculation_times = VecDeque::new();
         calculation_times.resize(500, 0.5);
 
-        DebugData { player_pos: Vec3::ZERO, front: Vec3::ZERO,
-            frame_time: 0, num_triangles: 0,
+        DebugData { frame_time: 0, num_triangles: 0,
             num_vertices: 0, calculation_times,
             chunk_size_bytes: 0, loaded_chunks: 0,
-            culled_chunks: 0, draw_world_time: 0.0,
+            culled_chunks: 0, drawn_chunks: 0, occluded_chunks: 0, query_occluded_chunks: 0, cascade_chunks: Vec::new(), lod_chunks: [0; 4], horizon_tiles: 0, drawn_horizon_tiles: 0, draw_world_time: 0.0,
+            upload_budget: 0.0, queued_jobs: 0, chunks_to_generate: 0,
+            chunks_to_upload: 0, chunks_to_sort: 0, edit_latency: 0.0, edit_time: 0.0, pending_edits: 0, pool_stats: PoolStats::default(),
         }
     }
 }
@@ -100,10 +120,10 @@ impl UiRenderer
         });
 
         // setup our UI shader
-        let mut ui_shader = Shader::new_from_vs_fs("rust-vox/shaders/ui.vert", "rust-vox/shaders/ui.frag").expect("Error Creating UI Shader");
+        let mut ui_shader = Shader::new_from_vs_fs(&asset_path("shaders/ui.vert"), &asset_path("shaders/ui.frag")).expect("Error Creating UI Shader");
 
         // load UI texture
-        let texture = image::open("rust-vox/textures/widgets.png").unwrap().flipv();
+        let texture = image::open(asset_path("textures/widgets.png")).unwrap().flipv();
         let width = texture.width() as i32;
         let height = texture.height() as i32;
         let mut ui_texture = 0;
@@ -124,9 +144,18 @@ impl UiRenderer
         ui_shader.set_uniform1i("ui_texture", 5).expect("error setting the UI sampler uniform");
         Shader::unbind();
 
-        let size = window.size();
-        let ratio = size.0 as f32 / size.1 as f32 ; // aspect ratio
-        
+        let size = window.drawable_size();
+        let mut cross_hair = Self::create_cross_hair(size.0 as f32 / size.1 as f32);
+
+        let mut allocator = DefaultAllocator::new();
+        allocator.alloc(&mut cross_hair);
+
+        UiRenderer{ allocator, used_font, imgui_renderer, ui_shader, cross_hair, debug_data: debug_info.clone() }
+    }
+
+    // the crosshair is in NDC, it is stretched in y by the aspect ratio to stay square on screen
+    fn create_cross_hair(ratio: f32) -> Mesh<UiVertex>
+    {
         let mut cross_hair: Mesh<UiVertex> = Mesh::default();
         let uv_lower_left = Vec2::new(0.0/16.0,0.0/16.0);
         let offset = 1.0/16.0;
@@ -138,20 +167,30 @@ impl UiRenderer
         UiVertex{position:Vec2::new(0.03,-0.03 * ratio), uv:uv_lower_left + Vec2::new(offset,0.0)}
         );
 
-        let mut allocator = DefaultAllocator::new();
-        allocator.alloc(&mut cross_hair);
+        cross_hair
+    }
 
-        UiRenderer{ allocator, used_font, imgui_renderer, ui_shader, cross_hair, debug_data: debug_info.clone() }
+    /// Called when the drawable size of the window changed
+    pub fn resize(&mut self, width: u32, height: u32)
+    {
+        if let Some(token) = self.cross_hair.release_token()
+        {
+            self.allocator.dealloc(token);
+        }
+
+        self.cross_hair = Self::create_cross_hair(width as f32 / height as f32);
+        self.allocator.alloc(&mut self.cross_hair);
     }
 
     /// Render the UI
-    pub fn render(&mut self, voxel_world: &mut World, renderer: &mut engine::renderer::Renderer, platform: &mut SdlPlatform, imgui_context: &mut Context, window: &Window, event_pump: &EventPump)
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(&mut self, voxel_world: &mut World, renderer: &mut engine::renderer::Renderer, input_map: &mut InputMap, settings: &mut Settings, platform: &mut SdlPlatform, imgui_context: &mut Context, window: &Window, event_pump: &EventPump)
     {
         // render the Imgui UI
         platform.prepare_frame(imgui_context, window, event_pump);
 
         self.imgui_renderer.render(imgui_context, |ui: &mut Ui| {
-            self.build_ui(ui, voxel_world, renderer);
+            self.build_ui(ui, voxel_world, renderer, input_map, settings);
         });
 
         // render our own UI
@@ -185,7 +224,7 @@ impl UiRenderer
         vao.unbind();
     }
 
-    pub fn build_ui(&self, ui: &imgui::Ui, voxel_world: &mut World, renderer: &mut engine::renderer::Renderer)
+    pub fn build_ui(&self, ui: &imgui::Ui, voxel_world: &mut World, renderer: &mut engine::renderer::Renderer, input_map: &mut InputMap, settings: &mut Settings)
     {
         let font = ui.push_font(self.used_font);
         ui.window("Tab")
@@ -193,16 +232,22 @@ impl UiRenderer
         .size([500.0, 600.0], Condition::FirstUseEver)
         .build(|| {
 
-        let mut debug_data = self.debug_data.borrow_mut();
+        let debug_data = self.debug_data.borrow();
 
         // Controls Section
         if CollapsingHeader::new("Controls")
         .default_open(false)
         .build(ui)
         {
-            ui.text("Num1 to Toggle Mouse");
-            ui.text("Num2 to Toggle Line Mode");
-            ui.text("NUm3 to Toggle between Vsync Off/On");
+            Self::build_controls(ui, input_map);
+        }
+
+        // Settings Section
+        if CollapsingHeader::new("Settings")
+        .default_open(false)
+        .build(ui)
+        {
+            Self::build_settings(ui, settings);
         }
 
         // Debug Info Section
@@ -210,14 +255,30 @@ impl UiRenderer
         .default_open(true)
         .build(ui)
         {
-            ui.text(format!("player in chunk: {}", ChunkManager::get_chunk_pos(debug_data.player_pos)));
-            ui.text(format!("player position: {}", debug_data.player_pos));
-            ui.text(format!("look_at vector: {}", debug_data.front));
+            let player_pos = voxel_world.camera.get_position();
+            ui.text(format!("player in chunk: {}", ChunkManager::get_chunk_pos(player_pos)));
+            ui.text(format!("player position: {}", player_pos));
+            ui.text(format!("look_at vector: {}", voxel_world.camera.get_front()));
 
             if ui.button("Rebuild World")
             {
                 voxel_world.rebuild(); // TODO: it out of place
             }
+
+            // the rendered chunks meshed by each mesher, side by side
+            if ui.button("Compare Meshers")
+            {
+                voxel_world.chunk_manager.compare_meshers();
+            }
+            if voxel_world.chunk_manager.is_comparing_meshers()
+            {
+                ui.text("comparing the meshers...");
+            }
+            for report in voxel_world.chunk_manager.get_mesher_reports()
+            {
+                ui.text(format!("{}: {} vertices, {} triangles, {:.2} ms ({:.3} ms/chunk)", report.mesher.name(), report.num_vertices, report.num_triangles,
+                    report.mesh_time, report.mesh_time / report.num_chunks.max(1) as f32));
+            }
         }
 
         // Profiling Section
@@ -242,7 +303,29 @@ impl UiRenderer
             ui.text(format!("Chunk Vertices: {}", debug_data.num_vertices));
             ui.text(format!("Chunk Level Info Storage: {:.2} MiBs", debug_data.chunk_size_bytes as f32 / (1024f32 * 1024f32)));
             ui.text(format!("Loaded Chunks: {}", debug_data.loaded_chunks));
+            ui.text(format!("Drawn Chunks: {}", debug_data.drawn_chunks));
             ui.text(format!("Culled Chunks: {}", debug_data.culled_chunks));
+            ui.text(format!("Occluded Chunks: {}, by queries: {}", debug_data.occluded_chunks, debug_data.query_occluded_chunks));
+            for (cascade, (drawn, culled)) in debug_data.cascade_chunks.iter().enumerate()
+            {
+                ui.text(format!("Cascade {}: drawn {}, culled {}", cascade, drawn, culled));
+            }
+            let lod = &debug_data.lod_chunks;
+            ui.text(format!("LOD Chunks: 1x {} 2x {} 4x {} 8x {}", lod[0], lod[1], lod[2], lod[3]));
+            ui.text(format!("Horizon Tiles: drawn {}, loaded {}", debug_data.drawn_horizon_tiles, debug_data.horizon_tiles));
+            ui.text(format!("Upload Budget: {:.2} ms", debug_data.upload_budget));
+            ui.text(format!("Queued Jobs: {}", debug_data.queued_jobs));
+            ui.text(format!("Waiting Generation: {}", debug_data.chunks_to_generate));
+            ui.text(format!("Waiting Upload: {}", debug_data.chunks_to_upload));
+            ui.text(format!("Waiting Re-sort: {}", debug_data.chunks_to_sort));
+            ui.text(format!("Edit Latency: {:.2} ms, main thread {:.2} ms, pending {}", debug_data.edit_latency, debug_data.edit_time, debug_data.pending_edits));
+
+            let pool = &debug_data.pool_stats;
+            let mib = |bytes: usize| bytes as f32 / (1024f32 * 1024f32);
+            ui.text(format!("Vertex Pool Allocations: {}", pool.num_allocations));
+            ui.text(format!("Vertex Pool Vertices: {:.2}/{:.2} MiBs, fragmentation {:.0}%", mib(pool.vertex_bytes_used), mib(pool.vertex_bytes_capacity), pool.vertex_fragmentation * 100.0));
+            ui.text(format!("Vertex Pool Indices: {:.2}/{:.2} MiBs, fragmentation {:.0}%", mib(pool.index_bytes_used), mib(pool.index_bytes_capacity), pool.index_fragmentation * 100.0));
+            ui.text(format!("Vertex Pool Moves: {}, Growths: {}", pool.num_moves, pool.num_growths));
         }
 
         if CollapsingHeader::new("Sky Options")
@@ -266,4 +349,106 @@ impl UiRenderer
 
         font.pop();});
     }
+
+    /// Edits the settings in place, the changes are applied by the caller
+    fn build_settings(ui: &imgui::Ui, settings: &mut Settings)
+    {
+        ui.slider("Visible Distance", 1, MAX_VISIBLE_DISTANCE, &mut settings.visible_distance);
+        ui.slider("Loaded Distance", settings.get_min_loaded_distance(), MAX_LOADED_DISTANCE, &mut settings.loaded_distance);
+        ui.slider("No Update Distance", 0, settings.visible_distance, &mut settings.no_update_distance);
+        ui.slider("LOD 2x Distance", 1, settings.lod_distances[1], &mut settings.lod_distances[0]);
+        ui.slider("LOD 4x Distance", settings.lod_distances[0], settings.lod_distances[2], &mut settings.lod_distances[1]);
+        ui.slider("LOD 8x Distance", settings.lod_distances[1], MAX_LOADED_DISTANCE, &mut settings.lod_distances[2]);
+        ui.slider("Horizon Distance", 0, MAX_HORIZON_DISTANCE, &mut settings.horizon_distance);
+
+        ui.slider("FOV", FOV_RANGE.0, FOV_RANGE.1, &mut settings.fov_y);
+        ui.slider("Far Plane", FAR_PLANE_RANGE.0, FAR_PLANE_RANGE.1, &mut settings.far_plane);
+        ui.slider("Fog Density", 0.0, 10.0, &mut settings.fog_density);
+        ui.slider("Mouse Sensitivity", MOUSE_SENSITIVITY_RANGE.0, MOUSE_SENSITIVITY_RANGE.1, &mut settings.mouse_sensitivity);
+        ui.checkbox("VSync", &mut settings.vsync);
+        ui.checkbox("GPU Culling", &mut settings.gpu_culling);
+        ui.checkbox("Cave Culling", &mut settings.cave_culling);
+        ui.checkbox("Occlusion Queries", &mut settings.occlusion_queries);
+
+        let mut mode_index = DisplayMode::ALL.iter().position(|mode| *mode == settings.display_mode).unwrap_or(0);
+        if ui.combo("Display Mode", &mut mode_index, &DisplayMode::ALL, |mode| mode.name().into())
+        {
+            settings.display_mode = DisplayMode::ALL[mode_index];
+        }
+
+        let mut mesher_index = MesherType::ALL.iter().position(|mesher| *mesher == settings.mesher).unwrap_or(0);
+        if ui.combo("Mesher", &mut mesher_index, &MesherType::ALL, |mesher| mesher.name().into())
+        {
+            settings.mesher = MesherType::ALL[mesher_index];
+        }
+
+        let mut resolution_index = SHADOW_MAP_RESOLUTIONS.iter().position(|res| *res == settings.shadow_map_resolution).unwrap_or(0);
+        if ui.combo("Shadow Map Resolution", &mut resolution_index, &SHADOW_MAP_RESOLUTIONS, |res| res.to_string().into())
+        {
+            settings.shadow_map_resolution = SHADOW_MAP_RESOLUTIONS[resolution_index];
+        }
+
+        ui.slider("Target Frame Time (ms)", TARGET_FRAME_TIME_RANGE.0, TARGET_FRAME_TIME_RANGE.1, &mut settings.target_frame_time);
+
+        let mut thread_count = settings.thread_count as i32;
+        if ui.slider("Worker Threads", THREAD_COUNT_RANGE.0 as i32, THREAD_COUNT_RANGE.1 as i32, &mut thread_count)
+        {
+            settings.thread_count = thread_count as usize;
+        }
+
+        // only applied when pressing enter, the window shouldn't resize on every keystroke
+        let mut window_size = [settings.window_width as i32, settings.window_height as i32];
+        if ui.input_int2("Window Size", &mut window_size).enter_returns_true(true).build()
+        {
+            settings.window_width = window_size[0].max(0) as u32;
+            settings.window_height = window_size[1].max(0) as u32;
+        }
+
+        if ui.button("Reset Settings")
+        {
+            *settings = Settings::default();
+        }
+    }
+
+    /// Lists every action with its bindings, clicking on a binding starts a rebind
+    fn build_controls(ui: &imgui::Ui, input_map: &mut InputMap)
+    {
+        for action in Action::ALL
+        {
+            let bindings: Vec<String> = input_map.get_bindings(action).iter().map(|binding| binding.to_string()).collect();
+
+            let label = if input_map.get_pending_rebind() == Some(action)
+            {
+                "press a key, mouse button or controller button, Escape cancels...".to_string()
+            }
+            else if bindings.is_empty()
+            {
+                "unbound".to_string()
+            }
+            else
+            {
+                bindings.join(", ")
+            };
+
+            ui.text(format!("{:<16}", action.name()));
+            ui.same_line();
+            // the ## suffix gives each button a unique id even if the labels are the same
+            if ui.button(format!("{}##{}", label, action.name()))
+            {
+                input_map.start_rebind(action);
+            }
+        }
+
+        if input_map.get_pending_rebind().is_some()
+        {
+            if ui.button("Cancel rebind")
+            {
+                input_map.cancel_rebind();
+            }
+        }
+        else if ui.button("Reset to defaults")
+        {
+            *input_map = InputMap::default();
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,46 @@
+#[cfg(test)]
+mod benchmark
+{
+    use engine::benchmark::{BenchmarkFlight, FrameTimeStats};
+    use glam::Vec3;
+
+    #[test]
+    fn frame_time_stats()
+    {
+        assert!(FrameTimeStats::new(&[]).is_none());
+
+        // 1..=100 ms
+        let frame_times: Vec<f32> = (1..=100).rev().map(|ms| ms as f32).collect();
+        let stats = FrameTimeStats::new(&frame_times).unwrap();
+
+        assert_eq!(stats.frames, 100);
+        assert_eq!(stats.min, 1.0);
+        assert_eq!(stats.max, 100.0);
+        assert_eq!(stats.average, 50.5);
+        assert_eq!(stats.p50, 50.0);
+        assert_eq!(stats.p95, 95.0);
+        assert_eq!(stats.p99, 99.0);
+    }
+
+    #[test]
+    fn flight_is_continuous()
+    {
+        let start = Vec3::new(0.0, 60.0, 0.0);
+        let flight = BenchmarkFlight::new(start, 5.0);
+
+        assert_eq!(flight.get_position(0.0), start);
+        assert!(!flight.is_finished(4.9));
+        assert!(flight.is_finished(5.0));
+
+        // no jumps between frames at 60 FPS, the camera stays at the same height
+        let mut t = 0.0;
+        while t < 5.0
+        {
+            let step = flight.get_position(t + 1.0 / 60.0) - flight.get_position(t);
+            assert!(step.length() < 2.0);
+            assert_eq!(flight.get_position(t).y, start.y);
+            assert!((flight.get_front(t).length() - 1.0).abs() < 1e-4);
+            t += 1.0 / 60.0;
+        }
+    }
+}
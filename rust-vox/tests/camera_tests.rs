@@ -0,0 +1,30 @@
+#[cfg(test)]
+mod camera
+{
+    use engine::engine::camera::Camera;
+    use glam::{Vec3, IVec3};
+
+    #[test]
+    fn relative_to_the_render_origin_far_away()
+    {
+        let mut camera = Camera::new(1.0, 1.0, 0.1, 100.0, Vec3::new(1_000_010.5, 60.0, -2_000_003.25), Vec3::X, Vec3::Y, 0.01);
+        assert_eq!(camera.get_render_origin(), IVec3::new(1_000_000, 0, -2_000_020));
+        assert_eq!(camera.get_relative_position(), Vec3::new(10.5, 60.0, 16.75));
+
+        // steps smaller than the precision of a f32 at this distance still move the camera
+        for _ in 0..100
+        {
+            camera.move_forward();
+        }
+        assert!((camera.get_relative_position().x - 11.5).abs() < 1e-3);
+
+        // entering the next chunk moves the origin
+        camera.set_front(Vec3::Z);
+        for _ in 0..400
+        {
+            camera.move_forward();
+        }
+        assert_eq!(camera.get_render_origin(), IVec3::new(1_000_000, 0, -2_000_000));
+        assert!((camera.get_relative_position().z - 0.75).abs() < 1e-3);
+    }
+}
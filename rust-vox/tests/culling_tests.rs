@@ -0,0 +1,70 @@
+#[cfg(test)]
+mod culling
+{
+    use engine::engine::{camera::{Camera, AABB, BoundingBox}, renderer::{culling::{get_frustum_planes, is_box_visible, cull_commands}, allocators::vertex_pool_allocator::Daic},
+        chunk::Chunk, terrain::FlatGenerator, geometry::voxel::{Voxel, VoxelType}};
+    use glam::{Vec3, Mat4, IVec2, IVec3};
+
+    #[test]
+    fn planes_from_view_projection()
+    {
+        // looking along +x
+        let camera = Camera::new(45f32.to_radians(), 1.0, 0.1, 100.0, Vec3::ZERO, Vec3::X, Vec3::Y, 1.0);
+        let planes = get_frustum_planes(&(camera.get_persp_trans() * camera.get_look_at()), true);
+
+        let unit_box = |center: Vec3| AABB::new(center - Vec3::ONE, center + Vec3::ONE);
+
+        assert!(is_box_visible(&planes, &unit_box(Vec3::new(10.0, 0.0, 0.0))));
+        assert!(!is_box_visible(&planes, &unit_box(Vec3::new(-10.0, 0.0, 0.0)))); // behind
+        assert!(!is_box_visible(&planes, &unit_box(Vec3::new(10.0, 0.0, 20.0)))); // on the side
+        assert!(!is_box_visible(&planes, &unit_box(Vec3::new(200.0, 0.0, 0.0)))); // past the far plane
+
+        // a light looking down, what is between the light and its near plane still casts shadows
+        let light = Mat4::orthographic_rh_gl(-10.0, 10.0, -10.0, 10.0, 1.0, 100.0) * Mat4::look_at_rh(Vec3::ZERO, Vec3::NEG_Y, Vec3::X);
+        let caster = unit_box(Vec3::new(0.0, 5.0, 0.0));
+        assert!(!is_box_visible(&get_frustum_planes(&light, true), &caster));
+        assert!(is_box_visible(&get_frustum_planes(&light, false), &caster));
+    }
+
+    #[test]
+    fn cpu_culling_matches_the_shader()
+    {
+        let camera = Camera::new(45f32.to_radians(), 1.0, 0.1, 100.0, Vec3::ZERO, Vec3::X, Vec3::Y, 1.0);
+        let planes = get_frustum_planes(&(camera.get_persp_trans() * camera.get_look_at()), true);
+
+        let unit_box = |center: Vec3| AABB::new(center - Vec3::ONE, center + Vec3::ONE);
+        let aabbs = [unit_box(Vec3::new(-10.0, 0.0, 0.0)), unit_box(Vec3::new(10.0, 0.0, 0.0)), unit_box(Vec3::new(20.0, 0.0, 0.0))];
+
+        // opaque commands then transparent ones, the last chunk has no opaque faces
+        let commands = [Daic::new(6, 1, 0, 0), Daic::new(6, 1, 6, 4), Daic::new(0, 1, 0, 0),
+                        Daic::new(3, 1, 12, 0), Daic::new(3, 1, 15, 4), Daic::new(3, 1, 18, 8)];
+
+        let mut culled = Vec::new();
+        assert_eq!(cull_commands(&planes, &aabbs, None, &commands, &mut culled), 2);
+        assert_eq!(culled.len(), commands.len());
+
+        // the visible opaque commands are compacted
+        assert_eq!(culled[0].get_num_indices(), 6);
+        assert!(culled[1..3].iter().all(|command| command.get_num_indices() == 0));
+
+        // the transparent commands keep their order, the one behind the camera draws nothing
+        let instances: Vec<u32> = culled[3..].iter().map(|command| command.get_num_instances()).collect();
+        assert_eq!(instances, [0, 1, 1]);
+    }
+
+    #[test]
+    fn chunk_aabb_fits_the_terrain()
+    {
+        // the flat ground fills the layers under 20
+        let mut chunk = Chunk::new(IVec2::new(1, 0), &FlatGenerator::default());
+        let aabb = chunk.get_aabb();
+        assert_eq!((aabb.min.y, aabb.max.y), (0.0, 20.0));
+        assert_eq!((aabb.min.x, aabb.max.x), (20.0, 40.0));
+
+        chunk.set_voxel(IVec3::new(3, 30, 3), Voxel::new(VoxelType::Dirt));
+        assert_eq!(chunk.get_occupied_y(), Some((0, 30)));
+
+        chunk.set_voxel(IVec3::new(3, 30, 3), Voxel::new(VoxelType::Air));
+        assert_eq!(chunk.get_occupied_y(), Some((0, 19)));
+    }
+}
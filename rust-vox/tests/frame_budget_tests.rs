@@ -0,0 +1,28 @@
+#[cfg(test)]
+mod frame_budget
+{
+    use std::time::Instant;
+    use engine::engine::frame_budget::{FrameBudget, MIN_BUDGET, MAX_BUDGET};
+
+    #[test]
+    fn adapts_to_frame_time()
+    {
+        let mut budget = FrameBudget::new(20.0);
+        let start = budget.get_budget();
+
+        // slow frames cut the budget down to the minimum
+        budget.adapt(30.0);
+        assert!(budget.get_budget() < start);
+        for _ in 0..100 { budget.adapt(30.0); }
+        assert_eq!(budget.get_budget(), MIN_BUDGET);
+
+        // fast frames let it grow back slowly, up to the maximum
+        budget.adapt(10.0);
+        assert!(budget.get_budget() > MIN_BUDGET && budget.get_budget() < start);
+        for _ in 0..1000 { budget.adapt(10.0); }
+        assert_eq!(budget.get_budget(), MAX_BUDGET);
+
+        let now = Instant::now();
+        assert!((budget.get_deadline(now).duration_since(now).as_secs_f32() * 1000.0 - MAX_BUDGET).abs() < 0.001);
+    }
+}
@@ -0,0 +1,47 @@
+#[cfg(test)]
+mod free_list
+{
+    use engine::engine::renderer::allocators::free_list::FreeList;
+
+    #[test]
+    fn alloc_free_and_merge()
+    {
+        let mut list = FreeList::new(100);
+
+        let a = list.alloc(30).unwrap();
+        let b = list.alloc(30).unwrap();
+        let c = list.alloc(30).unwrap();
+        assert_eq!((a, b, c), (0, 30, 60));
+        assert_eq!(list.alloc(20), None);
+
+        // a hole in the middle, the free elements are scattered
+        list.free(b, 30);
+        assert_eq!(list.get_num_used(), 60);
+        assert_eq!(list.get_largest_free(), 30);
+        assert!(list.get_fragmentation() > 0.0);
+
+        // first fit
+        assert_eq!(list.alloc(5), Some(30));
+        list.free(30, 5);
+
+        // freeing the neighbors merges everything back
+        list.free(a, 30);
+        list.free(c, 30);
+        assert_eq!(list.get_largest_free(), 100);
+        assert_eq!(list.get_fragmentation(), 0.0);
+    }
+
+    #[test]
+    fn grow()
+    {
+        let mut list = FreeList::new(10);
+        list.alloc(8).unwrap();
+        assert_eq!(list.alloc(4), None);
+
+        // the new elements merge with the free ones at the end
+        list.grow(20);
+        assert_eq!(list.get_largest_free(), 12);
+        assert_eq!(list.alloc(12), Some(8));
+        assert_eq!(list.get_num_used(), 20);
+    }
+}
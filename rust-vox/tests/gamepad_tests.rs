@@ -0,0 +1,115 @@
+#[cfg(test)]
+mod gamepad
+{
+    use engine::input::{Binding, gamepad::{apply_stick_response, GamepadState, GamepadConfig, Gamepads}};
+    use glam::Vec2;
+    use sdl2::{controller::Axis, event::Event, sys, EventPump};
+
+    fn axis_motion(which: u32, axis: Axis, value: i16) -> Event
+    {
+        Event::ControllerAxisMotion { timestamp: 0, which, axis, value }
+    }
+
+    #[test]
+    fn stick_response()
+    {
+        // inside the deadzone nothing moves
+        assert_eq!(apply_stick_response(Vec2::new(0.1, 0.05), 0.15, 2.0), Vec2::ZERO);
+
+        // no jump at the edge of the deadzone
+        assert!(apply_stick_response(Vec2::new(0.16, 0.0), 0.15, 2.0).length() < 0.01);
+
+        // full deflection keeps its direction and never goes over 1
+        let full = apply_stick_response(Vec2::new(1.0, 1.0), 0.15, 2.0);
+        assert!((full.length() - 1.0).abs() < 1e-5);
+        assert!((full.x - full.y).abs() < 1e-5);
+
+        // the curve gives finer control than linear at half deflection
+        let half = apply_stick_response(Vec2::new(0.5, 0.0), 0.0, 2.0);
+        assert!((half.x - 0.25).abs() < 1e-5);
+    }
+
+    #[test]
+    fn triggers_press_once()
+    {
+        let mut state = GamepadState::new(GamepadConfig::default());
+
+        assert_eq!(state.handle_event(&axis_motion(0, Axis::TriggerRight, 10000)), None);
+        assert_eq!(state.handle_event(&axis_motion(0, Axis::TriggerRight, 30000)), Some(Binding::ControllerTrigger(Axis::TriggerRight)));
+
+        // held down, no repeat
+        assert_eq!(state.handle_event(&axis_motion(0, Axis::TriggerRight, i16::MAX)), None);
+
+        // released then pressed again
+        assert_eq!(state.handle_event(&axis_motion(0, Axis::TriggerRight, 0)), None);
+        assert_eq!(state.handle_event(&axis_motion(0, Axis::TriggerRight, i16::MAX)), Some(Binding::ControllerTrigger(Axis::TriggerRight)));
+
+        // sticks never press anything
+        assert_eq!(state.handle_event(&axis_motion(0, Axis::LeftX, i16::MAX)), None);
+    }
+
+    #[test]
+    fn sticks_from_events()
+    {
+        let mut state = GamepadState::new(GamepadConfig::default());
+
+        // stick pushed up is forward
+        state.handle_event(&axis_motion(3, Axis::LeftY, i16::MIN + 1));
+        let movement = state.get_movement();
+        assert!(movement.x.abs() < 1e-5 && (movement.y - 1.0).abs() < 1e-5);
+
+        // stick drift is ignored
+        state.handle_event(&axis_motion(3, Axis::RightX, 1000));
+        assert_eq!(state.get_look(), Vec2::ZERO);
+
+        // unplugging the controller stops the movement
+        state.handle_event(&Event::ControllerDeviceRemoved { timestamp: 0, which: 3 });
+        assert_eq!(state.get_movement(), Vec2::ZERO);
+    }
+
+    // returns what was pressed in the pending events
+    fn pump(gamepads: &mut Gamepads, event_pump: &mut EventPump) -> Vec<Binding>
+    {
+        event_pump.poll_iter().filter_map(|event| gamepads.handle_event(&event).or(Binding::from_event(&event))).collect()
+    }
+
+    // goes through SDL with a virtual joystick, needs no physical controller
+    #[test]
+    fn virtual_controller_hotplug()
+    {
+        let sdl = sdl2::init().unwrap();
+        let mut gamepads = Gamepads::new(sdl.game_controller().unwrap(), GamepadConfig::default());
+        let mut event_pump = sdl.event_pump().unwrap();
+
+        // a virtual joystick of the game controller type gets the standard mapping: axis/button n is controller axis/button n
+        let device_index = unsafe { sys::SDL_JoystickAttachVirtual(sys::SDL_JoystickType::SDL_JOYSTICK_TYPE_GAMECONTROLLER, 6, 15, 0) };
+        assert!(device_index >= 0);
+        let joystick = unsafe { sys::SDL_JoystickOpen(device_index) };
+        assert!(!joystick.is_null());
+
+        pump(&mut gamepads, &mut event_pump);
+        assert_eq!(gamepads.get_num_connected(), 1);
+
+        unsafe
+        {
+            sys::SDL_JoystickSetVirtualAxis(joystick, Axis::LeftY as i32, i16::MIN);
+            sys::SDL_JoystickSetVirtualAxis(joystick, Axis::TriggerRight as i32, i16::MAX);
+            sys::SDL_JoystickSetVirtualButton(joystick, sys::SDL_GameControllerButton::SDL_CONTROLLER_BUTTON_A as i32, 1);
+        }
+
+        let pressed = pump(&mut gamepads, &mut event_pump);
+        assert!(pressed.contains(&Binding::ControllerTrigger(Axis::TriggerRight)));
+        assert!(pressed.contains(&Binding::ControllerButton(sdl2::controller::Button::A)));
+        assert!(gamepads.state.get_movement().y > 0.99);
+
+        unsafe
+        {
+            sys::SDL_JoystickClose(joystick);
+            sys::SDL_JoystickDetachVirtual(device_index);
+        }
+
+        pump(&mut gamepads, &mut event_pump);
+        assert_eq!(gamepads.get_num_connected(), 0);
+        assert_eq!(gamepads.state.get_movement(), Vec2::ZERO);
+    }
+}
@@ -0,0 +1,246 @@
+// These tests need an OpenGL 4.5 context, the CI runs them on Mesa's llvmpipe:
+// LIBGL_ALWAYS_SOFTWARE=1 xvfb-run -a cargo test -p rust-vox --test gpu_culling_tests -- --ignored
+
+#[cfg(test)]
+mod gpu_culling
+{
+    use std::{ffi::c_void, mem::size_of};
+    use engine::engine::{camera::{Camera, AABB}, renderer::{culling::{ChunkCuller, get_frustum_planes, cull_commands}, occlusion::OcclusionQueries, allocators::{MeshAllocator, vertex_pool_allocator::{Daic, VertexPoolAllocator}}},
+                         geometry::{mesh::Mesh, voxel_vertex::VoxelVertex, voxel::{Voxel, VoxelType}, meshing::chunk_mesher::NormalDirection}};
+    use glam::{Vec3, IVec2, Mat4};
+    use sdl2::{video::{GLProfile, Window, GLContext}, Sdl};
+
+    // a hidden window, the context is current while they live
+    fn create_context() -> (Sdl, Window, GLContext)
+    {
+        let sdl = sdl2::init().unwrap();
+        let video_subsystem = sdl.video().unwrap();
+        let gl_attr = video_subsystem.gl_attr();
+        gl_attr.set_context_version(4, 5);
+        gl_attr.set_context_profile(GLProfile::Core);
+
+        let window = video_subsystem.window("culling test", 64, 64).opengl().hidden().build().unwrap();
+        let gl_context = window.gl_create_context().unwrap();
+        gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as _);
+        (sdl, window, gl_context)
+    }
+
+    #[test]
+    #[ignore = "needs an OpenGL 4.5 context, run with LIBGL_ALWAYS_SOFTWARE=1"]
+    fn compute_culling_matches_the_cpu()
+    {
+        let _context = create_context();
+
+        // looking along +x, a row of chunks from behind the camera to past the far plane, every third one occluded
+        let camera = Camera::new(45f32.to_radians(), 1.0, 0.1, 100.0, Vec3::ZERO, Vec3::X, Vec3::Y, 1.0);
+        let planes = get_frustum_planes(&(camera.get_persp_trans() * camera.get_look_at()), true);
+
+        let num_chunks = 100; // more than a workgroup
+        let aabbs: Vec<AABB> = (0..num_chunks).map(|i|
+        {
+            let center = Vec3::new(i as f32 * 3.0 - 30.0, 0.0, (i % 7) as f32 * 4.0 - 12.0);
+            AABB::new(center - Vec3::ONE, center + Vec3::ONE)
+        }).collect();
+        let occluded: Vec<bool> = (0..num_chunks).map(|i| i % 3 == 0).collect();
+
+        // every fifth chunk has no opaque faces
+        let mut commands: Vec<Daic> = (0..num_chunks as u32).map(|i| Daic::new(if i % 5 == 0 { 0 } else { 6 }, 1, i * 12 + 6, i * 8).with_base_instance(i)).collect();
+        commands.extend((0..num_chunks as u32).map(|i| Daic::new(6, 1, i * 12, i * 8).with_base_instance(i)));
+
+        let mut buffer = 0;
+        unsafe
+        {
+            gl::GenBuffers(1, &mut buffer);
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, buffer);
+            gl::BufferData(gl::SHADER_STORAGE_BUFFER, (commands.len() * size_of::<Daic>()) as isize, commands.as_ptr() as *const c_void, gl::STATIC_DRAW);
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+        }
+
+        let mut culler = ChunkCuller::default();
+        let key = |command: &Daic| (command.get_base_instance(), command.get_num_indices(), command.get_num_instances());
+
+        for occlusion in [false, true]
+        {
+            culler.prepare_frame(aabbs.clone(), occluded.clone(), commands.clone(), 1);
+            culler.cull_buffer(0, &planes, occlusion, buffer, 0);
+            let (gpu, gpu_visible) = culler.read_back(0);
+            culler.end_frame();
+
+            let mut cpu = Vec::new();
+            let cpu_visible = cull_commands(&planes, &aabbs, occlusion.then_some(&occluded[..]), &commands, &mut cpu);
+            assert!(cpu_visible > 0 && cpu_visible < num_chunks);
+            assert_eq!(gpu_visible, cpu_visible);
+
+            // the opaque commands are compacted in the order the invocations ran
+            let mut gpu_opaque: Vec<_> = gpu[..num_chunks].iter().map(key).collect();
+            let mut cpu_opaque: Vec<_> = cpu[..num_chunks].iter().map(key).collect();
+            gpu_opaque.sort();
+            cpu_opaque.sort();
+            assert_eq!(gpu_opaque, cpu_opaque);
+
+            let gpu_transparent: Vec<_> = gpu[num_chunks..].iter().map(key).collect();
+            let cpu_transparent: Vec<_> = cpu[num_chunks..].iter().map(key).collect();
+            assert_eq!(gpu_transparent, cpu_transparent);
+        }
+
+        unsafe
+        {
+            gl::DeleteBuffers(1, &buffer);
+        }
+    }
+
+    #[test]
+    #[ignore = "needs an OpenGL 4.5 context, run with LIBGL_ALWAYS_SOFTWARE=1"]
+    fn occlusion_queries_follow_the_depth_buffer()
+    {
+        let _context = create_context();
+
+        // the boxes are drawn against the depth buffer of a framebuffer, with the transforms of the camera
+        let camera = Camera::new(45f32.to_radians(), 1.0, 0.1, 100.0, Vec3::ZERO, Vec3::X, Vec3::Y, 1.0);
+        let transforms: [Mat4; 2] = [camera.get_persp_trans(), camera.get_look_at()];
+        let planes = get_frustum_planes(&(transforms[0] * transforms[1]), true);
+        let (mut framebuffer, mut renderbuffers, mut ubo) = (0, [0; 2], 0);
+        unsafe
+        {
+            gl::GenFramebuffers(1, &mut framebuffer);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+            gl::GenRenderbuffers(2, renderbuffers.as_mut_ptr());
+            for (renderbuffer, (format, attachment)) in renderbuffers.iter().zip([(gl::RGBA8, gl::COLOR_ATTACHMENT0), (gl::DEPTH_COMPONENT24, gl::DEPTH_ATTACHMENT)])
+            {
+                gl::BindRenderbuffer(gl::RENDERBUFFER, *renderbuffer);
+                gl::RenderbufferStorage(gl::RENDERBUFFER, format, 64, 64);
+                gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, attachment, gl::RENDERBUFFER, *renderbuffer);
+            }
+            assert_eq!(gl::CheckFramebufferStatus(gl::FRAMEBUFFER), gl::FRAMEBUFFER_COMPLETE);
+            gl::Viewport(0, 0, 64, 64);
+            gl::Enable(gl::DEPTH_TEST);
+
+            gl::GenBuffers(1, &mut ubo);
+            gl::BindBuffer(gl::UNIFORM_BUFFER, ubo);
+            gl::BufferData(gl::UNIFORM_BUFFER, size_of::<[Mat4; 2]>() as isize, transforms.as_ptr() as *const c_void, gl::STATIC_DRAW);
+            gl::BindBufferBase(gl::UNIFORM_BUFFER, 0, ubo);
+        }
+
+        // a chunk in front of the camera, one behind it and the one around it
+        let (front, behind, around) = (IVec2::new(1, 0), IVec2::new(-2, 0), IVec2::ZERO);
+        let chunks = [(front, AABB::new(Vec3::new(20.0, -5.0, -5.0), Vec3::new(30.0, 5.0, 5.0))),
+                      (behind, AABB::new(Vec3::new(-30.0, -5.0, -5.0), Vec3::new(-20.0, 5.0, 5.0))),
+                      (around, AABB::new(Vec3::splat(-5.0), Vec3::splat(5.0)))];
+
+        // a frame whose depth buffer is a wall in front of everything, or empty, the results come back a few frames later
+        let mut occlusion = OcclusionQueries::default();
+        let frame = |occlusion: &mut OcclusionQueries, wall: bool|
+        {
+            unsafe
+            {
+                gl::ClearDepth(if wall { 0.0 } else { 1.0 });
+                gl::Clear(gl::DEPTH_BUFFER_BIT);
+            }
+            occlusion.collect_results();
+            occlusion.query(&chunks, &planes, Vec3::ZERO);
+            unsafe { gl::Finish(); }
+        };
+
+        for _ in 0..4
+        {
+            frame(&mut occlusion, true);
+        }
+        assert!(occlusion.is_occluded(front));
+        assert!(!occlusion.is_occluded(behind) && !occlusion.is_occluded(around));
+        assert_eq!(occlusion.get_num_occluded(), 1);
+
+        // turned off and on again, the results of before are forgotten, the queries in flight included
+        occlusion.reset();
+        assert_eq!(occlusion.get_num_occluded(), 0);
+        for _ in 0..4
+        {
+            frame(&mut occlusion, false);
+            assert!(!occlusion.is_occluded(front));
+        }
+
+        for _ in 0..4
+        {
+            frame(&mut occlusion, true);
+        }
+        assert!(occlusion.is_occluded(front));
+
+        unsafe
+        {
+            gl::DeleteFramebuffers(1, &framebuffer);
+            gl::DeleteRenderbuffers(2, renderbuffers.as_ptr());
+            gl::DeleteBuffers(1, &ubo);
+        }
+    }
+
+    #[test]
+    #[ignore = "needs an OpenGL 4.5 context, run with LIBGL_ALWAYS_SOFTWARE=1"]
+    fn moved_ranges_are_reused_after_the_copy()
+    {
+        let _context = create_context();
+
+        // the meshes are told apart by the height of their vertices and the step of their indices
+        let create_mesh = |height: u32, len: u32|
+        {
+            let mut mesh = Mesh::default();
+            mesh.vertices = (0..len).map(|i| VoxelVertex::new(Vec3::new((i % 16) as f32, height as f32, (i / 16) as f32), NormalDirection::Posx, (0, 0), Voxel::new(VoxelType::Dirt))).collect();
+            mesh.indices = (0..len).map(|i| i * height).collect();
+            mesh
+        };
+        let read_back = |buffer: u32, start: usize, len: usize, size: usize|
+        {
+            let mut data = vec![0u8; len * size];
+            unsafe { gl::GetNamedBufferSubData(buffer, (start * size) as isize, data.len() as isize, data.as_mut_ptr() as *mut c_void); }
+            data
+        };
+        let bytes = |mesh: &Mesh<VoxelVertex>| -> (Vec<u8>, Vec<u8>)
+        {
+            let vertices = unsafe { std::slice::from_raw_parts(mesh.vertices.as_ptr() as *const u8, mesh.get_vertices_size_bytes()) };
+            let indices = unsafe { std::slice::from_raw_parts(mesh.indices.as_ptr() as *const u8, mesh.get_indices_size_bytes()) };
+            (vertices.to_vec(), indices.to_vec())
+        };
+
+        // three meshes filling the first three quarters of the pool, freeing the first one lets the last one move in its place
+        let mut pool = VertexPoolAllocator::<VoxelVertex>::new(64, 64);
+        let mut meshes: Vec<Mesh<VoxelVertex>> = (1..4).map(|height| create_mesh(height, 16)).collect();
+        for mesh in meshes.iter_mut()
+        {
+            pool.alloc(mesh);
+        }
+        let tokens: Vec<u32> = meshes[1..].iter().map(|mesh| mesh.alloc_token.as_ref().unwrap().index).collect();
+        pool.dealloc(meshes[0].release_token().unwrap());
+
+        let mut frames_until_reuse = 0;
+        loop
+        {
+            pool.update();
+            pool.prepare_frame(tokens.iter().copied());
+            pool.end_frame();
+
+            let stats = pool.get_stats();
+            assert_eq!(stats.num_moves, 1);
+            if stats.vertex_bytes_used == 32 * size_of::<VoxelVertex>() && stats.index_bytes_used == 32 * size_of::<u32>()
+            {
+                break;
+            }
+            frames_until_reuse += 1;
+            assert!(frames_until_reuse < 10);
+        }
+        // the copy of frame 0 is fenced at its end, prepare_frame() waits for that fence in frame 3, the update of frame 4 reclaims the range
+        assert_eq!(frames_until_reuse, 4);
+
+        // the moved-from range only fits a mesh of half the pool along with the free tail
+        let mut reused = create_mesh(4, 32);
+        pool.alloc(&mut reused);
+        assert_eq!(pool.get_stats().num_growths, 0);
+        unsafe { gl::Finish(); }
+
+        let (vbo, ebo) = pool.get_buffers();
+        let vertex_size = size_of::<VoxelVertex>();
+        for (mesh, start) in [(&meshes[2], 0), (&meshes[1], 16), (&reused, 32)]
+        {
+            let (vertices, indices) = bytes(mesh);
+            assert_eq!(read_back(vbo, start, mesh.vertices.len(), vertex_size), vertices);
+            assert_eq!(read_back(ebo, start, mesh.indices.len(), size_of::<u32>()), indices);
+        }
+    }
+}
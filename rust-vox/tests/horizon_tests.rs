@@ -0,0 +1,48 @@
+#[cfg(test)]
+mod horizon
+{
+    use std::{sync::Arc, thread, time::{Duration, Instant}};
+    use engine::engine::{horizon::{Horizon, TILE_SIZE}, terrain::{create_generator, scan_surface, TerrainGenerator, PerlinGenerator, DEFAULT_SEED}};
+    use glam::Vec3;
+
+    #[test]
+    fn surface_matches_the_voxels()
+    {
+        let generator = PerlinGenerator::new(DEFAULT_SEED);
+        for x in (-200..200).step_by(7)
+        {
+            for z in (-200..200).step_by(11)
+            {
+                assert_eq!(generator.get_surface(x, z), scan_surface(&generator, x, z));
+            }
+        }
+    }
+
+    #[test]
+    fn tiles_follow_the_camera()
+    {
+        let generator: Arc<dyn TerrainGenerator> = Arc::from(create_generator("flat", DEFAULT_SEED).unwrap());
+        let mut horizon = Horizon::new(32); // two tiles around the camera's
+
+        let update_until_loaded = |horizon: &mut Horizon, camera_pos: Vec3|
+        {
+            let start = Instant::now();
+            horizon.update(&generator, camera_pos);
+            while !horizon.is_loaded()
+            {
+                assert!(start.elapsed() < Duration::from_secs(60));
+                thread::sleep(Duration::from_millis(1));
+                horizon.update(&generator, camera_pos);
+            }
+        };
+
+        update_until_loaded(&mut horizon, Vec3::new(10.0, 60.0, 10.0));
+        assert_eq!(horizon.get_num_tiles(), 25);
+        assert!(horizon.get_tiles().all(|tile| tile.aabb.min.y == 20.0 && tile.aabb.max.y == 20.0));
+
+        // the tiles a step away are kept, those further are dropped
+        update_until_loaded(&mut horizon, Vec3::new(10.0 + 2.0 * TILE_SIZE as f32, 60.0, 10.0));
+        assert_eq!(horizon.get_num_tiles(), 30);
+        assert_eq!(horizon.take_removed_tiles().len(), 5);
+    }
+}
@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod input_map
+{
+    use engine::input::{InputMap, Action, Binding};
+    use sdl2::controller::{Axis, Button};
+    use sdl2::{event::Event, keyboard::{Keycode, Mod}, mouse::{MouseButton, MouseState}};
+
+    fn key_down(keycode: Keycode) -> Event
+    {
+        Event::KeyDown { timestamp: 0, window_id: 0, keycode: Some(keycode), scancode: None, keymod: Mod::NOMOD, repeat: false }
+    }
+
+    fn mouse_down(mouse_btn: MouseButton) -> Event
+    {
+        Event::MouseButtonDown { timestamp: 0, window_id: 0, which: 0, mouse_btn, clicks: 1, x: 0, y: 0 }
+    }
+
+    #[test]
+    fn default_bindings()
+    {
+        let input_map = InputMap::default();
+
+        assert_eq!(input_map.map_event(&key_down(Keycode::W)), Some(Action::MoveForward));
+        assert_eq!(input_map.map_event(&key_down(Keycode::Escape)), Some(Action::Quit));
+        assert_eq!(input_map.map_event(&mouse_down(MouseButton::Left)), Some(Action::PlaceBlock));
+        assert_eq!(input_map.map_event(&key_down(Keycode::Q)), None);
+
+        // every action is bound to something
+        for action in Action::ALL
+        {
+            assert!(!input_map.get_bindings(action).is_empty());
+        }
+    }
+
+    #[test]
+    fn rebind_removes_conflicts()
+    {
+        let mut input_map = InputMap::default();
+
+        // W was bound to MoveForward, it must now only trigger Quit
+        input_map.rebind(Action::Quit, Binding::Key(Keycode::W));
+
+        assert_eq!(input_map.map_event(&key_down(Keycode::W)), Some(Action::Quit));
+        assert_eq!(input_map.map_event(&key_down(Keycode::Escape)), None);
+        assert!(!input_map.get_bindings(Action::MoveForward).contains(&Binding::Key(Keycode::W)));
+
+        // the controller binding of an action is kept when a key is bound to it
+        assert_eq!(input_map.get_bindings(Action::Quit), &[Binding::Key(Keycode::W)]);
+        assert!(input_map.get_bindings(Action::MoveForward).contains(&Binding::ControllerButton(Button::DPadUp)));
+    }
+
+    #[test]
+    fn capture_rebind()
+    {
+        let mut input_map = InputMap::default();
+
+        // nothing pending, the event is not consumed
+        assert!(!input_map.capture_rebind(Binding::Key(Keycode::Q)));
+
+        input_map.start_rebind(Action::DestroyBlock);
+        assert_eq!(input_map.get_pending_rebind(), Some(Action::DestroyBlock));
+
+        // events that do not press anything don't produce a binding
+        let motion = Event::MouseMotion { timestamp: 0, window_id: 0, which: 0, mousestate: MouseState::from_sdl_state(0), x: 0, y: 0, xrel: 1, yrel: 1 };
+        assert_eq!(Binding::from_event(&motion), None);
+
+        assert!(input_map.capture_rebind(Binding::from_event(&key_down(Keycode::Q)).unwrap()));
+        assert_eq!(input_map.get_pending_rebind(), None);
+        assert_eq!(input_map.map_event(&key_down(Keycode::Q)), Some(Action::DestroyBlock));
+        assert_eq!(input_map.map_event(&mouse_down(MouseButton::Right)), None);
+        assert!(input_map.get_bindings(Action::DestroyBlock).contains(&Binding::ControllerTrigger(Axis::TriggerLeft)));
+    }
+
+    #[test]
+    fn cancel_rebind()
+    {
+        let mut input_map = InputMap::default();
+
+        // Escape cancels the rebind without binding itself, it still quits
+        input_map.start_rebind(Action::DestroyBlock);
+        assert!(input_map.capture_rebind(Binding::Key(Keycode::Escape)));
+        assert_eq!(input_map.get_pending_rebind(), None);
+        assert_eq!(input_map.map_event(&key_down(Keycode::Escape)), Some(Action::Quit));
+        assert_eq!(input_map.map_event(&mouse_down(MouseButton::Right)), Some(Action::DestroyBlock));
+
+        // cancelled from the UI, the next input keeps its action
+        input_map.start_rebind(Action::DestroyBlock);
+        input_map.cancel_rebind();
+        assert!(!input_map.capture_rebind(Binding::Mouse(MouseButton::Left)));
+        assert_eq!(input_map.map_event(&mouse_down(MouseButton::Left)), Some(Action::PlaceBlock));
+        assert!(input_map.get_bindings(Action::DestroyBlock).contains(&Binding::Mouse(MouseButton::Right)));
+    }
+}
@@ -0,0 +1,70 @@
+#[cfg(test)]
+mod jobs
+{
+    use std::{sync::mpsc::channel, time::Duration};
+    use engine::jobs::JobSystem;
+    use glam::IVec2;
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    // occupies the only worker until the returned sender is dropped
+    fn block_worker(jobs: &JobSystem) -> std::sync::mpsc::Sender<()>
+    {
+        let (gate, wait) = channel::<()>();
+        let (completion, _) = channel();
+        jobs.submit(0.0, IVec2::ZERO, &completion, move || { let _ = wait.recv(); Some(()) }).unwrap();
+
+        // let the worker pick it up before anything else is queued
+        while jobs.get_num_queued() > 0
+        {
+            std::thread::yield_now();
+        }
+        gate
+    }
+
+    #[test]
+    fn priority_order_and_cancel()
+    {
+        let jobs = JobSystem::new(1, 16);
+        let gate = block_worker(&jobs);
+
+        let (sender, receiver) = channel();
+        for (priority, x) in [(3.0, 3), (1.0, 1), (2.0, 2), (0.5, 5)]
+        {
+            let handle = jobs.submit(priority, IVec2::new(x, 0), &sender, move || Some(x)).unwrap();
+            if x == 5
+            {
+                handle.cancel();
+            }
+        }
+
+        // reverse the order
+        jobs.reprioritize(|pos| -pos.x as f32);
+        drop(gate);
+
+        let results: Vec<i32> = (0..3).map(|_| receiver.recv_timeout(TIMEOUT).unwrap()).collect();
+        assert_eq!(results, vec![3, 2, 1]);
+        assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err()); // the cancelled job never ran
+    }
+
+    #[test]
+    fn bounded_queue_and_shutdown()
+    {
+        let jobs = JobSystem::new(1, 2);
+        let gate = block_worker(&jobs);
+
+        let (sender, receiver) = channel();
+        assert!(jobs.submit(1.0, IVec2::ZERO, &sender, || Some(1)).is_some());
+        let cancelled = jobs.submit(1.0, IVec2::ZERO, &sender, || Some(2)).unwrap();
+        assert!(jobs.submit(1.0, IVec2::ZERO, &sender, || Some(3)).is_none()); // full
+
+        // cancelled jobs make room
+        cancelled.cancel();
+        assert!(jobs.submit(1.0, IVec2::ZERO, &sender, || Some(4)).is_some());
+
+        // dropping abandons the queued jobs, only the running one is waited for
+        std::thread::spawn(move || { std::thread::sleep(Duration::from_millis(100)); drop(gate); });
+        drop(jobs);
+        assert!(receiver.try_recv().is_err());
+    }
+}
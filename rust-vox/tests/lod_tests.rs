@@ -0,0 +1,99 @@
+#[cfg(test)]
+mod lod
+{
+    use std::{time::Duration, sync::Arc};
+    use engine::{world::World, camera::Camera, settings::Settings, engine::{chunk::{Chunk, MOORE_NEIGHBORHOOD_OFFSET}, chunk_manager::{ChunkArena, ChunkManageUnit},
+        terrain::{create_generator, DEFAULT_SEED}, renderer::allocators::headless_allocator::HeadlessAllocator,
+        geometry::{chunk_mesh::ChunkMesh, meshing::{chunk_mesher::MesherType, lod_mesher::ChunkLod, voxel_fetcher::FetcherFactory}}}};
+    use glam::{Vec3, IVec2};
+
+    const LOAD_TIMEOUT: Duration = Duration::from_secs(60);
+
+    // 5x5 visible chunks around chunk (0,0), a new level of detail each chunk away from it
+    fn create_world() -> World<HeadlessAllocator>
+    {
+        let settings = Settings{no_update_distance: 2, visible_distance: 5, loaded_distance: 7, lod_distances: [0, 1, 2], ..Settings::default()};
+        let camera = Camera::new(45f32.to_radians(), 1.0, 0.1, 500.0, Vec3::new(10.0, 60.0, 10.0), Vec3::X, Vec3::Y, 1.0);
+
+        World::new_headless(camera, &settings, create_generator("perlin", DEFAULT_SEED).unwrap())
+    }
+
+    #[test]
+    fn rings_and_transitions()
+    {
+        let mut world = create_world();
+        assert!(world.update_until_loaded(LOAD_TIMEOUT));
+
+        let level = |world: &World<HeadlessAllocator>, chunk_pos: IVec2| world.chunk_manager.get_mesh_lod(chunk_pos).unwrap().level;
+        assert_eq!(level(&world, IVec2::new(0, 0)), 0);
+        assert_eq!(level(&world, IVec2::new(1, -1)), 1);
+        assert_eq!(level(&world, IVec2::new(-2, 1)), 2);
+
+        // the seams are on the sides facing another level
+        let lod = world.chunk_manager.get_mesh_lod(IVec2::new(1, 0)).unwrap();
+        assert_eq!(lod.seams, [None, Some(2), None, Some(0)]);
+        assert_eq!(world.chunk_manager.get_mesh_lod(IVec2::ZERO), Some(ChunkLod::default()));
+        assert!(world.chunk_manager.allocator.get_allocation(world.chunk_manager.get_mesh_alloc_index(IVec2::new(2, 2)).unwrap()).unwrap().num_indices > 0);
+
+        // the meshes are replaced in place
+        world.chunk_manager.set_lod_distances([8, 8, 8]);
+        world.update();
+        assert!(world.update_until_loaded(LOAD_TIMEOUT));
+        for x in -2..=2
+        {
+            for z in -2..=2
+            {
+                assert_eq!(level(&world, IVec2::new(x, z)), 0);
+            }
+        }
+        assert_eq!(world.chunk_manager.allocator.get_num_allocations(), 25);
+    }
+
+    #[test]
+    fn seam_walls_are_built_by_one_side()
+    {
+        // the chunks (0,0) and (1,0) and their Moore neighbors
+        let generator = create_generator("perlin", DEFAULT_SEED).unwrap();
+        let arena = Arc::new(ChunkArena::new(12));
+        let mut indices = std::collections::HashMap::new();
+        for x in -1..=2
+        {
+            for z in -1..=1
+            {
+                let mut unit = ChunkManageUnit::default();
+                unit.chunk = Some(Chunk::new(IVec2::new(x, z), generator.as_ref()));
+                indices.insert(IVec2::new(x, z), arena.try_insert(unit).ok().unwrap());
+            }
+        }
+
+        // the walls of the chunk on the plane x = plane of the chunk, as (y, z) rectangles
+        let walls = |chunk_pos: IVec2, lod: ChunkLod, plane: i32|
+        {
+            let neighborhood = std::iter::once(IVec2::ZERO).chain(MOORE_NEIGHBORHOOD_OFFSET).map(|offset| indices[&(chunk_pos + offset)]).collect::<Vec<_>>();
+            let factory = FetcherFactory::new(neighborhood.try_into().unwrap(), Arc::clone(&arena));
+            let chunk_mesh = ChunkMesh::with_lod(factory.get_fetcher().unwrap(), lod, MesherType::Greedy);
+
+            chunk_mesh.mesh.vertices.chunks(4).filter(|quad| quad.iter().all(|vertex| vertex.get_position().x == plane)).map(|quad|
+            {
+                let min = quad.iter().map(|vertex| vertex.get_position()).reduce(|a, b| a.min(b)).unwrap();
+                let max = quad.iter().map(|vertex| vertex.get_position()).reduce(|a, b| a.max(b)).unwrap();
+                (min.y, max.y, min.z, max.z)
+            }).collect::<Vec<_>>()
+        };
+
+        // the chunks at two levels on both sides of the seam, in both orders, the full resolution side builds no walls and keeps
+        // the faces of its mesher
+        for (near_level, far_level) in [(1, 2), (2, 1)]
+        {
+            let near = walls(IVec2::ZERO, ChunkLod{level: near_level, seams: [None, Some(far_level), None, None]}, 20);
+            let far = walls(IVec2::new(1, 0), ChunkLod{level: far_level, seams: [None, None, None, Some(near_level)]}, 0);
+            assert!(!near.is_empty() || !far.is_empty());
+
+            let overlap = |a: &(i32, i32, i32, i32), b: &(i32, i32, i32, i32)| a.0 < b.1 && b.0 < a.1 && a.2 < b.3 && b.2 < a.3;
+            for wall in near.iter()
+            {
+                assert!(!far.iter().any(|other| overlap(wall, other)), "{:?} is built by both sides", wall);
+            }
+        }
+    }
+}
@@ -0,0 +1,233 @@
+#[cfg(test)]
+mod mesher
+{
+    use std::{time::{Duration, Instant}, sync::Arc, collections::HashMap};
+    use engine::{world::World, generational_vec::GenerationIndex, camera::{Camera, BoundingBox}, settings::Settings, engine::{chunk::{Chunk, NEIGHBOR_OFFSET, MOORE_NEIGHBORHOOD_OFFSET}, chunk_manager::{ChunkManager, ChunkArena, ChunkManageUnit},
+        terrain::{create_generator, DEFAULT_SEED}, renderer::allocators::headless_allocator::HeadlessAllocator,
+        geometry::{mesh::Mesh, chunk_mesh::ChunkMesh, voxel::{Voxel, VoxelType}, voxel_vertex::VoxelVertex, meshing::{chunk_mesher::{ChunkMesher, MesherType}, greedy_mesher::GreedyMesher,
+            binary_mesher::{BinaryGreedyMesher, PaddedChunk}, surface_nets_mesher::SurfaceNetsMesher, voxel_fetcher::FetcherFactory, lod_mesher::ChunkLod}}}};
+    use glam::{Vec3, IVec2, IVec3};
+
+    #[test]
+    fn binary_mesher_matches_the_greedy_mesher()
+    {
+        let generator = create_generator("perlin", DEFAULT_SEED).unwrap();
+        let mut chunks: Vec<Chunk> = [IVec2::ZERO].into_iter().chain(NEIGHBOR_OFFSET).map(|pos| Chunk::new(pos, generator.as_ref())).collect();
+
+        // transparent voxels next to each other and on the borders, a tower up to the top of the chunk
+        for (pos, voxel_type) in [(IVec3::new(0, 30, 0), VoxelType::Glass), (IVec3::new(1, 30, 0), VoxelType::Water), (IVec3::new(19, 35, 7), VoxelType::Glass),
+            (IVec3::new(4, 99, 19), VoxelType::Sand), (IVec3::new(5, 99, 19), VoxelType::Sand), (IVec3::new(5, 0, 5), VoxelType::Air)]
+        {
+            chunks[0].set_voxel(pos, Voxel::new(voxel_type));
+        }
+        chunks[2].set_voxel(IVec3::new(0, 35, 7), Voxel::new(VoxelType::Water));
+        for y in 0..100
+        {
+            chunks[1].set_voxel(IVec3::new(3, y, 0), Voxel::new(VoxelType::Glass));
+        }
+
+        let get_voxel = |pos: IVec3|
+        {
+            let (chunk_pos, voxel_pos) = ChunkManager::get_local_voxel_coord(pos);
+            let chunk = chunks.iter().find(|chunk| chunk.pos_chunk_space() == chunk_pos).unwrap();
+            chunk.get_voxel(voxel_pos).unwrap_or(Voxel::new(VoxelType::Air))
+        };
+        let mut expected = Mesh::<VoxelVertex>::default();
+        let mut expected_faces = Vec::new();
+        GreedyMesher::mesh_grid(get_voxel, [20, 100, 20], 1, IVec3::ZERO, &mut expected, &mut expected_faces);
+
+        let neighbors = [1, 2, 3, 4].map(|index| Some(&chunks[index]));
+        let mut mesh = Mesh::<VoxelVertex>::default();
+        let mut faces = Vec::new();
+        BinaryGreedyMesher::mesh_padded(&PaddedChunk::new(&chunks[0], neighbors), IVec3::ZERO, &mut mesh, &mut faces);
+
+        assert!(!mesh.vertices.is_empty() && !faces.is_empty());
+        assert!(mesh.vertices == expected.vertices);
+        assert_eq!(mesh.indices, expected.indices);
+        assert_eq!(format!("{:?}", faces), format!("{:?}", expected_faces));
+    }
+
+    #[test]
+    fn switch_mesher_at_runtime()
+    {
+        let settings = Settings{no_update_distance: 2, visible_distance: 5, loaded_distance: 7, lod_distances: [8, 8, 8], mesher: MesherType::Greedy, ..Settings::default()};
+        let camera = Camera::new(45f32.to_radians(), 1.0, 0.1, 500.0, Vec3::new(10.0, 60.0, 10.0), Vec3::X, Vec3::Y, 1.0);
+        let mut world = World::new_headless(camera, &settings, create_generator("perlin", DEFAULT_SEED).unwrap());
+        assert!(world.update_until_loaded(Duration::from_secs(60)));
+
+        let num_indices = |world: &World<HeadlessAllocator>| world.chunk_manager.allocator.get_allocation(world.chunk_manager.get_mesh_alloc_index(IVec2::ZERO).unwrap()).unwrap().num_indices;
+        let greedy_indices = num_indices(&world);
+
+        // the chunks are meshed again with one face per voxel side
+        world.chunk_manager.set_mesher(MesherType::Culling);
+        assert!(world.update_until_loaded(Duration::from_secs(60)));
+        assert!(num_indices(&world) > greedy_indices);
+
+        // the chunks are compared on the workers
+        world.chunk_manager.compare_meshers();
+        let start = Instant::now();
+        while world.chunk_manager.is_comparing_meshers()
+        {
+            assert!(start.elapsed() < Duration::from_secs(60));
+            world.update();
+        }
+        let reports = world.chunk_manager.get_mesher_reports();
+        let report = |mesher: MesherType| reports.iter().find(|report| report.mesher == mesher).unwrap();
+        assert!(report(MesherType::Greedy).num_chunks > 0);
+        assert_eq!(report(MesherType::Greedy).num_vertices, report(MesherType::BinaryGreedy).num_vertices);
+        assert_eq!(report(MesherType::Greedy).num_triangles, report(MesherType::BinaryGreedy).num_triangles);
+        assert!(report(MesherType::Culling).num_triangles > report(MesherType::Greedy).num_triangles);
+    }
+
+    #[test]
+    fn switching_mesher_keeps_the_lod_meshes()
+    {
+        let settings = Settings{no_update_distance: 2, visible_distance: 5, loaded_distance: 7, lod_distances: [1, 8, 8], mesher: MesherType::Greedy, ..Settings::default()};
+        let camera = Camera::new(45f32.to_radians(), 1.0, 0.1, 500.0, Vec3::new(10.0, 60.0, 10.0), Vec3::X, Vec3::Y, 1.0);
+        let mut world = World::new_headless(camera, &settings, create_generator("perlin", DEFAULT_SEED).unwrap());
+        assert!(world.update_until_loaded(Duration::from_secs(60)));
+
+        let (near, far) = (IVec2::ZERO, IVec2::new(2, 0));
+        assert_eq!(world.chunk_manager.get_mesh_lod(near).unwrap().level, 0);
+        assert!(world.chunk_manager.get_mesh_lod(far).unwrap().level > 0);
+        let allocations = [near, far].map(|pos| world.chunk_manager.get_mesh_alloc_index(pos).unwrap());
+
+        // only the full resolution meshes use the mesher
+        world.chunk_manager.set_mesher(MesherType::Culling);
+        assert!(world.update_until_loaded(Duration::from_secs(60)));
+        assert_ne!(world.chunk_manager.get_mesh_alloc_index(near).unwrap(), allocations[0]);
+        assert_eq!(world.chunk_manager.get_mesh_alloc_index(far).unwrap(), allocations[1]);
+    }
+
+    // the chunks in the range of positions, their indices, and a factory for the fetcher of a chunk of their inside
+    fn create_arena(min: IVec2, max: IVec2) -> (Arc<ChunkArena>, HashMap<IVec2, GenerationIndex>, impl Fn(IVec2) -> FetcherFactory)
+    {
+        let generator = create_generator("perlin", DEFAULT_SEED).unwrap();
+        let arena = Arc::new(ChunkArena::new(((max - min + 1).x * (max - min + 1).y) as usize));
+        let mut indices = HashMap::new();
+        for x in min.x..=max.x
+        {
+            for z in min.y..=max.y
+            {
+                let mut unit = ChunkManageUnit::default();
+                unit.chunk = Some(Chunk::new(IVec2::new(x, z), generator.as_ref()));
+                indices.insert(IVec2::new(x, z), arena.try_insert(unit).ok().unwrap());
+            }
+        }
+
+        let (factory_arena, factory_indices) = (Arc::clone(&arena), indices.clone());
+        let get_factory = move |chunk_pos: IVec2|
+        {
+            let mut neighborhood = [factory_indices[&chunk_pos]; 9];
+            for (i, offset) in MOORE_NEIGHBORHOOD_OFFSET.into_iter().enumerate()
+            {
+                neighborhood[i + 1] = factory_indices[&(chunk_pos + offset)];
+            }
+            FetcherFactory::new(neighborhood, Arc::clone(&factory_arena))
+        };
+
+        (arena, indices, get_factory)
+    }
+
+    #[test]
+    fn surface_nets_seams_and_winding()
+    {
+        // the chunks (0,0) and (1,0) and their Moore neighbors
+        let (arena, indices, get_factory) = create_arena(IVec2::new(-1, -1), IVec2::new(2, 1));
+
+        // the vertices in the world, with their normals
+        let mesh_chunk = |chunk_pos: IVec2|
+        {
+            let factory = get_factory(chunk_pos);
+
+            let mut mesh = Mesh::<VoxelVertex>::default();
+            let mut faces = Vec::new();
+            SurfaceNetsMesher::generate_mesh(factory.get_fetcher().unwrap(), &mut mesh, &mut faces);
+
+            let origin = Vec3::new(chunk_pos.x as f32 * 20.0, 0.0, chunk_pos.y as f32 * 20.0);
+            let vertices: Vec<(Vec3, Vec3)> = mesh.vertices.iter().filter(|vertex| vertex.is_smooth()).map(|vertex| (origin + vertex.get_precise_position(), vertex.get_normal())).collect();
+            (mesh, vertices)
+        };
+        let (mesh, vertices) = mesh_chunk(IVec2::ZERO);
+        let (_, next_vertices) = mesh_chunk(IVec2::new(1, 0));
+        assert!(!vertices.is_empty());
+
+        // the last voxels of the chunk are the first voxels of the next one, they get the same vertices
+        let on_seam = |vertices: &Vec<(Vec3, Vec3)>| vertices.iter().filter(|(pos, _)| pos.x > 20.0 && pos.x < 21.0).map(|vertex| format!("{:?}", vertex)).collect::<Vec<_>>();
+        let mut seam = on_seam(&vertices);
+        let mut next_seam = on_seam(&next_vertices);
+        seam.sort();
+        next_seam.sort();
+        assert!(!seam.is_empty());
+        assert_eq!(seam, next_seam);
+
+        // the box culling the chunk covers its seam vertices
+        let chunk_aabb = arena.get_mut(indices[&IVec2::ZERO]).unwrap().chunk.as_ref().unwrap().get_aabb();
+        let aabb = ChunkMesh::new(get_factory(IVec2::ZERO).get_fetcher().unwrap(), MesherType::SurfaceNets).get_aabb(chunk_aabb);
+        assert!(vertices.iter().all(|(pos, _)| pos.cmpge(aabb.min).all() && pos.cmple(aabb.max).all()));
+        assert!(vertices.iter().any(|(pos, _)| pos.x > chunk_aabb.max.x));
+
+        // the triangles are clockwise seen from the side of their normals
+        let num_triangles = mesh.indices.len() / 3;
+        let num_facing = mesh.indices.chunks(3).filter(|triangle|
+        {
+            let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize]);
+            let face_normal = (c.get_precise_position() - a.get_precise_position()).cross(b.get_precise_position() - a.get_precise_position());
+            face_normal.dot(a.get_normal() + b.get_normal() + c.get_normal()) > 0.0
+        }).count();
+        assert!(num_facing as f32 > num_triangles as f32 * 0.95, "{} of {} triangles face their normals", num_facing, num_triangles);
+    }
+
+    #[test]
+    fn full_resolution_chunks_ignore_their_seams()
+    {
+        let (arena, indices, get_factory) = create_arena(IVec2::new(-1, -1), IVec2::new(1, 1));
+        let chunk_aabb = arena.get_mut(indices[&IVec2::ZERO]).unwrap().chunk.as_ref().unwrap().get_aabb();
+
+        // next to a chunk of the next level on +x, the chunk is meshed by the selected mesher as if it had no seam
+        let lod = ChunkLod{level: 0, seams: [None, Some(1), None, None]};
+        for mesher in [MesherType::Greedy, MesherType::BinaryGreedy, MesherType::SurfaceNets]
+        {
+            let seamed = ChunkMesh::with_lod(get_factory(IVec2::ZERO).get_fetcher().unwrap(), lod, mesher);
+            let full = ChunkMesh::new(get_factory(IVec2::ZERO).get_fetcher().unwrap(), mesher);
+
+            assert!(seamed.mesh.vertices == full.mesh.vertices);
+            assert_eq!(seamed.mesh.indices, full.mesh.indices);
+            assert_eq!(format!("{:?}", seamed.get_aabb(chunk_aabb)), format!("{:?}", full.get_aabb(chunk_aabb)));
+            assert_eq!(seamed.mesh.vertices.iter().any(|vertex| vertex.is_smooth()), mesher == MesherType::SurfaceNets);
+        }
+    }
+
+    #[test]
+    fn patched_mesh_matches_a_full_remesh()
+    {
+        let (arena, indices, get_factory) = create_arena(IVec2::new(-1, -1), IVec2::new(1, 1));
+        let factory = get_factory(IVec2::ZERO);
+        let base = ChunkMesh::new(factory.get_fetcher().unwrap(), MesherType::BinaryGreedy);
+
+        // on the borders, the bottom and the top, transparent voxels, and a voxel of the neighbor on +x
+        let edits = [(IVec3::new(0, 99, 5), VoxelType::Sand), (IVec3::new(19, 40, 19), VoxelType::Glass), (IVec3::new(7, 0, 3), VoxelType::Air),
+            (IVec3::new(5, 60, 5), VoxelType::Water), (IVec3::new(6, 60, 5), VoxelType::Water), (IVec3::new(20, 50, 4), VoxelType::Air)];
+        for (pos, voxel_type) in edits
+        {
+            let (chunk_pos, voxel_pos) = ChunkManager::get_local_voxel_coord(pos);
+            let index = indices[&chunk_pos];
+            arena.get_mut(index).unwrap().chunk.as_mut().unwrap().set_voxel(voxel_pos, Voxel::new(voxel_type));
+        }
+
+        let edited: Vec<IVec3> = edits.iter().map(|(pos, _)| *pos).collect();
+        let patched = ChunkMesh::patched(factory.get_fetcher().unwrap(), &base.mesh.vertices, &edited, MesherType::BinaryGreedy);
+        let full = ChunkMesh::new(factory.get_fetcher().unwrap(), MesherType::BinaryGreedy);
+
+        let quads = |chunk_mesh: &ChunkMesh|
+        {
+            let mut quads: Vec<String> = chunk_mesh.mesh.vertices.chunks(4).map(|quad| format!("{:?}", quad)).collect();
+            quads.sort();
+            quads
+        };
+        assert_ne!(quads(&base), quads(&full));
+        assert_eq!(quads(&patched), quads(&full));
+        assert_eq!(patched.trans_faces.len(), full.trans_faces.len());
+        assert_eq!(patched.mesh.indices.len(), full.mesh.indices.len());
+    }
+}
@@ -0,0 +1,54 @@
+#[cfg(test)]
+mod settings
+{
+    use std::fs;
+    use engine::settings::{Settings, DisplayMode};
+
+    #[test]
+    fn save_and_load()
+    {
+        let path = std::env::temp_dir().join("rust_vox_settings_save_and_load.toml");
+
+        let settings = Settings{visible_distance: 14, loaded_distance: 20, vsync: false, fog_density: 2.5, display_mode: DisplayMode::Borderless, ..Settings::default()};
+        settings.save(&path).unwrap();
+
+        let loaded = Settings::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, settings);
+    }
+
+    #[test]
+    fn partial_file_and_sanitize()
+    {
+        let path = std::env::temp_dir().join("rust_vox_settings_partial.toml");
+
+        // missing fields keep their defaults, out of range values are brought back
+        fs::write(&path, "visible_distance = 12\nloaded_distance = 4\nshadow_map_resolution = 3000\nthread_count = 0\n").unwrap();
+
+        let loaded = Settings::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.visible_distance, 12);
+        assert_eq!(loaded.loaded_distance, 14); // the loaded zone must engulf the visible zone
+        assert_eq!(loaded.shadow_map_resolution, 2048);
+        assert_eq!(loaded.thread_count, 1);
+        assert_eq!(loaded.window_width, Settings::default().window_width);
+    }
+
+    #[test]
+    fn overrides_are_not_saved()
+    {
+        let file = Settings::default();
+        let overridden = Settings{visible_distance: 20, loaded_distance: 28, vsync: false, ..file.clone()};
+
+        // the visible distance was changed again at runtime, the vsync was not
+        let current = Settings{visible_distance: 16, fog_density: 1.0, ..overridden.clone()};
+        let saved = current.without_overrides(&file, &overridden);
+
+        assert_eq!(saved.visible_distance, 16);
+        assert_eq!(saved.fog_density, 1.0);
+        assert_eq!(saved.loaded_distance, file.loaded_distance);
+        assert_eq!(saved.vsync, file.vsync);
+    }
+}
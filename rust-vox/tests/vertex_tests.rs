@@ -0,0 +1,42 @@
+#[cfg(test)]
+mod vertex
+{
+    use std::mem::size_of;
+    use engine::engine::geometry::{voxel_vertex::VoxelVertex, voxel::{Voxel, VoxelType}, meshing::chunk_mesher::NormalDirection};
+    use glam::{Vec3, IVec3};
+
+    #[test]
+    fn packed_fields_round_trip()
+    {
+        assert_eq!(size_of::<VoxelVertex>(), 8);
+
+        // the far corner of a chunk, with the longest quad
+        let vertex = VoxelVertex::new(Vec3::new(20.0, 100.0, 20.0), NormalDirection::Negz, (100, 20), Voxel::new(VoxelType::Glass));
+        assert_eq!(vertex.get_position(), IVec3::new(20, 100, 20));
+        assert_eq!(vertex.get_texture_uv(), (100, 20));
+        assert_eq!(vertex.get_normal_index(), NormalDirection::Negz as u32);
+        assert_eq!(vertex.get_texture_index(), VoxelType::Glass as u32);
+
+        let origin = VoxelVertex::new(Vec3::ZERO, NormalDirection::Posx, (0, 0), Voxel::new(VoxelType::Dirt));
+        assert_eq!(origin.get_position(), IVec3::ZERO);
+        assert_eq!(origin.get_normal_index(), 0);
+    }
+
+    #[test]
+    fn smooth_fields_round_trip()
+    {
+        let normal = Vec3::new(-0.3, 0.5, -0.8).normalize();
+        let vertex = VoxelVertex::new_smooth(Vec3::new(20.75, 99.5, 0.0625), normal, Voxel::new(VoxelType::Sand), (Voxel::new(VoxelType::Dirt), 0.3));
+        assert!(vertex.is_smooth());
+        assert_eq!(vertex.get_position(), IVec3::new(20, 99, 0));
+        assert_eq!(vertex.get_precise_position(), Vec3::new(20.75, 99.5, 0.0625));
+        assert_eq!(vertex.get_texture_index(), VoxelType::Sand as u32);
+        assert!(vertex.get_normal().distance(normal) < 0.03);
+
+        // the position rounds up to the next voxel
+        let vertex = VoxelVertex::new_smooth(Vec3::new(20.99, 0.0, 3.0), Vec3::Y, Voxel::new(VoxelType::Dirt), (Voxel::new(VoxelType::Dirt), 0.0));
+        assert_eq!(vertex.get_precise_position(), Vec3::new(21.0, 0.0, 3.0));
+        assert!(vertex.get_normal().distance(Vec3::Y) < 0.03);
+        assert!(!VoxelVertex::new(Vec3::ZERO, NormalDirection::Posx, (0, 0), Voxel::new(VoxelType::Dirt)).is_smooth());
+    }
+}
@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod visibility
+{
+    use std::time::Duration;
+    use engine::{world::World, camera::Camera, settings::Settings, engine::{chunk::Chunk, terrain::TerrainGenerator, geometry::voxel::{Voxel, VoxelType},
+        visibility::{ChunkVisibility, Face, get_reachable_chunks, get_section_pos}}};
+    use glam::{Vec3, IVec2, IVec3};
+
+    // solid ground up to the fourth section
+    struct Ground;
+
+    impl TerrainGenerator for Ground
+    {
+        fn generate(&self, voxel: &mut Voxel, _x: i32, y: i32, _z: i32)
+        {
+            voxel.set_type(if y < 60 { VoxelType::Dirt } else { VoxelType::Air });
+        }
+    }
+
+    #[test]
+    fn face_connections()
+    {
+        let mut chunk = Chunk::new(IVec2::ZERO, &Ground);
+
+        // a tunnel through the first section along x
+        for x in 0..20
+        {
+            chunk.set_voxel(IVec3::new(x, 10, 5), Voxel::new(VoxelType::Air));
+        }
+
+        let visibility = ChunkVisibility::new(&chunk);
+        let tunnel = visibility.get_section(0);
+        assert!(tunnel.is_connected(Face::NegX, Face::PosX));
+        assert!(!tunnel.is_connected(Face::NegX, Face::PosY));
+        assert!(!tunnel.is_connected(Face::NegZ, Face::PosZ));
+
+        assert!(!visibility.get_section(1).is_connected(Face::NegX, Face::PosX));
+        assert!(Face::ALL.into_iter().all(|face| visibility.get_section(4).is_connected(face, Face::PosY)));
+    }
+
+    #[test]
+    fn underground_camera_sees_its_neighbors_only()
+    {
+        let visibility = ChunkVisibility::new(&Chunk::new(IVec2::ZERO, &Ground));
+        let get_visibility = |_: IVec2| Some(visibility);
+
+        let underground = get_reachable_chunks(get_section_pos(IVec2::ZERO, 10), 3, get_visibility);
+        assert_eq!(underground.len(), 5);
+        assert!(underground.contains(&IVec2::new(1, 0)) && !underground.contains(&IVec2::new(2, 0)));
+
+        // over the ground every chunk of the zone is seen
+        let surface = get_reachable_chunks(get_section_pos(IVec2::ZERO, 80), 3, get_visibility);
+        assert_eq!(surface.len(), 49);
+    }
+
+    #[test]
+    fn reachable_chunks_stay_in_the_visible_zone()
+    {
+        // 5x5 visible chunks and 7x7 loaded ones, over the ground
+        let settings = Settings{no_update_distance: 2, visible_distance: 5, loaded_distance: 7, lod_distances: [8, 8, 8], ..Settings::default()};
+        let camera = Camera::new(45f32.to_radians(), 1.0, 0.1, 500.0, Vec3::new(10.0, 80.0, 10.0), Vec3::X, Vec3::Y, 1.0);
+        let mut world = World::new_headless(camera, &settings, Box::new(Ground));
+        assert!(world.update_until_loaded(Duration::from_secs(60)));
+
+        let reachable = world.chunk_manager.get_reachable_chunks(Vec3::new(10.0, 80.0, 10.0));
+        assert_eq!(reachable.len(), 25);
+        assert!(reachable.iter().all(|chunk_pos| chunk_pos.abs().max_element() <= 2));
+    }
+}
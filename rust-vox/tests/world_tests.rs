@@ -0,0 +1,254 @@
+#[cfg(test)]
+mod world
+{
+    use std::{time::Duration, collections::HashSet, fs, io};
+    use engine::{world::{World, WorldInfo}, camera::Camera, engine::chunk_manager::ChunkManager, settings::Settings, engine::{terrain::{create_generator, DEFAULT_SEED}, chunk::{CHUNK_SIZE_X, CHUNK_SIZE_Y, CHUNK_SIZE_Z},
+        geometry::{voxel::{Voxel, VoxelType}, meshing::chunk_mesher::MesherType}, renderer::allocators::headless_allocator::HeadlessAllocator}};
+    use glam::{Vec3, IVec2, IVec3};
+
+    const LOAD_TIMEOUT: Duration = Duration::from_secs(60);
+
+    // 5x5 visible chunks around chunk (0,0)
+    fn create_world() -> World<HeadlessAllocator>
+    {
+        let settings = Settings{no_update_distance: 2, visible_distance: 5, loaded_distance: 7, ..Settings::default()};
+        let camera = Camera::new(45f32.to_radians(), 1.0, 0.1, 500.0, Vec3::new(10.0, 60.0, 10.0), Vec3::X, Vec3::Y, 1.0);
+
+        World::new_headless(camera, &settings, create_generator("flat", DEFAULT_SEED).unwrap())
+    }
+
+    #[test]
+    fn load_headless()
+    {
+        let mut world = create_world();
+
+        assert!(world.update_until_loaded(LOAD_TIMEOUT));
+        assert_eq!(world.chunk_manager.get_num_chunks_to_render(), 25);
+        assert_eq!(world.chunk_manager.allocator.get_num_allocations(), 25);
+
+        for x in -2..=2
+        {
+            for z in -2..=2
+            {
+                let index = world.chunk_manager.get_mesh_alloc_index(IVec2::new(x, z)).unwrap();
+                assert!(world.chunk_manager.allocator.get_allocation(index).unwrap().num_indices > 0);
+            }
+        }
+    }
+
+    #[test]
+    fn remove_border_voxel_remeshes_neighbor()
+    {
+        let mut world = create_world();
+        assert!(world.update_until_loaded(LOAD_TIMEOUT));
+
+        // top voxel of the column on the +x border of chunk (0,0)
+        let x = CHUNK_SIZE_X as i32 - 1;
+        let y = (0..CHUNK_SIZE_Y as i32).rev().find(|y| world.chunk_manager.get_voxel(IVec3::new(x, *y, 10)).unwrap().is_filled()).unwrap();
+        let pos = IVec3::new(x, y, 10);
+
+        let chunks = [IVec2::new(0, 0), IVec2::new(1, 0), IVec2::new(0, 1)];
+        let before = chunks.map(|chunk| world.chunk_manager.get_mesh_alloc_index(chunk).unwrap());
+
+        world.chunk_manager.remove_voxel(pos);
+        assert!(world.update_until_loaded(LOAD_TIMEOUT));
+
+        let after = chunks.map(|chunk| world.chunk_manager.get_mesh_alloc_index(chunk).unwrap());
+
+        assert_eq!(world.chunk_manager.get_voxel(pos).unwrap().voxel_type as usize, VoxelType::Air as usize);
+        assert_ne!(before[0], after[0]);
+        assert_ne!(before[1], after[1]); // the neighbor sees the new face
+        assert_eq!(before[2], after[2]);
+        assert_eq!(world.chunk_manager.allocator.get_num_allocations(), 25);
+    }
+
+    #[test]
+    fn edits_keep_the_old_mesh_until_remeshed()
+    {
+        let mut world = create_world();
+        assert!(world.update_until_loaded(LOAD_TIMEOUT));
+
+        // a few top voxels inside chunk (0,0), edited in the same frame
+        let chunk = IVec2::new(0, 0);
+        let positions: Vec<IVec3> = (5..8).map(|x|
+        {
+            let y = (0..CHUNK_SIZE_Y as i32).rev().find(|y| world.chunk_manager.get_voxel(IVec3::new(x, *y, 10)).unwrap().is_filled()).unwrap();
+            IVec3::new(x, y, 10)
+        }).collect();
+
+        let before = world.chunk_manager.get_mesh_alloc_index(chunk).unwrap();
+        for pos in positions.iter()
+        {
+            world.chunk_manager.remove_voxel(*pos);
+        }
+
+        // the voxels are set on the update, the mesh comes from a worker later
+        assert!(positions.iter().all(|pos| world.chunk_manager.get_voxel(*pos).unwrap().is_filled()));
+        world.update();
+        assert!(positions.iter().all(|pos| !world.chunk_manager.get_voxel(*pos).unwrap().is_filled()));
+        assert_eq!(world.chunk_manager.get_mesh_alloc_index(chunk), Some(before));
+
+        assert!(world.update_until_loaded(LOAD_TIMEOUT));
+        assert_ne!(world.chunk_manager.get_mesh_alloc_index(chunk), Some(before));
+        assert_eq!(world.chunk_manager.allocator.get_num_allocations(), 25);
+    }
+
+    #[test]
+    fn dependent_chunks()
+    {
+        let chunks = |x: i32, z: i32| ChunkManager::get_dependent_chunks(IVec3::new(x, 50, z)).into_iter().collect::<HashSet<_>>();
+        let set = |chunks: &[(i32, i32)]| chunks.iter().map(|(x, z)| IVec2::new(*x, *z)).collect::<HashSet<_>>();
+        let (max_x, max_z) = (CHUNK_SIZE_X as i32 - 1, CHUNK_SIZE_Z as i32 - 1);
+
+        assert_eq!(chunks(10, 10), set(&[(0, 0)]));
+        assert_eq!(chunks(max_x, 10), set(&[(0, 0), (1, 0)]));
+        assert_eq!(chunks(10, 0), set(&[(0, 0), (0, -1)]));
+        assert_eq!(chunks(1, 10), set(&[(0, 0), (-1, 0)])); // the Surface Nets of the -x neighbor read two layers past its border
+        assert_eq!(chunks(max_x, max_z), set(&[(0, 0), (1, 0), (0, 1), (1, 1)]));
+        assert_eq!(chunks(-1, -1), set(&[(-1, -1), (0, -1), (-1, 0), (0, 0)]));
+    }
+
+    #[test]
+    fn border_and_corner_edits_remesh_the_neighbors()
+    {
+        let mut world = create_world();
+        world.chunk_manager.set_mesher(MesherType::Greedy);
+        assert!(world.update_until_loaded(LOAD_TIMEOUT));
+
+        let chunks: Vec<IVec2> = (-2..=2).flat_map(|x| (-2..=2).map(move |z| IVec2::new(x, z))).collect();
+        let top = |world: &World<HeadlessAllocator>, x: i32, z: i32|
+        {
+            let y = (0..CHUNK_SIZE_Y as i32).rev().find(|y| world.chunk_manager.get_voxel(IVec3::new(x, *y, z)).unwrap().is_filled()).unwrap();
+            IVec3::new(x, y, z)
+        };
+        let (max_x, max_z) = (CHUNK_SIZE_X as i32 - 1, CHUNK_SIZE_Z as i32 - 1);
+        let (air, glass) = (Voxel::new(VoxelType::Air), Voxel::new(VoxelType::Glass));
+
+        // the borders and corners of chunk (0,0), then a bulk edit around the corner of the world origin
+        let mut cases: Vec<Vec<(IVec3, Voxel)>> = [(0, 10), (max_x, 10), (10, 0), (10, max_z)].iter().map(|(x, z)| vec![(top(&world, *x, *z), air)]).collect();
+        cases.extend([(0, 0), (max_x, 0), (0, max_z), (max_x, max_z)].iter().map(|(x, z)| vec![(top(&world, *x, *z) + IVec3::Y, glass)]));
+        cases.push((-1..=1).flat_map(|x| (-1..=1).map(move |z| (x, z))).map(|(x, z)| (top(&world, x, z), air)).collect());
+
+        let indices = |world: &World<HeadlessAllocator>| chunks.iter().map(|chunk| world.chunk_manager.get_mesh_alloc_index(*chunk).unwrap()).collect::<Vec<_>>();
+        let num_indices = |world: &World<HeadlessAllocator>| indices(world).iter().map(|index| world.chunk_manager.allocator.get_allocation(*index).unwrap().num_indices).collect::<Vec<_>>();
+
+        for edits in cases
+        {
+            let before = indices(&world);
+            let expected: HashSet<IVec2> = edits.iter().flat_map(|(pos, _)| ChunkManager::get_dependent_chunks(*pos)).collect();
+
+            world.chunk_manager.set_voxels(edits.iter().copied());
+            assert!(world.update_until_loaded(LOAD_TIMEOUT));
+
+            // only the chunks reading the voxels are meshed again
+            for ((chunk, before), after) in chunks.iter().zip(before).zip(indices(&world))
+            {
+                assert_eq!(before != after, expected.contains(chunk), "chunk {} after the edits {:?}", chunk, edits.iter().map(|(pos, _)| *pos).collect::<Vec<_>>());
+            }
+
+            // and none of them is stale, the other greedy mesher builds the same quads from scratch
+            let patched = num_indices(&world);
+            let mesher = if world.chunk_manager.get_mesher() == MesherType::Greedy { MesherType::BinaryGreedy } else { MesherType::Greedy };
+            world.chunk_manager.set_mesher(mesher);
+            assert!(world.update_until_loaded(LOAD_TIMEOUT));
+            assert_eq!(patched, num_indices(&world));
+        }
+
+        assert_eq!(world.chunk_manager.allocator.get_num_allocations(), 25);
+    }
+
+    #[test]
+    fn writes_wait_for_the_generation_of_their_chunk()
+    {
+        let mut world = create_world();
+        let mut control = create_world();
+        assert!(world.update_until_loaded(LOAD_TIMEOUT));
+        assert!(control.update_until_loaded(LOAD_TIMEOUT));
+
+        // on the +x border of chunk (5,0), far out of the loaded zone
+        let height = (0..CHUNK_SIZE_Y as i32).rev().find(|y| world.chunk_manager.get_voxel(IVec3::new(10, *y, 10)).unwrap().is_filled()).unwrap();
+        let pos = IVec3::new(5 * CHUNK_SIZE_X as i32 + CHUNK_SIZE_X as i32 - 1, height + 1, 10);
+        assert!(world.chunk_manager.get_voxel(pos).is_none());
+        world.chunk_manager.set_voxels([(pos, Voxel::new(VoxelType::Glass))]);
+        assert!(world.update_until_loaded(LOAD_TIMEOUT));
+
+        // then the chunk is generated, with the voxel over its terrain
+        for world in [&mut world, &mut control]
+        {
+            world.camera.set_position(Vec3::new(5.5 * CHUNK_SIZE_X as f32, 60.0, 10.0));
+            assert!(world.update_until_loaded(LOAD_TIMEOUT));
+        }
+        assert_eq!(world.chunk_manager.get_voxel(pos).unwrap().voxel_type as usize, VoxelType::Glass as usize);
+
+        // the chunk and its +x neighbor see the voxel, the other chunks don't
+        let num_indices = |world: &World<HeadlessAllocator>, chunk: IVec2|
+        {
+            let index = world.chunk_manager.get_mesh_alloc_index(chunk).unwrap();
+            world.chunk_manager.allocator.get_allocation(index).unwrap().num_indices
+        };
+        for x in 4..=6
+        {
+            let chunk = IVec2::new(x, 0);
+            assert_eq!(num_indices(&world, chunk) != num_indices(&control, chunk), x != 4, "chunk {}", chunk);
+        }
+    }
+
+    #[test]
+    fn worlds_have_their_own_generator()
+    {
+        let settings = Settings{no_update_distance: 2, visible_distance: 3, loaded_distance: 5, ..Settings::default()};
+        let camera = || Camera::new(45f32.to_radians(), 1.0, 0.1, 500.0, Vec3::new(10.0, 60.0, 10.0), Vec3::X, Vec3::Y, 1.0);
+
+        let mut flat = World::new_headless(camera(), &settings, create_generator("flat", DEFAULT_SEED).unwrap());
+        let mut perlin = World::new_headless(camera(), &settings, create_generator("perlin", DEFAULT_SEED).unwrap());
+        assert!(flat.update_until_loaded(LOAD_TIMEOUT));
+        assert!(perlin.update_until_loaded(LOAD_TIMEOUT));
+
+        let heights = |world: &World<HeadlessAllocator>| (0..CHUNK_SIZE_X as i32).map(|x|
+            (0..CHUNK_SIZE_Y as i32).rev().find(|y| world.chunk_manager.get_voxel(IVec3::new(x, *y, 10)).unwrap().is_filled())).collect::<Vec<_>>();
+
+        assert_ne!(heights(&flat), heights(&perlin));
+
+        // swapping the generator regenerates the loaded chunks
+        perlin.set_generator(create_generator("flat", DEFAULT_SEED).unwrap());
+        assert!(perlin.update_until_loaded(LOAD_TIMEOUT));
+
+        assert_eq!(heights(&flat), heights(&perlin));
+        assert_eq!(perlin.chunk_manager.allocator.get_num_allocations(), 9);
+    }
+
+    #[test]
+    fn streaming_order()
+    {
+        // looking along +x
+        let camera = Camera::new(45f32.to_radians(), 1.0, 0.1, 500.0, Vec3::new(10.0, 60.0, 10.0), Vec3::X, Vec3::Y, 1.0);
+        let score = |velocity: Vec3, chunk_pos: IVec2| ChunkManager::get_streaming_score(&camera, velocity, chunk_pos);
+
+        // in view first
+        assert!(score(Vec3::ZERO, IVec2::new(3, 0)) < score(Vec3::ZERO, IVec2::new(-3, 0)));
+        assert!(score(Vec3::ZERO, IVec2::new(1, 0)) < score(Vec3::ZERO, IVec2::new(3, 0)));
+
+        // then in the direction of the movement
+        assert_eq!(score(Vec3::ZERO, IVec2::new(0, 3)), score(Vec3::ZERO, IVec2::new(0, -3)));
+        assert!(score(Vec3::new(0.0, 0.0, 50.0), IVec2::new(0, 3)) < score(Vec3::new(0.0, 0.0, 50.0), IVec2::new(0, -3)));
+    }
+
+    #[test]
+    fn world_info_errors()
+    {
+        let dir = std::env::temp_dir().join("rust_vox_world_info");
+        let _ = fs::remove_dir_all(&dir);
+
+        // a new world has no info yet
+        assert_eq!(WorldInfo::load(&dir).unwrap_err().kind(), io::ErrorKind::NotFound);
+
+        let info = WorldInfo{seed: 42, generator: "flat".to_string()};
+        info.save(&dir).unwrap();
+        assert_eq!(WorldInfo::load(&dir).unwrap(), info);
+
+        // a corrupt file is not mistaken for a new world
+        fs::write(dir.join("world.toml"), "seed = \"forty two\"").unwrap();
+        assert_eq!(WorldInfo::load(&dir).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}